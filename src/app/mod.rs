@@ -0,0 +1,1879 @@
+use sdl2::controller::{Axis, Button};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::control::{Autopilot, Controller};
+use crate::daily::{self, Challenge, Date};
+use crate::editor::Editor;
+use crate::ghost::{self, Ghost};
+use crate::highscore::HighScores;
+use crate::level::Level;
+use crate::mode::{GameMode, Mode};
+use crate::net::{Session, Transport};
+use crate::planet::{Body, BODIES};
+use crate::replay::Replay;
+use crate::simulation::{Controls, State, World, SPAWN_POSITION};
+use crate::vecmath::Vec2d;
+use crate::{draw, graphics, window_center, window_height, window_width};
+
+// Time the debris may fly around before the game over screen shows up
+const GAME_OVER_DELAY_MS: f32 = 2000.0;
+const MAX_SEED_DIGITS: usize = 19;
+const NOTICE_TIME_MS: f32 = 3000.0;
+const DEFAULT_LEVEL_PATH: &str = "level.txt";
+// Idle time on the title screen before the autopilot flies a demo
+const DEMO_DELAY_MS: f32 = 15000.0;
+// A finished network race keeps sending its last inputs this long, so the
+// other player gets to the end as well
+const NET_GRACE_MS: f32 = 2000.0;
+// Lander colours, player one first
+const PLAYER_COLORS: [Color; 2] = [Color::RGB(255, 255, 255), Color::RGB(0, 200, 255)];
+// Stick deflection ignored around the center, as part of the full range
+const STICK_DEADZONE: f32 = 0.25;
+const IDLE: Controls = Controls {
+    throttle: 0.0,
+    rotation: 0.0,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Screen {
+    Title,
+    MainMenu,
+    SeedEntry,
+    Options,
+    HighScores,
+    Editor,
+    InGame,
+    Paused,
+    LevelComplete,
+    GameOver,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MenuItem {
+    Play,
+    EnterSeed,
+    Options,
+    HighScores,
+    Editor,
+    Daily,
+    Mode,
+    TwoPlayers,
+    Quit,
+}
+
+const MAIN_MENU: [MenuItem; 9] = [
+    MenuItem::Play,
+    MenuItem::EnterSeed,
+    MenuItem::Options,
+    MenuItem::HighScores,
+    MenuItem::Editor,
+    MenuItem::Daily,
+    MenuItem::Mode,
+    MenuItem::TwoPlayers,
+    MenuItem::Quit,
+];
+
+pub struct Options {
+    pub show_hud: bool,
+    pub show_bbox: bool,
+}
+
+const OPTION_COUNT: usize = 3; // HUD, bounding box, back
+
+// The application level state machine. Every screen has its own input
+// handling and rendering, the World only exists while a level is played.
+pub struct App {
+    screen: Screen,
+    world: Option<World>,
+    menu_index: usize,
+    seed_input: String,
+    seed: u64,
+    level: u32,
+    body: Body,
+    last_score: u32,
+    last_rank: Option<usize>,
+    time_in_screen: f32, // in ms
+    options: Options,
+    config: Config,
+    start_seed: Option<u64>,
+    start_body: Body,
+    custom_level: Option<Level>,
+    level_path: Option<PathBuf>,
+    editor: Option<Editor>,
+    test_flight: bool, // the level comes from the editor and returns to it
+    autopilot: bool,   // every level is flown by the autopilot
+    pilot: Option<Box<dyn Controller>>,
+    demo: bool,                               // the pilot flies until a key is pressed
+    players: usize,                           // racing on one keyboard or with gamepads
+    mode: Mode,                               // picked in the menu
+    rules: Option<Box<dyn GameMode>>,         // of the run, kept from level to level
+    daily_date: Option<Date>,                 // practiced instead of the challenge of today
+    daily: Option<(Challenge, bool)>,         // flown and whether it counts
+    gamepads: Vec<u32>,                       // in the order first used, one per player
+    net: Option<Session<Box<dyn Transport>>>, // racing a player over the network
+    net_input: Controls,                      // of the own lander in a network race
+    record_path: Option<PathBuf>,
+    recording: Option<Replay>,
+    playback: Option<(Replay, usize)>, // replay and the next tick to play
+    ghost_dir: Option<PathBuf>,
+    ghost: Option<(Ghost, usize)>, // personal best and the ticks flown since the start
+    notice: Option<(String, f32)>, // text and remaining time in ms
+    high_scores: HighScores,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(mut high_scores: HighScores, config: Config) -> Self {
+        let notice = high_scores
+            .take_problem()
+            .map(|problem| (problem, NOTICE_TIME_MS));
+        App {
+            screen: Screen::Title,
+            world: None,
+            menu_index: 0,
+            seed_input: String::new(),
+            seed: 0,
+            level: 1,
+            body: Body::Moon,
+            last_score: 0,
+            last_rank: None,
+            time_in_screen: 0.0,
+            options: Options {
+                show_hud: true,
+                show_bbox: false,
+            },
+            config,
+            start_seed: None,
+            start_body: Body::Moon,
+            custom_level: None,
+            level_path: None,
+            editor: None,
+            test_flight: false,
+            autopilot: false,
+            pilot: None,
+            demo: false,
+            players: 1,
+            mode: Mode::Classic,
+            rules: None,
+            daily_date: None,
+            daily: None,
+            gamepads: Vec::new(),
+            net: None,
+            net_input: IDLE,
+            record_path: None,
+            recording: None,
+            playback: None,
+            ghost_dir: None,
+            ghost: None,
+            notice,
+            high_scores,
+            quit: false,
+        }
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    // "Play" starts with this seed instead of a random one
+    pub fn set_start_seed(&mut self, seed: u64) {
+        self.start_seed = Some(seed);
+    }
+
+    // The first level is on this body, the following cycle through the rest
+    pub fn set_start_body(&mut self, body: Body) {
+        self.start_body = body;
+    }
+
+    // Every level flies this hand-made one instead of a generated map, the
+    // editor edits it and saves it back to the path
+    pub fn set_level(&mut self, level: Level, path: PathBuf) {
+        self.custom_level = Some(level);
+        self.level_path = Some(path);
+    }
+
+    // Levels started from the menu are flown by the rules of the mode
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    // The daily challenge is the one of the past date, as practice
+    pub fn set_daily_date(&mut self, date: Date) {
+        self.daily_date = Some(date);
+    }
+
+    // Flight keys are ignored, the autopilot lands every level
+    pub fn set_autopilot(&mut self, autopilot: bool) {
+        self.autopilot = autopilot;
+    }
+
+    // Every started level is recorded, the file holds the last one flown
+    pub fn record_to(&mut self, path: PathBuf) {
+        self.record_path = Some(path);
+    }
+
+    // Every generated level is raced against the quickest landing on it
+    // so far, which is kept in the directory
+    pub fn set_ghost_dir(&mut self, dir: PathBuf) {
+        self.ghost_dir = Some(dir);
+    }
+
+    // Takes effect immediately, except for recorded and replayed levels,
+    // the daily challenge and network races, those have to be flown with
+    // the config they started with.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        match self.world.as_mut() {
+            Some(_)
+                if self.recording.is_some()
+                    || self.playback.is_some()
+                    || self.daily.is_some()
+                    || self.net.is_some() =>
+            {
+                self.notify(String::from("Config applies from the next level"))
+            }
+            Some(world) => world.set_config(config),
+            None => (),
+        }
+    }
+
+    // Shows a short message on top of every screen
+    pub fn notify(&mut self, text: String) {
+        self.notice = Some((text, NOTICE_TIME_MS));
+    }
+
+    // Broken or unsaved high scores are told like failed ghost saves
+    fn report_high_scores(&mut self) {
+        if let Some(problem) = self.high_scores.take_problem() {
+            self.notify(problem);
+        }
+    }
+
+    // Flies the replay right away, flight keys are ignored until it ends
+    pub fn play(&mut self, replay: Replay) {
+        self.launch(replay.seed, 1, replay.config, replay.body);
+        self.playback = Some((replay, 0));
+    }
+
+    // Races the other player of the session on the seed, body and config
+    // both agreed on. Keys and gamepads all steer the own lander.
+    pub fn race_online(
+        &mut self,
+        session: Session<Box<dyn Transport>>,
+        seed: u64,
+        body: Body,
+        config: Config,
+    ) {
+        self.players = 2;
+        self.recording = None;
+        self.net = Some(session);
+        self.net_input = IDLE;
+        self.launch(seed, 1, config, body);
+    }
+
+    fn leave_race(&mut self) {
+        self.net = None;
+        self.world = None;
+        self.switch_to(Screen::MainMenu);
+    }
+
+    fn switch_to(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.menu_index = 0;
+        self.time_in_screen = 0.0;
+    }
+
+    fn start_level(&mut self, seed: u64, level: u32) {
+        // A run keeps its rules from level to level, races are classic
+        let mut rules = match self.rules.take() {
+            Some(rules) if level > 1 => rules,
+            _ if self.players > 1 => Mode::Classic.create(),
+            _ => self.mode.create(),
+        };
+        match self.custom_level.as_ref() {
+            Some(custom) => {
+                let world = World::from_level(custom, self.config);
+                self.body = custom.body;
+                self.enter_level(seed, level, world);
+            }
+            None => self.launch(
+                seed,
+                level,
+                self.config,
+                Body::for_level(self.start_body, level),
+            ),
+        }
+        // Replays and ghosts only know the classic rules
+        if !rules.ranked() {
+            self.recording = None;
+            self.ghost = None;
+        }
+        if let Some(world) = self.world.as_mut() {
+            if self.ghost.is_none() {
+                world.set_ghost(None);
+            }
+            rules.start(world, level);
+        }
+        self.rules = Some(rules);
+    }
+
+    // Flies the challenge of the date. The one of today counts for the
+    // results of the day and has only a few attempts, others are practice.
+    fn start_daily(&mut self, date: Date) {
+        let counts = self.daily_date.is_none() && !self.autopilot;
+        if counts {
+            if self
+                .high_scores
+                .daily(date)
+                .is_some_and(|entry| entry.attempts >= daily::ATTEMPTS)
+            {
+                self.notify(String::from("No attempts left today, come back tomorrow"));
+                return;
+            }
+            self.high_scores.add_daily_attempt(date);
+            self.report_high_scores();
+        }
+        let challenge = Challenge::for_date(date);
+        self.players = 1;
+        self.launch(challenge.seed, 1, challenge.config(), challenge.body);
+        self.daily = Some((challenge, counts));
+    }
+
+    fn launch(&mut self, seed: u64, level: u32, config: Config, body: Body) {
+        self.body = body;
+        // Replays only hold the controls of a single lander
+        if (self.record_path.is_some() || self.ghost_dir.is_some()) && self.players == 1 {
+            self.recording = Some(Replay::new(seed, config, body));
+        }
+        self.enter_level(
+            seed,
+            level,
+            World::new(window_width, window_height, seed, config, body),
+        );
+        let ghost = match self.ghost_dir.as_ref() {
+            Some(dir) if self.players == 1 => {
+                Ghost::load(&ghost::path_for(dir, seed, body, &config)).ok()
+            }
+            _ => None,
+        };
+        if let (Some(ghost), Some(world)) = (ghost.as_ref(), self.world.as_mut()) {
+            world.set_ghost(Some(ghost.pose_at(0)));
+        }
+        self.ghost = ghost.map(|ghost| (ghost, 0));
+    }
+
+    fn enter_level(&mut self, seed: u64, level: u32, mut world: World) {
+        self.seed = seed;
+        self.level = level;
+        self.playback = None;
+        self.ghost = None;
+        self.rules = None;
+        self.daily = None;
+        self.test_flight = false;
+        self.demo = false;
+        self.pilot = match self.autopilot {
+            true => Some(Box::new(Autopilot::new())),
+            false => None,
+        };
+        world.set_show_hud(self.options.show_hud);
+        world.set_show_bbox(self.options.show_bbox);
+        // The second player starts mirrored on the other side
+        for player in 1..self.players {
+            let spawn = Vec2d::new(window_width as f32 - SPAWN_POSITION.x, SPAWN_POSITION.y);
+            world.add_lander(spawn, PLAYER_COLORS[player % PLAYER_COLORS.len()]);
+        }
+        self.world = Some(world);
+        self.switch_to(Screen::InGame);
+    }
+
+    // A random level on a random body, flown by the autopilot
+    fn start_demo(&mut self) {
+        let seed: u64 = rand::random();
+        let body = BODIES[(seed % BODIES.len() as u64) as usize];
+        self.body = body;
+        self.players = 1;
+        self.recording = None;
+        self.enter_level(
+            seed,
+            1,
+            World::new(window_width, window_height, seed, self.config, body),
+        );
+        self.pilot = Some(Box::new(Autopilot::new()));
+        self.demo = true;
+    }
+
+    fn end_demo(&mut self) {
+        self.world = None;
+        self.pilot = None;
+        self.demo = false;
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Event::Quit { .. } = event {
+            self.quit = true;
+            return;
+        }
+        match self.screen {
+            Screen::Title => self.title_input(event),
+            Screen::MainMenu => self.main_menu_input(event),
+            Screen::SeedEntry => self.seed_entry_input(event),
+            Screen::Options => self.options_input(event),
+            Screen::HighScores => self.high_scores_input(event),
+            Screen::Editor => self.editor_input(event),
+            Screen::InGame => self.in_game_input(event),
+            Screen::Paused => self.paused_input(event),
+            Screen::LevelComplete => self.level_complete_input(event),
+            Screen::GameOver => self.game_over_input(event),
+        }
+    }
+
+    pub fn tick(&mut self, time_in_ms: f32, tick_resolution_in_ms: f32) {
+        self.time_in_screen += time_in_ms;
+        if let Some((_, remaining)) = self.notice.as_mut() {
+            *remaining -= time_in_ms;
+            if *remaining <= 0.0 {
+                self.notice = None;
+            }
+        }
+        if self.screen == Screen::Title && self.time_in_screen >= DEMO_DELAY_MS {
+            self.start_demo();
+        }
+        // The other player does not wait for a pause or a finished race
+        let online = self.net.is_some()
+            && match self.screen {
+                Screen::Paused => true,
+                Screen::LevelComplete | Screen::GameOver => self.time_in_screen < NET_GRACE_MS,
+                _ => false,
+            };
+        if self.screen != Screen::InGame && !online {
+            return;
+        }
+        let world = match self.world.as_mut() {
+            Some(world) => world,
+            None => return,
+        };
+        if let Some(net) = self.net.as_mut() {
+            if let Some(pilot) = self.pilot.as_mut() {
+                self.net_input = pilot.control(
+                    &world.observe_lander(net.local_player()),
+                    time_in_ms / 1000.0,
+                );
+            }
+            // The session ticks the World once the input of both is there
+            if let Err(e) = net.step(world, self.net_input) {
+                // After the race the result stays on screen
+                if matches!(self.screen, Screen::InGame | Screen::Paused) {
+                    self.leave_race();
+                    self.notify(e);
+                }
+                return;
+            }
+            if let Some(tick) = net.desync() {
+                self.notice = Some((
+                    format!("Out of sync with the other player since tick {}", tick),
+                    NOTICE_TIME_MS,
+                ));
+            }
+            if self.screen != Screen::InGame {
+                return;
+            }
+            // A crash or landing on predicted input may still be rolled back
+            if net.outcome() == State::Running {
+                return;
+            }
+        } else {
+            if let Some((replay, tick)) = self.playback.as_mut() {
+                world.apply_controls(replay.controls_at(*tick).unwrap_or(IDLE));
+                *tick += 1;
+            }
+            if let Some(pilot) = self.pilot.as_mut() {
+                world.apply_controls(pilot.control(&world.observe(), time_in_ms / 1000.0));
+            }
+            if let Some(recording) = self.recording.as_mut() {
+                recording.record(world.controls());
+            }
+            world.tick(time_in_ms, tick_resolution_in_ms);
+            if let Some(rules) = self.rules.as_mut() {
+                let touchdowns = world.take_touchdowns();
+                rules.judge(world, &touchdowns);
+            }
+            if let Some((ghost, tick)) = self.ghost.as_mut() {
+                *tick += 1;
+                world.set_ghost(Some(ghost.pose_at(*tick)));
+            }
+        }
+        if *world.state() != State::Running {
+            // Own landings become the ghost if they were quicker
+            let own = self.pilot.is_none() && self.playback.is_none();
+            if let (State::Won, true, Some(dir), Some(run)) = (
+                world.state(),
+                own,
+                self.ghost_dir.as_ref(),
+                self.recording.as_ref(),
+            ) {
+                match ghost::save_if_best(dir, run) {
+                    Ok(true) => {
+                        self.notice = Some((String::from("New personal best"), NOTICE_TIME_MS))
+                    }
+                    Ok(false) => (),
+                    Err(e) => {
+                        self.notice = Some((format!("Could not save ghost: {}", e), NOTICE_TIME_MS))
+                    }
+                }
+            }
+            if let Err(e) = save_recording(&mut self.recording, self.record_path.as_ref()) {
+                self.notice = Some((format!("Could not save replay: {}", e), NOTICE_TIME_MS));
+            }
+        }
+        if self.demo {
+            if *world.state() != State::Running && self.time_in_screen >= GAME_OVER_DELAY_MS {
+                self.end_demo();
+                self.switch_to(Screen::Title);
+            }
+            return;
+        }
+        if self.test_flight {
+            let outcome = match world.state() {
+                State::Running => return,
+                State::Won => format!("Landed, score {}", world.score()),
+                State::Lost => String::from("Crashed"),
+            };
+            if self.time_in_screen >= GAME_OVER_DELAY_MS {
+                self.back_to_editor();
+                self.notify(outcome);
+            }
+            return;
+        }
+        match world.state() {
+            State::Running => (),
+            State::Won => {
+                self.last_score = world.score();
+                if let Some((challenge, true)) = self.daily.as_ref() {
+                    self.high_scores
+                        .add_daily_score(challenge.date, self.last_score);
+                }
+                // High scores are kept per seed, hand-made levels, races,
+                // autopilot landings, most modes and the daily challenge
+                // have none
+                let ranked =
+                    self.rules.as_ref().is_none_or(|rules| rules.ranked()) && self.daily.is_none();
+                self.last_rank = match (&self.custom_level, &self.pilot, self.players, ranked) {
+                    (None, None, 1, true) => self.high_scores.add(self.last_score, self.seed),
+                    _ => None,
+                };
+                self.report_high_scores();
+                self.switch_to(Screen::LevelComplete);
+            }
+            State::Lost => {
+                if self.time_in_screen >= GAME_OVER_DELAY_MS {
+                    self.switch_to(Screen::GameOver);
+                }
+            }
+        }
+    }
+
+    fn title_input(&mut self, event: &Event) {
+        match key_down(event) {
+            Some(Keycode::Escape) => self.quit = true,
+            Some(_) => self.switch_to(Screen::MainMenu),
+            None => (),
+        }
+    }
+
+    fn main_menu_input(&mut self, event: &Event) {
+        match key_down(event) {
+            Some(Keycode::Up) => {
+                self.menu_index = (self.menu_index + MAIN_MENU.len() - 1) % MAIN_MENU.len()
+            }
+            Some(Keycode::Down) => self.menu_index = (self.menu_index + 1) % MAIN_MENU.len(),
+            Some(Keycode::Escape) => self.switch_to(Screen::Title),
+            Some(Keycode::Return) | Some(Keycode::Space) => match MAIN_MENU[self.menu_index] {
+                MenuItem::Play => {
+                    self.players = 1;
+                    self.start_level(self.start_seed.unwrap_or_else(rand::random), 1)
+                }
+                MenuItem::TwoPlayers => {
+                    self.players = 2;
+                    self.start_level(self.start_seed.unwrap_or_else(rand::random), 1)
+                }
+                MenuItem::EnterSeed => {
+                    self.players = 1;
+                    self.seed_input.clear();
+                    self.switch_to(Screen::SeedEntry);
+                }
+                MenuItem::Options => self.switch_to(Screen::Options),
+                MenuItem::HighScores => self.switch_to(Screen::HighScores),
+                MenuItem::Editor => self.open_editor(),
+                MenuItem::Daily => self.start_daily(self.daily_date.unwrap_or_else(Date::today)),
+                MenuItem::Mode => self.mode = self.mode.next(),
+                MenuItem::Quit => self.quit = true,
+            },
+            Some(Keycode::Left) | Some(Keycode::Right)
+                if MAIN_MENU[self.menu_index] == MenuItem::Mode =>
+            {
+                self.mode = self.mode.next()
+            }
+            _ => (),
+        }
+    }
+
+    fn seed_entry_input(&mut self, event: &Event) {
+        let key = match key_down(event) {
+            Some(key) => key,
+            None => return,
+        };
+        match key {
+            Keycode::Escape => self.switch_to(Screen::MainMenu),
+            Keycode::Backspace => {
+                self.seed_input.pop();
+            }
+            Keycode::Return | Keycode::KpEnter => {
+                if let Ok(seed) = self.seed_input.parse() {
+                    self.start_level(seed, 1);
+                }
+            }
+            _ => {
+                if let Some(digit) = digit(key) {
+                    if self.seed_input.len() < MAX_SEED_DIGITS {
+                        self.seed_input.push(digit);
+                    }
+                }
+            }
+        }
+    }
+
+    fn options_input(&mut self, event: &Event) {
+        match key_down(event) {
+            Some(Keycode::Up) => {
+                self.menu_index = (self.menu_index + OPTION_COUNT - 1) % OPTION_COUNT
+            }
+            Some(Keycode::Down) => self.menu_index = (self.menu_index + 1) % OPTION_COUNT,
+            Some(Keycode::Escape) => self.switch_to(Screen::MainMenu),
+            Some(Keycode::Return)
+            | Some(Keycode::Space)
+            | Some(Keycode::Left)
+            | Some(Keycode::Right) => match self.menu_index {
+                0 => self.options.show_hud = !self.options.show_hud,
+                1 => self.options.show_bbox = !self.options.show_bbox,
+                _ => self.switch_to(Screen::MainMenu),
+            },
+            _ => (),
+        }
+    }
+
+    fn high_scores_input(&mut self, event: &Event) {
+        if key_down(event).is_some() {
+            self.switch_to(Screen::MainMenu);
+        }
+    }
+
+    // Edits the level given on the command line, or starts a new one
+    fn open_editor(&mut self) {
+        if self.editor.is_none() {
+            let level = self
+                .custom_level
+                .clone()
+                .unwrap_or_else(Editor::empty_level);
+            let path = self
+                .level_path
+                .clone()
+                .unwrap_or(PathBuf::from(DEFAULT_LEVEL_PATH));
+            self.editor = Some(Editor::new(level, path, self.config.lander.starting_fuel));
+        }
+        self.switch_to(Screen::Editor);
+    }
+
+    fn editor_input(&mut self, event: &Event) {
+        let editor = match self.editor.as_mut() {
+            Some(editor) => editor,
+            None => return,
+        };
+        match key_down(event) {
+            Some(Keycode::Escape) => self.switch_to(Screen::MainMenu),
+            Some(Keycode::Return) | Some(Keycode::KpEnter) => {
+                let world = World::from_level(editor.level(), self.config);
+                self.body = editor.level().body;
+                self.recording = None;
+                self.enter_level(0, 1, world);
+                self.test_flight = true;
+            }
+            Some(Keycode::S) => {
+                let notice = match editor.save() {
+                    Ok(()) => format!("Saved {}", editor.path().display()),
+                    Err(e) => e,
+                };
+                self.notify(notice);
+            }
+            _ => editor.handle_event(event),
+        }
+    }
+
+    fn back_to_editor(&mut self) {
+        self.world = None;
+        self.test_flight = false;
+        self.switch_to(Screen::Editor);
+    }
+
+    fn in_game_input(&mut self, event: &Event) {
+        if self.demo {
+            if key_down(event).is_some() {
+                self.end_demo();
+                self.switch_to(Screen::MainMenu);
+            }
+            return;
+        }
+        if self.test_flight && key_down(event) == Some(Keycode::Escape) {
+            self.back_to_editor();
+            return;
+        }
+        if self.playback.is_some() || self.pilot.is_some() {
+            if let Some(Keycode::Escape) | Some(Keycode::P) = key_down(event) {
+                self.switch_to(Screen::Paused);
+            }
+            return;
+        }
+        if self.net.is_some() {
+            self.online_input(event);
+            return;
+        }
+        let world = match self.world.as_mut() {
+            Some(world) => world,
+            None => return,
+        };
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Escape | Keycode::P => self.switch_to(Screen::Paused),
+                Keycode::Space | Keycode::Up => world.thrust_toggle(true),
+                Keycode::Left => world.rotation_left_toggle(true),
+                Keycode::Right => world.rotation_right_toggle(true),
+                // The second player shares the keyboard
+                Keycode::W if self.players > 1 => world.set_throttle_of(1, 1.0),
+                Keycode::A if self.players > 1 => world.set_rotation_of(1, -1.0),
+                Keycode::D if self.players > 1 => world.set_rotation_of(1, 1.0),
+                _ => (),
+            },
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Space | Keycode::Up => world.thrust_toggle(false),
+                Keycode::Left => world.rotation_left_toggle(false),
+                Keycode::Right => world.rotation_right_toggle(false),
+                Keycode::W if self.players > 1 => world.set_throttle_of(1, 0.0),
+                Keycode::A | Keycode::D if self.players > 1 => world.set_rotation_of(1, 0.0),
+                _ => (),
+            },
+            Event::ControllerButtonDown {
+                button: Button::Start,
+                ..
+            } => self.switch_to(Screen::Paused),
+            Event::ControllerButtonDown {
+                which,
+                button: Button::A,
+                ..
+            } => {
+                if let Some(player) = gamepad_player(&mut self.gamepads, *which, world) {
+                    world.set_throttle_of(player, 1.0);
+                }
+            }
+            Event::ControllerButtonUp {
+                which,
+                button: Button::A,
+                ..
+            } => {
+                if let Some(player) = gamepad_player(&mut self.gamepads, *which, world) {
+                    world.set_throttle_of(player, 0.0);
+                }
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                if let Some(player) = gamepad_player(&mut self.gamepads, *which, world) {
+                    let value = *value as f32 / i16::MAX as f32;
+                    match axis {
+                        Axis::LeftX if value.abs() < STICK_DEADZONE => {
+                            world.set_rotation_of(player, 0.0)
+                        }
+                        Axis::LeftX => world.set_rotation_of(player, value),
+                        Axis::TriggerRight => world.set_throttle_of(player, value),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // In a network race the input goes through the session, never
+    // straight into the World, or it would differ from the other player's
+    fn online_input(&mut self, event: &Event) {
+        let input = &mut self.net_input;
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Escape | Keycode::P => self.switch_to(Screen::Paused),
+                Keycode::Space | Keycode::Up | Keycode::W => input.throttle = 1.0,
+                Keycode::Left | Keycode::A => input.rotation = -1.0,
+                Keycode::Right | Keycode::D => input.rotation = 1.0,
+                _ => (),
+            },
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Space | Keycode::Up | Keycode::W => input.throttle = 0.0,
+                Keycode::Left | Keycode::Right | Keycode::A | Keycode::D => input.rotation = 0.0,
+                _ => (),
+            },
+            Event::ControllerButtonDown {
+                button: Button::Start,
+                ..
+            } => self.switch_to(Screen::Paused),
+            Event::ControllerButtonDown {
+                button: Button::A, ..
+            } => input.throttle = 1.0,
+            Event::ControllerButtonUp {
+                button: Button::A, ..
+            } => input.throttle = 0.0,
+            Event::ControllerAxisMotion { axis, value, .. } => {
+                let value = *value as f32 / i16::MAX as f32;
+                match axis {
+                    Axis::LeftX if value.abs() < STICK_DEADZONE => input.rotation = 0.0,
+                    Axis::LeftX => input.rotation = value,
+                    Axis::TriggerRight => input.throttle = value.max(0.0),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn paused_input(&mut self, event: &Event) {
+        match key_down(event) {
+            Some(Keycode::Escape) | Some(Keycode::P) => {
+                // Keys may have been released while paused
+                if self.net.is_some() {
+                    self.net_input = IDLE;
+                } else if let Some(world) = self.world.as_mut() {
+                    for idx in 0..world.lander_count() {
+                        world.apply_controls_to(idx, IDLE);
+                    }
+                }
+                self.screen = Screen::InGame;
+            }
+            Some(Keycode::E) => self.export_map(),
+            Some(Keycode::Q) if self.test_flight => self.back_to_editor(),
+            Some(Keycode::Q) if self.net.is_some() => self.leave_race(),
+            Some(Keycode::Q) => {
+                self.recording = None;
+                self.playback = None;
+                self.world = None;
+                self.switch_to(Screen::MainMenu);
+            }
+            _ => (),
+        }
+    }
+
+    // Keeps the map of the running level as a level file
+    fn export_map(&mut self) {
+        let world = match self.world.as_ref() {
+            Some(world) => world,
+            None => return,
+        };
+        let path = PathBuf::from(format!("level-{}.txt", self.seed));
+        match Level::from_map(world.map(), self.body).save(&path) {
+            Ok(()) => self.notify(format!("Saved {}", path.display())),
+            Err(e) => self.notify(e),
+        }
+    }
+
+    fn level_complete_input(&mut self, event: &Event) {
+        if self.net.is_some() && self.time_in_screen < NET_GRACE_MS {
+            return;
+        }
+        // A network race and the daily challenge are a single level
+        if self.net.is_some() || self.daily.is_some() {
+            if let Some(Keycode::Return) | Some(Keycode::Space) | Some(Keycode::Escape) =
+                key_down(event)
+            {
+                self.leave_race();
+            }
+            return;
+        }
+        match key_down(event) {
+            Some(Keycode::Return) | Some(Keycode::Space) => {
+                self.start_level(self.seed.wrapping_add(1), self.level + 1)
+            }
+            Some(Keycode::Escape) => {
+                self.world = None;
+                self.switch_to(Screen::MainMenu);
+            }
+            _ => (),
+        }
+    }
+
+    fn may_retry(&self) -> bool {
+        match self.daily.as_ref() {
+            Some((challenge, true)) => self.daily_attempts(challenge.date) < daily::ATTEMPTS,
+            Some((_, false)) => true,
+            None => self.rules.as_ref().is_none_or(|rules| rules.retry()),
+        }
+    }
+
+    fn daily_attempts(&self, date: Date) -> u32 {
+        self.high_scores
+            .daily(date)
+            .map_or(0, |entry| entry.attempts)
+    }
+
+    fn game_over_input(&mut self, event: &Event) {
+        if self.net.is_some() && self.time_in_screen < NET_GRACE_MS {
+            return;
+        }
+        if self.net.is_some() || !self.may_retry() {
+            if let Some(Keycode::Return) | Some(Keycode::Space) | Some(Keycode::Escape) =
+                key_down(event)
+            {
+                self.leave_race();
+            }
+            return;
+        }
+        match key_down(event) {
+            Some(Keycode::Return) | Some(Keycode::Space) => match self.daily.as_ref() {
+                Some((challenge, _)) => self.start_daily(challenge.date),
+                None => self.start_level(self.seed, self.level),
+            },
+            Some(Keycode::Escape) => {
+                self.world = None;
+                self.switch_to(Screen::MainMenu);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn render(&mut self, canvas: &mut Canvas<Window>) {
+        self.render_screen(canvas);
+        if let Some((text, _)) = self.notice.as_ref() {
+            render_line(
+                canvas,
+                text,
+                16,
+                window_center.y as i32 - 30,
+                Color::RGB(255, 255, 0),
+            );
+        }
+    }
+
+    fn render_screen(&mut self, canvas: &mut Canvas<Window>) {
+        match self.screen {
+            Screen::Title => self.render_title(canvas),
+            Screen::MainMenu => self.render_main_menu(canvas),
+            Screen::SeedEntry => self.render_seed_entry(canvas),
+            Screen::Options => self.render_options(canvas),
+            Screen::HighScores => self.render_high_scores(canvas),
+            Screen::Editor => {
+                if let Some(editor) = self.editor.as_ref() {
+                    editor.render(canvas);
+                }
+            }
+            Screen::InGame => {
+                self.render_world(canvas);
+                if self.demo && blink(self.time_in_screen) {
+                    render_line(
+                        canvas,
+                        "DEMO - press any key",
+                        20,
+                        -120,
+                        Color::RGB(0, 255, 0),
+                    );
+                }
+                self.render_ghost_delta(canvas);
+                self.render_mode_hud(canvas);
+                self.render_daily_hud(canvas);
+            }
+            Screen::Paused => {
+                self.render_world(canvas);
+                self.render_mode_hud(canvas);
+                render_line(canvas, "PAUSED", 60, 0, Color::RGB(255, 255, 0));
+                render_line(
+                    canvas,
+                    "P: resume   E: export map   Q: quit to menu",
+                    16,
+                    60,
+                    Color::RGB(255, 255, 255),
+                );
+                if self.net.is_some() {
+                    render_line(canvas, "the race goes on", 16, 90, Color::RGB(255, 255, 0));
+                }
+            }
+            Screen::LevelComplete => {
+                self.render_world(canvas);
+                graphics::renderWonText(canvas);
+                match self.world.as_ref().filter(|world| world.lander_count() > 1) {
+                    Some(world) => render_race_results(canvas, world),
+                    None => {
+                        let score = format!(
+                            "Level {} - {} - Score {}",
+                            self.level,
+                            self.body.name(),
+                            self.last_score
+                        );
+                        render_line(canvas, &score, 20, 60, Color::RGB(255, 255, 255));
+                    }
+                }
+                if let Some(rank) = self.last_rank {
+                    let rank = format!("New high score, rank {}", rank + 1);
+                    render_line(canvas, &rank, 16, 90, Color::RGB(255, 255, 0));
+                }
+                self.render_daily_hud(canvas);
+                let single = self.net.is_some() || self.daily.is_some();
+                let keys = if single {
+                    "ENTER: menu"
+                } else {
+                    "ENTER: next level   ESC: menu"
+                };
+                render_line(canvas, keys, 16, 120, Color::RGB(255, 255, 255));
+            }
+            Screen::GameOver => {
+                self.render_world(canvas);
+                graphics::renderGameOver(canvas);
+                self.render_daily_hud(canvas);
+                let keys = if self.net.is_some() || !self.may_retry() {
+                    "ENTER: menu"
+                } else {
+                    "ENTER: retry   ESC: menu"
+                };
+                render_line(canvas, keys, 16, 60, Color::RGB(255, 255, 255));
+            }
+        }
+    }
+
+    fn render_world(&mut self, canvas: &mut Canvas<Window>) {
+        if let Some(world) = self.world.as_mut() {
+            world.render(canvas);
+        }
+    }
+
+    fn render_mode_hud(&self, canvas: &mut Canvas<Window>) {
+        if let (Some(rules), Some(world)) = (self.rules.as_ref(), self.world.as_ref()) {
+            for (idx, line) in rules.hud(world).iter().enumerate() {
+                render_line(
+                    canvas,
+                    line,
+                    16,
+                    40 + 20 * idx as i32 - window_center.y as i32,
+                    Color::RGB(255, 255, 255),
+                );
+            }
+        }
+    }
+
+    // What the challenge of the day asks for and how it went so far
+    fn render_daily_hud(&self, canvas: &mut Canvas<Window>) {
+        let Some((challenge, counts)) = self.daily.as_ref() else {
+            return;
+        };
+        let mut text = format!(
+            "Daily {} - {} - fuel {:.1} s - wind {:+.1}",
+            challenge.date,
+            challenge.body.name(),
+            challenge.fuel,
+            challenge.wind
+        );
+        match (counts, self.high_scores.daily(challenge.date)) {
+            (true, Some(entry)) => {
+                text += &format!(
+                    " - attempt {} of {} - best {}",
+                    entry.attempts,
+                    daily::ATTEMPTS,
+                    entry.score
+                )
+            }
+            (true, None) => (),
+            (false, _) => text += " - practice",
+        }
+        render_line(
+            canvas,
+            &text,
+            16,
+            40 - window_center.y as i32,
+            Color::RGB(255, 255, 255),
+        );
+    }
+
+    // Green while ahead of the ghost, red while behind it
+    fn render_ghost_delta(&self, canvas: &mut Canvas<Window>) {
+        let (Some((ghost, tick)), Some(world)) = (self.ghost.as_ref(), self.world.as_ref()) else {
+            return;
+        };
+        if world.lander_state(0) != State::Running {
+            return;
+        }
+        let delta = ghost.delta_at(*tick, world.pose_of(0).position);
+        let color = if delta <= 0.0 {
+            Color::RGB(0, 255, 0)
+        } else {
+            Color::RGB(255, 0, 0)
+        };
+        let text = format!("Ghost {:+.1} s", delta);
+        render_line(canvas, &text, 16, 20 - window_center.y as i32, color);
+    }
+
+    fn render_title(&self, canvas: &mut Canvas<Window>) {
+        render_line(canvas, "LUNAR LANDER", 60, -60, Color::RGB(255, 255, 255));
+        if blink(self.time_in_screen) {
+            render_line(canvas, "press any key", 20, 40, Color::RGB(0, 255, 0));
+        }
+    }
+
+    fn render_main_menu(&self, canvas: &mut Canvas<Window>) {
+        render_line(canvas, "LUNAR LANDER", 40, -150, Color::RGB(255, 255, 255));
+        let mode = format!("Mode: {}", self.mode.title());
+        let daily = match self.daily_date {
+            Some(date) => format!("Daily challenge {} (practice)", date),
+            None => String::from("Daily challenge"),
+        };
+        for (idx, item) in MAIN_MENU.iter().enumerate() {
+            let label = match item {
+                MenuItem::Play => "Play",
+                MenuItem::EnterSeed => "Enter seed",
+                MenuItem::Options => "Options",
+                MenuItem::HighScores => "High scores",
+                MenuItem::Editor => "Level editor",
+                MenuItem::Daily => daily.as_str(),
+                MenuItem::Mode => mode.as_str(),
+                MenuItem::TwoPlayers => "Two players",
+                MenuItem::Quit => "Quit",
+            };
+            render_menu_item(canvas, label, idx, idx == self.menu_index);
+        }
+    }
+
+    fn render_seed_entry(&self, canvas: &mut Canvas<Window>) {
+        render_line(canvas, "ENTER SEED", 40, -100, Color::RGB(255, 255, 255));
+        let cursor = if blink(self.time_in_screen) { "_" } else { " " };
+        let input = format!("{}{}", self.seed_input, cursor);
+        render_line(canvas, &input, 30, 0, Color::RGB(0, 255, 0));
+        render_line(
+            canvas,
+            "ENTER: play   ESC: back",
+            16,
+            80,
+            Color::RGB(255, 255, 255),
+        );
+    }
+
+    fn render_options(&self, canvas: &mut Canvas<Window>) {
+        render_line(canvas, "OPTIONS", 40, -150, Color::RGB(255, 255, 255));
+        let labels = [
+            format!("HUD: {}", on_off(self.options.show_hud)),
+            format!("Bounding box: {}", on_off(self.options.show_bbox)),
+            String::from("Back"),
+        ];
+        for (idx, label) in labels.iter().enumerate() {
+            render_menu_item(canvas, label, idx, idx == self.menu_index);
+        }
+    }
+
+    fn render_high_scores(&self, canvas: &mut Canvas<Window>) {
+        render_line(canvas, "HIGH SCORES", 40, -200, Color::RGB(255, 255, 255));
+        if self.high_scores.entries().is_empty() {
+            render_line(canvas, "no landings yet", 20, 0, Color::RGB(0, 255, 0));
+        }
+        for (idx, entry) in self.high_scores.entries().iter().enumerate() {
+            let line = format!("{:2}. {:6}  seed {}", idx + 1, entry.score, entry.seed);
+            render_line(
+                canvas,
+                &line,
+                20,
+                -130 + 30 * idx as i32,
+                Color::RGB(0, 255, 0),
+            );
+        }
+    }
+}
+
+// Writes a finished recording, only once per level
+fn save_recording(recording: &mut Option<Replay>, path: Option<&PathBuf>) -> Result<(), String> {
+    match (recording.take(), path) {
+        (Some(recording), Some(path)) => recording.save(path),
+        _ => Ok(()),
+    }
+}
+
+// The player a gamepad controls, gamepads join in the order they are
+// first used. None once there are more gamepads than landers.
+fn gamepad_player(gamepads: &mut Vec<u32>, which: u32, world: &World) -> Option<usize> {
+    let player = match gamepads.iter().position(|id| *id == which) {
+        Some(player) => player,
+        None => {
+            gamepads.push(which);
+            gamepads.len() - 1
+        }
+    };
+    (player < world.lander_count()).then_some(player)
+}
+
+// One line per player, in the colour of the lander
+fn render_race_results(canvas: &mut Canvas<Window>, world: &World) {
+    for idx in 0..world.lander_count() {
+        let result = match world.lander_state(idx) {
+            State::Won => format!("P{} landed, score {}", idx + 1, world.score_of(idx)),
+            _ => format!("P{} crashed", idx + 1),
+        };
+        let color = PLAYER_COLORS[idx % PLAYER_COLORS.len()];
+        render_line(canvas, &result, 20, 60 + 30 * idx as i32, color);
+    }
+}
+
+fn key_down(event: &Event) -> Option<Keycode> {
+    match event {
+        Event::KeyDown {
+            keycode: Some(keycode),
+            ..
+        } => Some(*keycode),
+        _ => None,
+    }
+}
+
+fn digit(key: Keycode) -> Option<char> {
+    let digit = match key {
+        Keycode::Num0 | Keycode::Kp0 => '0',
+        Keycode::Num1 | Keycode::Kp1 => '1',
+        Keycode::Num2 | Keycode::Kp2 => '2',
+        Keycode::Num3 | Keycode::Kp3 => '3',
+        Keycode::Num4 | Keycode::Kp4 => '4',
+        Keycode::Num5 | Keycode::Kp5 => '5',
+        Keycode::Num6 | Keycode::Kp6 => '6',
+        Keycode::Num7 | Keycode::Kp7 => '7',
+        Keycode::Num8 | Keycode::Kp8 => '8',
+        Keycode::Num9 | Keycode::Kp9 => '9',
+        _ => return None,
+    };
+    Some(digit)
+}
+
+// Blinks once per second
+fn blink(time_in_ms: f32) -> bool {
+    (time_in_ms as u32 / 500).is_multiple_of(2)
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+// Draws a line of text centered horizontally, offset vertically from the
+// center of the window
+fn render_line(canvas: &mut Canvas<Window>, text: &str, size: u16, y_offset: i32, color: Color) {
+    draw::draw_text_centered(
+        canvas,
+        text,
+        size,
+        Point::new(window_center.x as i32, window_center.y as i32 + y_offset),
+        color,
+    )
+    .unwrap();
+}
+
+fn render_menu_item(canvas: &mut Canvas<Window>, label: &str, idx: usize, selected: bool) {
+    let y_offset = -50 + 40 * idx as i32;
+    if selected {
+        let label = format!("> {} <", label);
+        render_line(canvas, &label, 24, y_offset, Color::RGB(255, 255, 0));
+    } else {
+        render_line(canvas, label, 24, y_offset, Color::RGB(255, 255, 255));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::controller::Axis;
+    use sdl2::event::Event;
+    use sdl2::keyboard::{Keycode, Mod};
+
+    use crate::app::{App, Screen, DEMO_DELAY_MS, NET_GRACE_MS};
+    use crate::config::{Config, Difficulty};
+    use crate::control::{Autopilot, Controller};
+    use crate::daily::{self, Challenge, Date};
+    use crate::highscore::HighScores;
+    use crate::mode::Mode;
+    use crate::net::{LossyTransport, Session, Sync, Transport, UdpTransport};
+    use crate::planet::Body;
+    use crate::replay::Replay;
+    use crate::simulation::State;
+
+    fn press(app: &mut App, keycode: Keycode) {
+        app.handle_event(&Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+        });
+    }
+
+    #[test]
+    fn title_leads_to_menu_and_play() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        assert_eq!(app.screen, Screen::Title);
+        press(&mut app, Keycode::A);
+        assert_eq!(app.screen, Screen::MainMenu);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+        assert!(app.world.is_some());
+    }
+
+    #[test]
+    fn broken_high_scores_are_told_on_the_title() {
+        let path =
+            std::env::temp_dir().join(format!("lunar_app_scores_{}.txt", std::process::id()));
+        std::fs::write(&path, "500 7\nbroken\n").unwrap();
+        let app = App::new(HighScores::load(&path), Config::default());
+        let (notice, _) = app.notice.as_ref().unwrap();
+        assert!(
+            notice.ends_with(":2: ignoring broken high score entry"),
+            "{}",
+            notice
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn menu_wraps_around_to_quit() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Up);
+        press(&mut app, Keycode::Return);
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn seed_entry_starts_seeded_level() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Down);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::SeedEntry);
+        for key in [
+            Keycode::Num4,
+            Keycode::X,
+            Keycode::Num2,
+            Keycode::Num3,
+            Keycode::Backspace,
+        ] {
+            press(&mut app, key);
+        }
+        assert_eq!(app.seed_input, "42");
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+        assert_eq!(app.seed, 42);
+    }
+
+    #[test]
+    fn empty_seed_is_not_accepted() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Down);
+        press(&mut app, Keycode::Return);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::SeedEntry);
+    }
+
+    #[test]
+    fn pause_freezes_the_game() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        press(&mut app, Keycode::P);
+        assert_eq!(app.screen, Screen::Paused);
+        app.tick(5000.0, 10.0);
+        assert_eq!(app.screen, Screen::Paused);
+        press(&mut app, Keycode::P);
+        assert_eq!(app.screen, Screen::InGame);
+        press(&mut app, Keycode::Escape);
+        press(&mut app, Keycode::Q);
+        assert_eq!(app.screen, Screen::MainMenu);
+        assert!(app.world.is_none());
+    }
+
+    #[test]
+    fn options_toggle() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Down);
+        press(&mut app, Keycode::Down);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::Options);
+        press(&mut app, Keycode::Return);
+        assert!(!app.options.show_hud);
+        press(&mut app, Keycode::Escape);
+        assert_eq!(app.screen, Screen::MainMenu);
+    }
+
+    #[test]
+    fn editor_test_flights_return_to_the_editor() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        for _ in 0..4 {
+            press(&mut app, Keycode::Down);
+        }
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::Editor);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+        // The lander falls onto the flat ground of the new level
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+            if app.screen != Screen::InGame {
+                break;
+            }
+        }
+        assert_eq!(app.screen, Screen::Editor);
+        assert!(app.notice.is_some());
+        press(&mut app, Keycode::Return);
+        press(&mut app, Keycode::Escape);
+        assert_eq!(app.screen, Screen::Editor);
+        assert!(app.world.is_none());
+    }
+
+    #[test]
+    fn idle_title_shows_a_demo() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        while app.time_in_screen < DEMO_DELAY_MS {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::InGame);
+        assert!(app.pilot.is_some());
+        press(&mut app, Keycode::Space);
+        assert_eq!(app.screen, Screen::MainMenu);
+        assert!(app.world.is_none());
+    }
+
+    #[test]
+    fn autopilot_lands_without_high_score() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.set_autopilot(true);
+        app.set_start_seed(1);
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        // flight keys do not disturb the autopilot
+        press(&mut app, Keycode::Space);
+        for _ in 0..5000 {
+            app.tick(50.0, 10.0);
+            if app.screen != Screen::InGame {
+                break;
+            }
+        }
+        assert_eq!(app.screen, Screen::LevelComplete);
+        assert!(app.last_score > 0);
+        assert!(app.last_rank.is_none());
+    }
+
+    #[test]
+    fn two_players_race_on_one_keyboard_and_gamepads() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Up);
+        press(&mut app, Keycode::Up);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+        assert!(app.recording.is_none());
+        let world = app.world.as_ref().unwrap();
+        assert_eq!(world.lander_count(), 2);
+
+        press(&mut app, Keycode::W);
+        press(&mut app, Keycode::Left);
+        let world = app.world.as_ref().unwrap();
+        assert_eq!(world.controls_of(1).throttle, 1.0);
+        assert_eq!(world.controls().throttle, 0.0);
+        assert_eq!(world.controls().rotation, -1.0);
+
+        // The first gamepad used flies player one, the next player two
+        for which in [7, 9, 11] {
+            app.handle_event(&Event::ControllerAxisMotion {
+                timestamp: 0,
+                which,
+                axis: Axis::LeftX,
+                value: i16::MAX,
+            });
+        }
+        let world = app.world.as_ref().unwrap();
+        assert_eq!(world.controls().rotation, 1.0);
+        assert_eq!(world.controls_of(1).rotation, 1.0);
+        assert_eq!(app.gamepads, vec![7, 9, 11]);
+
+        // Races keep no high scores, free fall crashes both landers
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::GameOver);
+        assert!(app.high_scores.entries().is_empty());
+    }
+
+    #[test]
+    fn modes_are_picked_in_the_menu_and_set_the_rules() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        for key in [Keycode::Up, Keycode::Up, Keycode::Up, Keycode::Right] {
+            press(&mut app, key);
+        }
+        assert_eq!(app.mode, Mode::TimeAttack);
+        for key in [Keycode::Down, Keycode::Down, Keycode::Down, Keycode::Return] {
+            press(&mut app, key);
+        }
+        assert_eq!(app.screen, Screen::InGame);
+        let world = app.world.as_ref().unwrap();
+        let hud = app.rules.as_ref().unwrap().hud(world);
+        assert!(hud[0].starts_with("Time attack"), "{:?}", hud);
+
+        // Hardcore has no retry, the crash ends the run
+        app.set_mode(Mode::Hardcore);
+        press(&mut app, Keycode::Escape);
+        press(&mut app, Keycode::Q);
+        press(&mut app, Keycode::Return);
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::GameOver);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::MainMenu);
+    }
+
+    #[test]
+    fn landings_become_the_ghost_of_the_seed() {
+        let dir = std::env::temp_dir().join(format!("lunar-app-ghosts-{}", std::process::id()));
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.set_ghost_dir(dir.clone());
+        app.set_start_seed(4);
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        assert!(app.ghost.is_none());
+
+        // Flown by hand, as far as the App knows
+        let mut pilot = Autopilot::new();
+        while app.screen == Screen::InGame {
+            let world = app.world.as_mut().unwrap();
+            let controls = pilot.control(&world.observe(), 0.05);
+            world.apply_controls(controls);
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::LevelComplete);
+        assert_eq!(app.notice.as_ref().unwrap().0, "New personal best");
+
+        press(&mut app, Keycode::Escape);
+        press(&mut app, Keycode::Return);
+        let (_, tick) = app.ghost.as_ref().unwrap();
+        assert_eq!(*tick, 0);
+        app.tick(50.0, 10.0);
+        assert_eq!(app.ghost.as_ref().unwrap().1, 1);
+
+        // The ghost flew other physics than an easy level
+        press(&mut app, Keycode::Escape);
+        press(&mut app, Keycode::Q);
+        app.set_config(Difficulty::Easy.apply(Config::default()));
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+        assert!(app.ghost.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn network_races_steer_the_own_lander_through_the_session() {
+        let host_transport = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host_transport.local_addr().unwrap().to_string();
+        let joiner_transport = UdpTransport::connect(&address).unwrap();
+        let sync = Sync::Lockstep { delay: 2 };
+        let mut host = App::new(HighScores::new(), Config::default());
+        let mut joiner = App::new(HighScores::new(), Config::default());
+        host.race_online(
+            Session::new(Box::new(host_transport), 0, sync),
+            5,
+            Body::Mars,
+            Config::default(),
+        );
+        joiner.race_online(
+            Session::new(Box::new(joiner_transport), 1, sync),
+            5,
+            Body::Mars,
+            Config::default(),
+        );
+        assert_eq!(joiner.world.as_ref().unwrap().lander_count(), 2);
+
+        // Pausing does not stop the race
+        press(&mut joiner, Keycode::Up);
+        press(&mut host, Keycode::P);
+        assert_eq!(host.screen, Screen::Paused);
+        for _ in 0..100 {
+            host.tick(50.0, 10.0);
+            joiner.tick(50.0, 10.0);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let (host_net, joiner_net) = (host.net.as_ref().unwrap(), joiner.net.as_ref().unwrap());
+        assert!(host_net.confirmed() > 20 && joiner_net.confirmed() > 20);
+        assert_eq!(host_net.hash_at(20), joiner_net.hash_at(20));
+        assert_eq!(host_net.desync(), None);
+        let world = host.world.as_ref().unwrap();
+        assert_eq!(world.controls_of(1).throttle, 1.0);
+        assert_eq!(world.controls().throttle, 0.0);
+
+        // Retuning one peer mid race would drive the Worlds apart
+        let mut config = Config::default();
+        config.physics.gravity = 0.0;
+        host.set_config(config);
+        assert_eq!(
+            host.notice.as_ref().unwrap().0,
+            "Config applies from the next level"
+        );
+
+        press(&mut host, Keycode::Q);
+        assert_eq!(host.screen, Screen::MainMenu);
+        assert!(host.net.is_none() && host.world.is_none());
+    }
+
+    // Both landers fall to the ground while the host keeps guessing the
+    // joiner's throttle wrong, so the crash first happens on predicted input
+    #[test]
+    fn network_races_end_on_the_confirmed_result() {
+        let host_transport = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host_transport.local_addr().unwrap().to_string();
+        let joiner_transport = UdpTransport::connect(&address).unwrap();
+        let latency = std::time::Duration::from_millis(30);
+        let lossy = |transport: UdpTransport, seed| -> Box<dyn Transport> {
+            Box::new(LossyTransport::new(
+                transport,
+                0.3,
+                latency,
+                latency / 2,
+                seed,
+            ))
+        };
+        let sync = Sync::Rollback {
+            delay: 1,
+            max_rollback: 8,
+        };
+        let mut host = App::new(HighScores::new(), Config::default());
+        let mut joiner = App::new(HighScores::new(), Config::default());
+        host.race_online(
+            Session::new(lossy(host_transport, 1), 0, sync),
+            5,
+            Body::Mars,
+            Config::default(),
+        );
+        joiner.race_online(
+            Session::new(lossy(joiner_transport, 2), 1, sync),
+            5,
+            Body::Mars,
+            Config::default(),
+        );
+
+        let mut steps = 0;
+        while [&host, &joiner]
+            .iter()
+            .any(|app| app.screen == Screen::InGame || app.time_in_screen < NET_GRACE_MS)
+        {
+            joiner.net_input.throttle = if steps % 7 == 0 { 0.5 } else { 0.0 };
+            host.tick(50.0, 10.0);
+            joiner.tick(50.0, 10.0);
+            for app in [&host, &joiner] {
+                if app.screen != Screen::InGame {
+                    assert_ne!(app.net.as_ref().unwrap().outcome(), State::Running);
+                }
+            }
+            steps += 1;
+            assert!(steps < 2000, "the race did not end");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        for app in [&host, &joiner] {
+            assert_eq!(app.screen, Screen::GameOver);
+            assert!(app.notice.is_none());
+            assert_eq!(app.net.as_ref().unwrap().outcome(), State::Lost);
+        }
+        let (host_net, joiner_net) = (host.net.as_ref().unwrap(), joiner.net.as_ref().unwrap());
+        let tick = host_net.confirmed().min(joiner_net.confirmed()) - 1;
+        assert_eq!(host_net.hash_at(tick), joiner_net.hash_at(tick));
+        assert_eq!(host_net.desync(), None);
+
+        // Keys only count once the other player had time to finish
+        press(&mut host, Keycode::Return);
+        assert_eq!(host.screen, Screen::MainMenu);
+    }
+
+    #[test]
+    fn daily_challenges_count_a_few_attempts_and_practice_is_free() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        let today = Date::today();
+        press(&mut app, Keycode::A);
+        for attempt in 1..=daily::ATTEMPTS {
+            for _ in 0..5 {
+                press(&mut app, Keycode::Down);
+            }
+            press(&mut app, Keycode::Return);
+            assert_eq!(app.screen, Screen::InGame);
+            assert_eq!(app.seed, Challenge::for_date(today).seed);
+            assert_eq!(app.high_scores.daily(today).unwrap().attempts, attempt);
+            // The lander falls, whether it crashes or lands the attempt is used
+            while app.screen == Screen::InGame {
+                app.tick(50.0, 10.0);
+            }
+            press(&mut app, Keycode::Escape);
+            assert_eq!(app.screen, Screen::MainMenu);
+        }
+        for _ in 0..5 {
+            press(&mut app, Keycode::Down);
+        }
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::MainMenu);
+        assert_eq!(
+            app.high_scores.daily(today).unwrap().attempts,
+            daily::ATTEMPTS
+        );
+
+        // Still on the challenge in the menu
+        let past = Date::parse("2024-02-29").unwrap();
+        app.set_daily_date(past);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+        assert_eq!(app.daily, Some((Challenge::for_date(past), false)));
+        assert_eq!(app.high_scores.daily(past), None);
+        assert!(app.may_retry());
+    }
+
+    #[test]
+    fn crash_ends_in_game_over() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        // Free fall from the start position always crashes
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::GameOver);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.screen, Screen::InGame);
+    }
+
+    #[test]
+    fn start_seed_is_used_for_play() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.set_start_seed(99);
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.seed, 99);
+    }
+
+    #[test]
+    fn playback_repeats_the_recording() {
+        let dir = std::env::temp_dir();
+        let recorded = dir.join(format!("lunar_replay_a_{}.txt", std::process::id()));
+        let replayed = dir.join(format!("lunar_replay_b_{}.txt", std::process::id()));
+
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.record_to(recorded.clone());
+        app.set_start_seed(5);
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        press(&mut app, Keycode::Space);
+        for idx in 0..2000 {
+            if idx == 30 {
+                app.handle_event(&Event::KeyUp {
+                    timestamp: 0,
+                    window_id: 0,
+                    keycode: Some(Keycode::Space),
+                    scancode: None,
+                    keymod: Mod::NOMOD,
+                    repeat: false,
+                });
+                press(&mut app, Keycode::Left);
+            }
+            app.tick(50.0, 10.0);
+        }
+        let replay = Replay::load(&recorded).unwrap();
+        assert_eq!(replay.seed, 5);
+        assert!(replay.controls_at(0).is_some());
+
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.record_to(replayed.clone());
+        app.play(replay);
+        // flight keys do not disturb the playback
+        press(&mut app, Keycode::Space);
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(
+            std::fs::read_to_string(&recorded).unwrap(),
+            std::fs::read_to_string(&replayed).unwrap()
+        );
+        std::fs::remove_file(&recorded).unwrap();
+        std::fs::remove_file(&replayed).unwrap();
+    }
+
+    #[test]
+    fn config_changes_apply_to_the_running_level() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        let mut config = Config::default();
+        config.physics.gravity = 0.0;
+        app.set_config(config);
+        assert!(app.notice.is_none());
+        // Without gravity the lander floats forever
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::InGame);
+    }
+
+    #[test]
+    fn recorded_levels_keep_their_config() {
+        let path = std::env::temp_dir().join(format!("lunar_replay_c_{}.txt", std::process::id()));
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.record_to(path.clone());
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        let mut config = Config::default();
+        config.physics.gravity = 0.0;
+        app.set_config(config);
+        assert!(app.notice.is_some());
+        for _ in 0..2000 {
+            app.tick(50.0, 10.0);
+        }
+        assert_eq!(app.screen, Screen::GameOver);
+        assert!(app.notice.is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn levels_cycle_through_the_bodies() {
+        let mut app = App::new(HighScores::new(), Config::default());
+        app.set_start_body(Body::Titan);
+        press(&mut app, Keycode::A);
+        press(&mut app, Keycode::Return);
+        assert_eq!(app.body, Body::Titan);
+        app.screen = Screen::LevelComplete;
+        press(&mut app, Keycode::Return);
+        assert_eq!((app.level, app.body), (2, Body::Moon));
+        press(&mut app, Keycode::P);
+        press(&mut app, Keycode::Q);
+        press(&mut app, Keycode::Return);
+        assert_eq!((app.level, app.body), (1, Body::Titan));
+    }
+}
@@ -0,0 +1,378 @@
+use std::path::PathBuf;
+
+use crate::cli::Args;
+use crate::config::{Config, Difficulty, DIFFICULTIES};
+use crate::control::{Autopilot, Controller, Script};
+use crate::level::fuel_to_reach;
+use crate::mainloop::{SIM_RATE_HZ, SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+use crate::planet::Body;
+use crate::replay::Replay;
+use crate::simulation::{State, World};
+use crate::{window_height, window_width};
+
+pub const HELP: &str = "\
+Usage: batch [OPTIONS]
+
+Flies many seeds without a window and prints statistics for balancing.
+
+Options:
+  --seeds <N>             Number of seeds per difficulty (default 100)
+  --first-seed <N>        Seed to start with (default 1)
+  --pilot <NAME>          autopilot or idle (default autopilot)
+  --script <FILE>         Fly the controls of a replay file on every seed
+  --difficulty <NAME>     easy, normal, hard or all (default all)
+  --body <NAME>           moon, mars, mercury, asteroid or titan (default moon)
+  --config <FILE>         Tuning config file (default built-in values)
+  -h, --help              Print this help
+";
+
+// A run gives up after five minutes of game time
+const MAX_TICKS: u32 = 5 * 60 * SIM_RATE_HZ;
+// Width of the touchdown speed histogram bars, in pixel per second
+const HISTOGRAM_STEP: f32 = 2.5;
+const HISTOGRAM_WIDTH: usize = 40;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pilot {
+    Autopilot,
+    Idle,
+    Script(PathBuf),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub seeds: u64,
+    pub first_seed: u64,
+    pub pilot: Pilot,
+    pub difficulties: Vec<Difficulty>,
+    pub body: Body,
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Options),
+    Help,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            seeds: 100,
+            first_seed: 1,
+            pilot: Pilot::Autopilot,
+            difficulties: DIFFICULTIES.to_vec(),
+            body: Body::Moon,
+            config: None,
+        }
+    }
+}
+
+// Parses the arguments without the program name
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut args = Args::new(args);
+
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--seeds" => options.seeds = args.parse(arg)?,
+            "--first-seed" => options.first_seed = args.parse(arg)?,
+            "--pilot" => {
+                options.pilot = match args.value(arg)? {
+                    "autopilot" => Pilot::Autopilot,
+                    "idle" => Pilot::Idle,
+                    name => return Err(format!("unknown pilot '{}', use autopilot or idle", name)),
+                }
+            }
+            "--script" => options.pilot = Pilot::Script(PathBuf::from(args.value(arg)?)),
+            "--difficulty" => {
+                options.difficulties = match args.value(arg)? {
+                    "all" => DIFFICULTIES.to_vec(),
+                    name => vec![Difficulty::from_name(name)
+                        .ok_or(format!("unknown difficulty '{}', use easy, normal, hard or all", name))?],
+                }
+            }
+            "--body" => {
+                let name = args.value(arg)?;
+                options.body = Body::from_name(name).ok_or(format!("unknown body '{}'", name))?;
+            }
+            "--config" => options.config = Some(PathBuf::from(args.value(arg)?)),
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+
+    if options.seeds == 0 {
+        return Err(String::from("--seeds must be greater than 0"));
+    }
+    Ok(Command::Run(options))
+}
+
+// How the flight on one seed went
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Run {
+    pub seed: u64,
+    pub state: State, // Running if it timed out
+    pub fuel_left: f32,
+    pub touchdown_speed: Option<f32>, // at the first ground contact
+    pub fuel_to_pad: Option<f32>,     // to the cheapest pad, None without pads
+}
+
+pub fn fly(seed: u64, config: Config, body: Body, pilot: &mut dyn Controller) -> Run {
+    let mut world = World::new(window_width, window_height, seed, config, body);
+    let start = world.observe();
+    let fuel_to_pad = start
+        .pads
+        .iter()
+        .map(|pad| fuel_to_reach(world.map(), start.position, start.velocity, pad, start.gravity, start.thrust))
+        .reduce(f32::min);
+
+    let mut touchdown_speed = None;
+    let mut ticks = 0;
+    while *world.state() == State::Running && ticks < MAX_TICKS {
+        let before = world.observe();
+        let speed = before.velocity.len();
+        let controls = pilot.control(&before, SIM_TICK_MS / 1000.0);
+        world.apply_controls(controls);
+        world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+        // A crash ends the game before the lander counts as grounded
+        if touchdown_speed.is_none() && (world.observe().grounded || *world.state() == State::Lost) {
+            touchdown_speed = Some(speed);
+        }
+        ticks += 1;
+    }
+    Run {
+        seed,
+        state: *world.state(),
+        fuel_left: world.observe().fuel.max(0.0),
+        touchdown_speed,
+        fuel_to_pad,
+    }
+}
+
+// Statistics over the runs of one difficulty
+pub struct Summary {
+    pub runs: Vec<Run>,
+    pub starting_fuel: f32,
+}
+
+impl Summary {
+    pub fn new(runs: Vec<Run>, starting_fuel: f32) -> Self {
+        Summary { runs, starting_fuel }
+    }
+
+    fn count(&self, state: State) -> usize {
+        self.runs.iter().filter(|r| r.state == state).count()
+    }
+
+    pub fn success_rate(&self) -> f32 {
+        self.count(State::Won) as f32 / self.runs.len().max(1) as f32
+    }
+
+    // Seeds without any pad the starting fuel could possibly reach
+    pub fn unreachable(&self) -> Vec<u64> {
+        self.runs
+            .iter()
+            .filter(|r| r.fuel_to_pad.is_none_or(|fuel| fuel > self.starting_fuel))
+            .map(|r| r.seed)
+            .collect()
+    }
+
+    pub fn touchdown_speeds(&self) -> Vec<f32> {
+        let mut speeds: Vec<f32> = self.runs.iter().filter_map(|r| r.touchdown_speed).collect();
+        speeds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        speeds
+    }
+
+    pub fn report(&self) -> String {
+        let total = self.runs.len();
+        let mut text = format!(
+            "  landed       {} of {} ({:.1}%), crashed {}, timed out {}\n",
+            self.count(State::Won),
+            total,
+            self.success_rate() * 100.0,
+            self.count(State::Lost),
+            self.count(State::Running)
+        );
+
+        let fuel: Vec<f32> = self.runs.iter().filter(|r| r.state == State::Won).map(|r| r.fuel_left).collect();
+        if !fuel.is_empty() {
+            text += &format!(
+                "  fuel left    mean {:.2} s, min {:.2} s, max {:.2} s of {:.2} s\n",
+                mean(&fuel),
+                fuel.iter().copied().fold(f32::MAX, f32::min),
+                fuel.iter().copied().fold(0.0, f32::max),
+                self.starting_fuel
+            );
+        }
+
+        let speeds = self.touchdown_speeds();
+        if !speeds.is_empty() {
+            text += &format!(
+                "  touchdown    mean {:.2}, median {:.2}, p90 {:.2}, max {:.2} px/s\n",
+                mean(&speeds),
+                percentile(&speeds, 0.5),
+                percentile(&speeds, 0.9),
+                speeds[speeds.len() - 1]
+            );
+            text += &histogram(&speeds);
+        }
+
+        let needed: Vec<f32> = self.runs.iter().filter_map(|r| r.fuel_to_pad).collect();
+        let unreachable = self.unreachable();
+        text += &format!(
+            "  reachable    {} of {} maps ({:.1}%), the cheapest pad needs {:.2} s of fuel on average\n",
+            total - unreachable.len(),
+            total,
+            (total - unreachable.len()) as f32 / total.max(1) as f32 * 100.0,
+            mean(&needed)
+        );
+        if !unreachable.is_empty() {
+            let seeds: Vec<String> = unreachable.iter().map(|s| s.to_string()).collect();
+            text += &format!("  unreachable  seeds {}\n", seeds.join(", "));
+        }
+        text
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+// Of sorted values
+fn percentile(values: &[f32], p: f32) -> f32 {
+    let idx = ((values.len() - 1) as f32 * p).round() as usize;
+    values[idx]
+}
+
+// A bar per HISTOGRAM_STEP of touchdown speed, of sorted speeds
+fn histogram(speeds: &[f32]) -> String {
+    let buckets = (speeds[speeds.len() - 1] / HISTOGRAM_STEP) as usize + 1;
+    let mut counts = vec![0; buckets];
+    for speed in speeds {
+        counts[(speed / HISTOGRAM_STEP) as usize] += 1;
+    }
+    let most = counts.iter().copied().max().unwrap_or(1);
+    let mut text = String::new();
+    for (idx, count) in counts.iter().enumerate() {
+        let from = idx as f32 * HISTOGRAM_STEP;
+        let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(most));
+        let line = format!("    {:5.1} - {:5.1} {:5} {}", from, from + HISTOGRAM_STEP, count, bar);
+        text += line.trim_end();
+        text.push('\n');
+    }
+    text
+}
+
+// Flies every seed on every difficulty and reports each difficulty
+pub fn run(options: &Options) -> Result<String, String> {
+    let config = match options.config.as_ref() {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    let script = match &options.pilot {
+        Pilot::Script(path) => Some(Replay::load(path)?),
+        _ => None,
+    };
+    let pilot_name = match &options.pilot {
+        Pilot::Autopilot => String::from("the autopilot"),
+        Pilot::Idle => String::from("no pilot"),
+        Pilot::Script(path) => path.display().to_string(),
+    };
+
+    let mut text = String::new();
+    for difficulty in options.difficulties.iter() {
+        let config = difficulty.apply(config);
+        let runs = (options.first_seed..options.first_seed + options.seeds)
+            .map(|seed| {
+                let mut pilot: Box<dyn Controller> = match script.as_ref() {
+                    Some(replay) => Box::new(Script::new(replay.controls().to_vec())),
+                    None if options.pilot == Pilot::Idle => Box::new(Script::new(Vec::new())),
+                    None => Box::new(Autopilot::new()),
+                };
+                fly(seed, config, options.body, pilot.as_mut())
+            })
+            .collect();
+        let summary = Summary::new(runs, config.lander.starting_fuel);
+        text += &format!(
+            "{}: {} seeds on {} with {}\n",
+            difficulty.name(),
+            options.seeds,
+            options.body.name(),
+            pilot_name
+        );
+        text += &summary.report();
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::*;
+
+    fn args(args: &[&str]) -> Result<Command, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        parse(&args)
+    }
+
+    fn run_at(seed: u64, state: State, touchdown_speed: f32, fuel_to_pad: Option<f32>) -> Run {
+        Run {
+            seed,
+            state,
+            fuel_left: 5.0,
+            touchdown_speed: Some(touchdown_speed),
+            fuel_to_pad,
+        }
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let options = match args(&["--seeds", "10", "--difficulty", "hard", "--pilot", "idle", "--body", "mars"]).unwrap() {
+            Command::Run(options) => options,
+            Command::Help => panic!(),
+        };
+        assert_eq!(options.seeds, 10);
+        assert_eq!(options.difficulties, vec![Difficulty::Hard]);
+        assert_eq!(options.pilot, Pilot::Idle);
+        assert_eq!(options.body, Body::Mars);
+        assert_eq!(args(&[]).unwrap(), Command::Run(Options::default()));
+        assert_eq!(args(&["--help"]).unwrap(), Command::Help);
+        assert_eq!(args(&["--pilot", "robot"]).unwrap_err(), "unknown pilot 'robot', use autopilot or idle");
+        assert_eq!(args(&["--seeds", "0"]).unwrap_err(), "--seeds must be greater than 0");
+        for option in ["--seeds", "--first-seed", "--pilot", "--script", "--difficulty", "--body", "--config"] {
+            assert!(HELP.contains(option), "{} is not documented", option);
+        }
+    }
+
+    #[test]
+    fn summary_counts_and_flags() {
+        let summary = Summary::new(
+            vec![
+                run_at(1, State::Won, 1.0, Some(2.0)),
+                run_at(2, State::Won, 0.5, Some(1.0)),
+                run_at(3, State::Lost, 9.0, Some(30.0)),
+                run_at(4, State::Running, 0.0, None),
+            ],
+            10.0,
+        );
+        assert_eq!(summary.success_rate(), 0.5);
+        assert_eq!(summary.unreachable(), vec![3, 4]);
+        assert_eq!(summary.touchdown_speeds(), vec![0.0, 0.5, 1.0, 9.0]);
+        let report = summary.report();
+        assert!(report.contains("landed       2 of 4 (50.0%), crashed 1, timed out 1"));
+        assert!(report.contains("unreachable  seeds 3, 4"));
+    }
+
+    #[test]
+    fn pilots_are_compared_on_the_same_seeds() {
+        let config = Config::default();
+        let landed = fly(1, config, Body::Moon, &mut Autopilot::new());
+        assert_eq!(landed.state, State::Won);
+        assert!(landed.touchdown_speed.unwrap() < config.lander.crash_speed);
+        assert!(landed.fuel_to_pad.unwrap() < config.lander.starting_fuel - landed.fuel_left);
+        let dropped = fly(1, config, Body::Moon, &mut Script::new(Vec::new()));
+        assert_eq!(dropped.state, State::Lost);
+        assert_eq!(dropped.fuel_left, config.lander.starting_fuel);
+        assert_eq!(dropped.fuel_to_pad, landed.fuel_to_pad);
+    }
+}
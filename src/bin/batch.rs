@@ -0,0 +1,19 @@
+use lunar::batch;
+
+// Balancing statistics, see batch::HELP
+pub fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match batch::parse(&args) {
+        Ok(batch::Command::Run(options)) => options,
+        Ok(batch::Command::Help) => {
+            print!("{}", batch::HELP);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, batch::HELP);
+            std::process::exit(2);
+        }
+    };
+    print!("{}", batch::run(&options)?);
+    Ok(())
+}
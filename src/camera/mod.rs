@@ -0,0 +1,123 @@
+use crate::vecmath::{TransformationMatrix, Vec2d};
+use crate::{window_center, window_height, window_width};
+
+// Closest the camera gets, in screen pixel per world pixel
+const MAX_ZOOM: f32 = 4.0;
+// Below this altitude the camera starts to zoom in, in pixel
+const ZOOM_ALTITUDE: f32 = 100.0;
+// Part of the way to the wanted zoom the camera goes per second
+const ZOOM_RATE: f32 = 2.0;
+
+// Looks at the world from above the lander. High up it shows the whole
+// world, towards the ground it zooms in on the lander, like the arcade
+// original. It never shows anything beside the world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    center: Vec2d,
+    zoom: f32,
+    world: Vec2d, // size of the world, in pixel
+}
+
+impl Camera {
+    pub fn new(world_width: f32, world_height: f32) -> Self {
+        let world = Vec2d::new(world_width.max(1.0), world_height.max(1.0));
+        let mut camera = Camera {
+            center: world / 2.0,
+            zoom: 1.0,
+            world,
+        };
+        camera.zoom = camera.min_zoom();
+        camera
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    // Wide worlds fit into the screen when zoomed out
+    fn min_zoom(&self) -> f32 {
+        (window_width as f32 / self.world.x)
+            .min(window_height as f32 / self.world.y)
+            .min(1.0)
+    }
+
+    // Eases towards the zoom for the altitude and keeps the target in view
+    pub fn follow(&mut self, target: Vec2d, altitude: f32, time_in_s: f32) {
+        let min_zoom = self.min_zoom();
+        let wanted = (ZOOM_ALTITUDE / altitude.max(1.0)).clamp(min_zoom, MAX_ZOOM);
+        self.zoom += (wanted - self.zoom) * (ZOOM_RATE * time_in_s).min(1.0);
+
+        let half = Vec2d::new(window_width as f32, window_height as f32) / (2.0 * self.zoom);
+        let clamp = |value: f32, half: f32, size: f32| {
+            if 2.0 * half >= size {
+                size / 2.0
+            } else {
+                value.clamp(half, size - half)
+            }
+        };
+        self.center = Vec2d::new(clamp(target.x, half.x, self.world.x), clamp(target.y, half.y, self.world.y));
+    }
+
+    // From world into screen pixels
+    pub fn transform(&self) -> TransformationMatrix {
+        TransformationMatrix::translation_v(window_center)
+            * TransformationMatrix::scale(self.zoom, self.zoom)
+            * TransformationMatrix::translation_v(self.center * -1.0)
+    }
+
+    // The world x seen from left to right
+    pub fn visible_x(&self) -> (f32, f32) {
+        let half = window_width as f32 / (2.0 * self.zoom);
+        (self.center.x - half, self.center.x + half)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::Camera;
+    use crate::vecmath::Vec2d;
+    use crate::{window_height, window_width};
+
+    fn settle(camera: &mut Camera, target: Vec2d, altitude: f32) {
+        for _ in 0..100 {
+            camera.follow(target, altitude, 0.1);
+        }
+    }
+
+    #[test]
+    fn high_up_the_whole_world_is_seen() {
+        let mut camera = Camera::new(window_width as f32, window_height as f32);
+        settle(&mut camera, Vec2d::new(100.0, 100.0), 300.0);
+        let p = Vec2d::new(123.0, 456.0);
+        let seen = camera.transform().transform(&p);
+        assert!((seen - p).len() < 1e-3);
+        assert_eq!(camera.visible_x(), (0.0, window_width as f32));
+    }
+
+    #[test]
+    fn zooms_in_close_to_the_ground() {
+        let mut camera = Camera::new(window_width as f32, window_height as f32);
+        let lander = Vec2d::new(400.0, 300.0);
+        settle(&mut camera, lander, 10.0);
+        assert!(camera.zoom() > 3.0);
+        let seen = camera.transform().transform(&lander);
+        assert!((seen - Vec2d::new(400.0, 300.0)).len() < 1e-3);
+    }
+
+    #[test]
+    fn never_looks_beside_the_world() {
+        let mut camera = Camera::new(window_width as f32, window_height as f32);
+        settle(&mut camera, Vec2d::new(0.0, 600.0), 10.0);
+        let (left, _) = camera.visible_x();
+        assert!(left.abs() < 1e-3);
+        let corner = camera.transform().transform(&Vec2d::new(0.0, 600.0));
+        assert!((corner - Vec2d::new(0.0, window_height as f32)).len() < 1e-3);
+    }
+
+    #[test]
+    fn wide_worlds_fit_the_screen() {
+        let camera = Camera::new(4.0 * window_width as f32, window_height as f32);
+        assert_eq!(camera.zoom(), 0.25);
+        assert_eq!(camera.visible_x(), (0.0, 4.0 * window_width as f32));
+    }
+}
@@ -0,0 +1,358 @@
+use std::path::PathBuf;
+
+use crate::mainloop::Pacing;
+use crate::planet::Body;
+use crate::config::{Config, Difficulty};
+use crate::daily::Date;
+use crate::mode::Mode;
+
+pub const HELP: &str = "\
+Usage: lunar [OPTIONS]
+
+Options:
+  --seed <N>              Terrain seed of the first level (random by default)
+  --width <PX>            Window width (default 800)
+  --height <PX>           Window height (default 600)
+  --fullscreen            Start in fullscreen mode
+  --config <FILE>         Tuning config file (default lunar.toml if present)
+  --print-config          Print the config in use, e.g. to start a new file
+  --difficulty <NAME>     easy, normal or hard (default normal)
+  --body <NAME>           moon, mars, mercury, asteroid or titan, the body
+                          of the first level (default moon)
+  --mode <NAME>           classic, time-attack, fuel, precision or hardcore,
+                          the rules of the levels (default classic)
+  --daily <YYYY-MM-DD>    Practice the daily challenge of a past date,
+                          without attempt limit or stored results
+  --fuel <SECONDS>        Starting fuel, overrides config and difficulty
+  --gravity <PX/S^2>      Gravity, overrides config and difficulty
+  --record <FILE>         Record the last flown level into a replay file
+  --play <FILE>           Play back a replay file
+  --level <FILE>          Fly a hand-made level file instead of generated maps
+  --autopilot             Let the autopilot fly every level
+  --headless              Simulate without a window and print the result
+  --host <PORT>           Wait for a second player to race over the network
+  --join <HOST:PORT>      Race the player hosting at the address
+  --net-delay <TICKS>     Ticks the own input takes to apply in a network
+                          race, set by the host (default 3)
+  --rollback <TICKS>      Run up to that many ticks ahead of late network
+                          input and correct them once it arrives, set by
+                          the host (default 0, wait for the input instead)
+  --net-loss <SHARE>      Drop that share of the sent packets, 0 to 1, to
+                          test network races
+  --net-latency <MS>      Delay the sent packets, to test network races
+  --assets <DIR>          Directory with the game assets (default assets)
+  --vsync                 Wait for vsync when presenting frames (default)
+  --fps <N>               Limit the frame rate to N frames per second
+  --uncapped              Render as many frames as possible
+  -h, --help              Print this help
+
+Keys:
+  F11, Alt+Enter          Toggle fullscreen
+  F3                      Show frame statistics
+";
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub seed: Option<u64>,
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub config: Option<PathBuf>,
+    pub print_config: bool,
+    pub difficulty: Difficulty,
+    pub body: Body,
+    pub mode: Mode,
+    pub daily: Option<Date>, // practice date of the daily challenge
+    pub fuel: Option<f32>,
+    pub gravity: Option<f32>,
+    pub record: Option<PathBuf>,
+    pub play: Option<PathBuf>,
+    pub level: Option<PathBuf>,
+    pub autopilot: bool,
+    pub headless: bool,
+    pub asset_dir: PathBuf,
+    pub pacing: Pacing,
+    pub host: Option<u16>,
+    pub join: Option<String>,
+    pub net_delay: u32,
+    pub rollback: u32,
+    pub net_loss: f32,
+    pub net_latency: u32, // in ms
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Box<Options>),
+    Help,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            seed: None,
+            width: crate::window_width,
+            height: crate::window_height,
+            fullscreen: false,
+            config: None,
+            print_config: false,
+            difficulty: Difficulty::Normal,
+            body: Body::Moon,
+            mode: Mode::Classic,
+            daily: None,
+            fuel: None,
+            gravity: None,
+            record: None,
+            play: None,
+            level: None,
+            autopilot: false,
+            headless: false,
+            asset_dir: PathBuf::from("assets"),
+            pacing: Pacing::VSync,
+            host: None,
+            join: None,
+            net_delay: 3,
+            rollback: 0,
+            net_loss: 0.0,
+            net_latency: 0,
+        }
+    }
+}
+
+impl Options {
+    // The loaded config adjusted by the difficulty preset, with the
+    // explicit overrides applied last
+    pub fn apply(&self, config: Config) -> Config {
+        let mut config = self.difficulty.apply(config);
+        if let Some(fuel) = self.fuel {
+            config.lander.starting_fuel = fuel;
+        }
+        if let Some(gravity) = self.gravity {
+            config.physics.gravity = gravity;
+        }
+        config
+    }
+}
+
+// Parses the arguments without the program name
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut args = Args::new(args);
+
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--seed" => options.seed = Some(args.parse(arg)?),
+            "--width" => options.width = parse_size(arg, args.value(arg)?)?,
+            "--height" => options.height = parse_size(arg, args.value(arg)?)?,
+            "--fullscreen" => options.fullscreen = true,
+            "--config" => options.config = Some(PathBuf::from(args.value(arg)?)),
+            "--print-config" => options.print_config = true,
+            "--difficulty" => {
+                let name = args.value(arg)?;
+                options.difficulty = Difficulty::from_name(name)
+                    .ok_or(format!("unknown difficulty '{}', use easy, normal or hard", name))?;
+            }
+            "--body" => {
+                let name = args.value(arg)?;
+                options.body = Body::from_name(name).ok_or(format!("unknown body '{}'", name))?;
+            }
+            "--mode" => {
+                let name = args.value(arg)?;
+                options.mode = Mode::from_name(name).ok_or(format!("unknown mode '{}'", name))?;
+            }
+            "--daily" => options.daily = Some(Date::parse(args.value(arg)?)?),
+            "--fuel" => options.fuel = Some(parse_positive(arg, args.value(arg)?)?),
+            "--gravity" => options.gravity = Some(parse_positive(arg, args.value(arg)?)?),
+            "--record" => options.record = Some(PathBuf::from(args.value(arg)?)),
+            "--play" => options.play = Some(PathBuf::from(args.value(arg)?)),
+            "--level" => options.level = Some(PathBuf::from(args.value(arg)?)),
+            "--autopilot" => options.autopilot = true,
+            "--headless" => options.headless = true,
+            "--assets" => options.asset_dir = PathBuf::from(args.value(arg)?),
+            "--vsync" => options.pacing = Pacing::VSync,
+            "--fps" => options.pacing = Pacing::Capped(parse_size(arg, args.value(arg)?)?),
+            "--uncapped" => options.pacing = Pacing::Uncapped,
+            "--host" => options.host = Some(args.parse(arg)?),
+            "--join" => options.join = Some(String::from(args.value(arg)?)),
+            "--net-delay" => options.net_delay = args.parse(arg)?,
+            "--rollback" => options.rollback = args.parse(arg)?,
+            "--net-loss" => {
+                options.net_loss = parse_positive(arg, args.value(arg)?)?;
+                if options.net_loss > 1.0 {
+                    return Err(String::from("--net-loss must be between 0 and 1"));
+                }
+            }
+            "--net-latency" => options.net_latency = args.parse(arg)?,
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+
+    // The challenge of today is flown from the menu, to count
+    if options.daily.is_some_and(|date| date >= Date::today()) {
+        return Err(String::from("--daily needs a date before today"));
+    }
+    if options.record.is_some() && options.play.is_some() {
+        return Err(String::from("--record and --play can not be combined"));
+    }
+    // Replays only know generated maps
+    if options.level.is_some() && (options.record.is_some() || options.play.is_some()) {
+        return Err(String::from("--level can not be combined with --record or --play"));
+    }
+    if options.autopilot && options.play.is_some() {
+        return Err(String::from("--autopilot and --play can not be combined"));
+    }
+    if options.host.is_some() && options.join.is_some() {
+        return Err(String::from("--host and --join can not be combined"));
+    }
+    // Network races fly generated maps with both players at the controls
+    let online = options.host.is_some() || options.join.is_some();
+    if online && (options.headless || options.record.is_some() || options.play.is_some() || options.level.is_some()) {
+        return Err(String::from("--host and --join can not be combined with --headless, --record, --play or --level"));
+    }
+    Ok(Command::Run(Box::new(options)))
+}
+
+// The arguments of a command line, options with their values
+pub(crate) struct Args<'a> {
+    args: std::slice::Iter<'a, String>,
+}
+
+impl<'a> Args<'a> {
+    pub(crate) fn new(args: &'a [String]) -> Self {
+        Args { args: args.iter() }
+    }
+
+    // The value following the option
+    pub(crate) fn value(&mut self, name: &str) -> Result<&'a str, String> {
+        self.next().ok_or(format!("{} needs a value", name))
+    }
+
+    pub(crate) fn parse<T: std::str::FromStr>(&mut self, name: &str) -> Result<T, String> {
+        parse_value(name, self.value(name)?)
+    }
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.args.next().map(|arg| arg.as_str())
+    }
+}
+
+pub(crate) fn parse_value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, name))
+}
+
+fn parse_size(name: &str, value: &str) -> Result<u32, String> {
+    match parse_value(name, value)? {
+        0 => Err(format!("{} must be greater than 0", name)),
+        size => Ok(size),
+    }
+}
+
+fn parse_positive(name: &str, value: &str) -> Result<f32, String> {
+    let v: f32 = parse_value(name, value)?;
+    if !v.is_finite() || v < 0.0 {
+        return Err(format!("{} must be a positive number", name));
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::cli::*;
+
+    fn run(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        match parse(&args)? {
+            Command::Run(options) => Ok(*options),
+            Command::Help => Err(String::from("help")),
+        }
+    }
+
+    #[test]
+    fn no_arguments_give_defaults() {
+        assert_eq!(run(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn all_options_are_parsed() {
+        let options = run(&[
+            "--seed", "42", "--width", "1024", "--height", "768", "--fullscreen", "--config", "my.toml", "--difficulty", "hard",
+            "--fuel", "5.5", "--gravity", "1", "--record", "run.txt", "--autopilot", "--headless", "--assets", "/tmp/a",
+            "--fps", "144", "--body", "titan", "--mode", "time-attack",
+        ])
+        .unwrap();
+        assert_eq!(options.seed, Some(42));
+        assert_eq!((options.width, options.height), (1024, 768));
+        assert!(options.fullscreen && options.headless && options.autopilot);
+        assert_eq!(options.difficulty, Difficulty::Hard);
+        assert_eq!(options.body, Body::Titan);
+        assert_eq!(options.mode, Mode::TimeAttack);
+        assert_eq!(options.record, Some(PathBuf::from("run.txt")));
+        assert_eq!(options.asset_dir, PathBuf::from("/tmp/a"));
+        assert_eq!(options.pacing, Pacing::Capped(144));
+        assert_eq!(options.config, Some(PathBuf::from("my.toml")));
+        let config = options.apply(Config::default());
+        assert_eq!(config.lander.starting_fuel, 5.5);
+        assert_eq!(config.physics.gravity, 1.0);
+    }
+
+    #[test]
+    fn difficulty_is_used_without_overrides() {
+        let options = run(&["--difficulty", "easy", "--uncapped"]).unwrap();
+        assert_eq!(options.apply(Config::default()), Difficulty::Easy.apply(Config::default()));
+        assert_eq!(options.pacing, Pacing::Uncapped);
+    }
+
+    #[test]
+    fn help_wins() {
+        let args = vec![String::from("--seed"), String::from("1"), String::from("--help")];
+        assert_eq!(parse(&args), Ok(Command::Help));
+        for option in ["--seed", "--width", "--fullscreen", "--config", "--print-config", "--difficulty", "--body", "--mode", "--daily", "--fuel", "--gravity",
+            "--record", "--play", "--level", "--autopilot", "--headless", "--assets", "--vsync", "--fps", "--uncapped", "--host",
+            "--join", "--net-delay", "--rollback", "--net-loss", "--net-latency"] {
+            assert!(HELP.contains(option), "{} is not documented", option);
+        }
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        assert_eq!(run(&["--seed"]).unwrap_err(), "--seed needs a value");
+        assert_eq!(run(&["--seed", "abc"]).unwrap_err(), "invalid value 'abc' for --seed");
+        assert_eq!(run(&["--width", "0"]).unwrap_err(), "--width must be greater than 0");
+        assert_eq!(run(&["--fuel", "-1"]).unwrap_err(), "--fuel must be a positive number");
+        assert_eq!(run(&["--bogus"]).unwrap_err(), "unknown option '--bogus'");
+        assert!(run(&["--difficulty", "insane"]).is_err());
+        assert_eq!(run(&["--body", "pluto"]).unwrap_err(), "unknown body 'pluto'");
+        assert_eq!(run(&["--mode", "zen"]).unwrap_err(), "unknown mode 'zen'");
+        assert!(run(&["--record", "a", "--play", "b"]).is_err());
+        assert!(run(&["--level", "a", "--record", "b"]).is_err());
+        assert!(run(&["--autopilot", "--play", "b"]).is_err());
+        assert_eq!(run(&["--level", "a"]).unwrap().level, Some(PathBuf::from("a")));
+        assert!(run(&["--host", "7777", "--join", "localhost:7777"]).is_err());
+        assert!(run(&["--join", "localhost:7777", "--play", "b"]).is_err());
+        assert_eq!(run(&["--net-loss", "2"]).unwrap_err(), "--net-loss must be between 0 and 1");
+        assert_eq!(run(&["--daily", "18.10.2026"]).unwrap_err(), "invalid date '18.10.2026', use YYYY-MM-DD");
+        assert_eq!(run(&["--daily", "9999-01-01"]).unwrap_err(), "--daily needs a date before today");
+        assert!(run(&["--daily", &Date::today().to_string()]).is_err());
+        let daily = run(&["--daily", "2024-02-29"]).unwrap().daily;
+        assert_eq!(daily, Some(Date { year: 2024, month: 2, day: 29 }));
+    }
+
+    #[test]
+    fn network_races_are_parsed() {
+        let options = run(&["--host", "7777", "--rollback", "8", "--net-delay", "1", "--net-loss", "0.1", "--net-latency", "50"]).unwrap();
+        assert_eq!(options.host, Some(7777));
+        assert_eq!((options.net_delay, options.rollback, options.net_latency), (1, 8, 50));
+        assert_eq!(options.net_loss, 0.1);
+        let options = run(&["--join", "192.168.0.2:7777"]).unwrap();
+        assert_eq!(options.join.as_deref(), Some("192.168.0.2:7777"));
+        assert_eq!((options.net_delay, options.rollback), (3, 0));
+    }
+}
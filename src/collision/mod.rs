@@ -4,15 +4,7 @@ use sdl2::rect::Rect;
 use sdl2::rect::Point;
 //use std::cmp::{min, max};
 
-fn is_on_segment(a: Vec2d, b: Vec2d, sample: Vec2d) -> bool
-{
-    let total = (a - b).len();
-    let dA = (a-sample).len();
-    let dB = (b-sample).len();
-    return ((dA + dB) - total).abs() < 0.001
-}
-
-pub fn detect_collision(bbox: Vec<Vec2d>, points: &Vec<Vec2d>)
+pub fn detect_collision(bbox: Vec<Vec2d>, points: &[Vec2d])
     -> Option<Vec<(Vec2d, Vec2d)>>
 {
     // create vector of bounding box lines
@@ -22,13 +14,18 @@ pub fn detect_collision(bbox: Vec<Vec2d>, points: &Vec<Vec2d>)
     }
     bb_lines.push((bbox[bbox.len()-1], bbox[0]));
 
+    // Segments beside the box can not touch it
+    let left = bbox.iter().fold(f32::MAX, |l, p| min(l, p.x));
+    let right = bbox.iter().fold(f32::MIN, |r, p| max(r, p.x));
+
     let mut collisions: Vec<(Vec2d, Vec2d)> = Vec::new();
 
     for idx in 1..points.len() {
+        if max(points[idx - 1].x, points[idx].x) < left || min(points[idx - 1].x, points[idx].x) > right {
+            continue;
+        }
         for (a,b) in bb_lines.iter() {
-            if let Some(collision_point) = get_line_intersection(*a, *b, points[idx - 1], points[idx]) {
-                if !is_on_segment(*a, *b, collision_point) {continue;}
-                if !is_on_segment(points[idx - 1], points[idx], collision_point) {continue;}               
+            if segment_intersection(*a, *b, points[idx - 1], points[idx]) != Intersection::None {
                 collisions.push((points[idx - 1], points[idx]));
             }
         }
@@ -41,6 +38,27 @@ pub fn detect_collision(bbox: Vec<Vec2d>, points: &Vec<Vec2d>)
     }
 }
 
+// Casts a ray from `from` to `to` against the polyline `points`. Returns the
+// hit closest to `from` together with the segment that was hit.
+pub fn raycast(from: Vec2d, to: Vec2d, points: &[Vec2d]) -> Option<(Vec2d, (Vec2d, Vec2d))>
+{
+    let mut closest: Option<(Vec2d, (Vec2d, Vec2d))> = None;
+    for idx in 1..points.len() {
+        let segment = (points[idx - 1], points[idx]);
+        let hit = match segment_intersection(from, to, segment.0, segment.1) {
+            Intersection::None => continue,
+            Intersection::Point(p) => p,
+            Intersection::Overlap(p, q) => {
+                if (p - from).len() < (q - from).len() { p } else { q }
+            }
+        };
+        if closest.is_none_or(|(c, _)| (hit - from).len() < (c - from).len()) {
+            closest = Some((hit, segment));
+        }
+    }
+    closest
+}
+
 fn min(a: f32, b: f32) -> f32
 {
     if a > b {
@@ -59,31 +77,164 @@ fn max(a: f32, b: f32) -> f32
     }
 }
 
-fn get_line_intersection(p1: Vec2d, p2: Vec2d, p3: Vec2d, p4: Vec2d) -> Option<Vec2d>
+// Relative tolerance used by segment_intersection. It is a few ulps of f32,
+// which is what the input coordinates carry. All checks are scaled by the
+// size of the involved segments, so the same value works for the lander
+// model coordinates as well as for screen sized terrain.
+const INTERSECTION_EPSILON: f64 = 1e-6;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Intersection {
+    None,
+    // The segments cross or touch in exactly one point
+    Point(Vec2d),
+    // The segments are collinear and share the part between both points
+    Overlap(Vec2d, Vec2d),
+}
+
+fn cross(ax: f64, ay: f64, bx: f64, by: f64) -> f64
 {
-    let s1_x = p2.x - p1.x;
-    let s2_x = p4.x - p3.x;
-    let s1_y = p2.y - p1.y;
-    let s2_y = p4.y - p3.y;
+    ax * by - ay * bx
+}
 
-    let s = (-s1_y * (p1.x - p2.x) + s1_x * (p1.y - p3.y)) / (-s2_x * s1_y + s1_x * s2_y);
-    let t = ( s2_x * (p1.y - p3.y) - s2_y * (p1.x - p3.x)) / (-s2_x * s1_y + s1_x * s2_y);
+fn delta(from: Vec2d, to: Vec2d) -> (f64, f64)
+{
+    (to.x as f64 - from.x as f64, to.y as f64 - from.y as f64)
+}
 
-    if (s >= 0.0 && s <= 1.0 && t >= 0.0 && t <= 1.0)
-    {
-        // Collision detected
-        return Some(Vec2d::new(p1.x + (t * s1_x), p1.y + (t * s1_y)));
+fn lerp(p: Vec2d, dx: f64, dy: f64, t: f64) -> Vec2d
+{
+    Vec2d::new((p.x as f64 + dx * t) as f32, (p.y as f64 + dy * t) as f32)
+}
+
+// Intersects the segments p1-p2 and p3-p4. Parallel, collinear and
+// touching segments are handled explicitly instead of relying on a
+// division by (almost) zero.
+pub fn segment_intersection(p1: Vec2d, p2: Vec2d, p3: Vec2d, p4: Vec2d) -> Intersection
+{
+    // Work in f64 relative to p1, which keeps the cancellation in the
+    // cross products small for the coordinates we deal with.
+    let (rx, ry) = delta(p1, p2);
+    let (sx, sy) = delta(p3, p4);
+    let (qx, qy) = delta(p1, p3);
+
+    let r_len = (rx * rx + ry * ry).sqrt();
+    let s_len = (sx * sx + sy * sy).sqrt();
+    let q_len = (qx * qx + qy * qy).sqrt();
+    let tol = INTERSECTION_EPSILON * r_len.max(s_len).max(q_len).max(1.0);
+
+    // Degenerate segments are treated as points
+    if r_len <= tol && s_len <= tol {
+        if q_len <= tol {
+            return Intersection::Point(p1);
+        }
+        return Intersection::None;
+    }
+    if r_len <= tol {
+        return point_on_segment(p1, p3, p4, tol);
     }
+    if s_len <= tol {
+        return point_on_segment(p3, p1, p2, tol);
+    }
+
+    let denom = cross(rx, ry, sx, sy);
+    let q_cross_r = cross(qx, qy, rx, ry);
 
-    return None
+    if denom.abs() <= INTERSECTION_EPSILON * r_len * s_len {
+        // Parallel: only collinear segments can still share points
+        if q_cross_r.abs() > tol * r_len {
+            return Intersection::None;
+        }
+
+        // Project p3 and p4 onto p1-p2, p1 being 0 and p2 being 1
+        let rr = rx * rx + ry * ry;
+        let t0 = (qx * rx + qy * ry) / rr;
+        let t1 = t0 + (sx * rx + sy * ry) / rr;
+        let lo = t0.min(t1).max(0.0);
+        let hi = t0.max(t1).min(1.0);
+        let t_tol = tol / r_len;
+
+        if hi < lo - t_tol {
+            return Intersection::None;
+        }
+        if hi - lo <= t_tol {
+            return Intersection::Point(lerp(p1, rx, ry, (lo + hi) / 2.0));
+        }
+        return Intersection::Overlap(lerp(p1, rx, ry, lo), lerp(p1, rx, ry, hi));
+    }
+
+    let t = cross(qx, qy, sx, sy) / denom;
+    let u = q_cross_r / denom;
+    let t_tol = tol / r_len;
+    let u_tol = tol / s_len;
+
+    if t < -t_tol || t > 1.0 + t_tol || u < -u_tol || u > 1.0 + u_tol {
+        return Intersection::None;
+    }
+
+    // Snap to the exact endpoint for touching segments, so callers can
+    // compare against the input points.
+    if t.abs() <= t_tol {
+        return Intersection::Point(p1);
+    }
+    if (t - 1.0).abs() <= t_tol {
+        return Intersection::Point(p2);
+    }
+    if u.abs() <= u_tol {
+        return Intersection::Point(p3);
+    }
+    if (u - 1.0).abs() <= u_tol {
+        return Intersection::Point(p4);
+    }
+
+    Intersection::Point(lerp(p1, rx, ry, t))
+}
+
+fn point_on_segment(p: Vec2d, a: Vec2d, b: Vec2d, tol: f64) -> Intersection
+{
+    let (abx, aby) = delta(a, b);
+    let (apx, apy) = delta(a, p);
+    let ab_len = (abx * abx + aby * aby).sqrt();
+
+    if cross(abx, aby, apx, apy).abs() > tol * ab_len {
+        return Intersection::None;
+    }
+    let t = (apx * abx + apy * aby) / (ab_len * ab_len);
+    let t_tol = tol / ab_len;
+    if t < -t_tol || t > 1.0 + t_tol {
+        return Intersection::None;
+    }
+    Intersection::Point(p)
 }
 
 
 #[cfg(test)]
 mod tests {
 
-    use crate::collision;
+    use crate::collision::{self, Intersection};
     use crate::vecmath::Vec2d;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    const NUM_SAMPLES: usize = 10000;
+
+    fn random_point(rng: &mut StdRng) -> Vec2d {
+        Vec2d::new(rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0))
+    }
+
+    fn distance_to_segment(p: Vec2d, a: Vec2d, b: Vec2d) -> f32 {
+        let ab = b - a;
+        let len_sq = ab.x * ab.x + ab.y * ab.y;
+        if len_sq == 0.0 {
+            return (p - a).len();
+        }
+        let t = (((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / len_sq).clamp(0.0, 1.0);
+        (p - (a + ab * t)).len()
+    }
+
+    fn is_finite(p: Vec2d) -> bool {
+        p.x.is_finite() && p.y.is_finite()
+    }
 
     #[test]
     fn test_line_intersection()
@@ -93,12 +244,219 @@ mod tests {
         let p3 = Vec2d::new(5.0, -5.0);
         let p4 = Vec2d::new(5.0, 5.0);
 
-        if let Some(result) = collision::get_line_intersection(p1, p2, p3, p4)
+        if let Intersection::Point(result) = collision::segment_intersection(p1, p2, p3, p4)
         {
             assert_eq!(result.x, 5.0);
             assert_eq!(result.y, 0.0);
         } else {
-            assert!(false);
+            panic!("segments should cross");
+        }
+    }
+
+    #[test]
+    fn parallel_segments_do_not_intersect()
+    {
+        let result = collision::segment_intersection(
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(10.0, 0.0),
+            Vec2d::new(0.0, 1.0),
+            Vec2d::new(10.0, 1.0),
+        );
+        assert_eq!(result, Intersection::None);
+    }
+
+    #[test]
+    fn collinear_segments_overlap()
+    {
+        let result = collision::segment_intersection(
+            Vec2d::new(0.0, 300.0),
+            Vec2d::new(40.0, 300.0),
+            Vec2d::new(30.0, 300.0),
+            Vec2d::new(60.0, 300.0),
+        );
+        assert_eq!(
+            result,
+            Intersection::Overlap(Vec2d::new(30.0, 300.0), Vec2d::new(40.0, 300.0))
+        );
+    }
+
+    #[test]
+    fn collinear_disjoint_segments_do_not_intersect()
+    {
+        let result = collision::segment_intersection(
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(10.0, 10.0),
+            Vec2d::new(11.0, 11.0),
+            Vec2d::new(20.0, 20.0),
+        );
+        assert_eq!(result, Intersection::None);
+    }
+
+    #[test]
+    fn collinear_segments_touching_in_endpoint()
+    {
+        let result = collision::segment_intersection(
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(10.0, 0.0),
+            Vec2d::new(10.0, 0.0),
+            Vec2d::new(20.0, 0.0),
+        );
+        assert_eq!(result, Intersection::Point(Vec2d::new(10.0, 0.0)));
+    }
+
+    #[test]
+    fn t_junction_touches_segment()
+    {
+        let result = collision::segment_intersection(
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(10.0, 0.0),
+            Vec2d::new(5.0, 0.0),
+            Vec2d::new(5.0, 10.0),
+        );
+        assert_eq!(result, Intersection::Point(Vec2d::new(5.0, 0.0)));
+    }
+
+    #[test]
+    fn degenerate_segment_is_a_point()
+    {
+        let on = collision::segment_intersection(
+            Vec2d::new(5.0, 0.0),
+            Vec2d::new(5.0, 0.0),
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(10.0, 0.0),
+        );
+        assert_eq!(on, Intersection::Point(Vec2d::new(5.0, 0.0)));
+
+        let off = collision::segment_intersection(
+            Vec2d::new(5.0, 1.0),
+            Vec2d::new(5.0, 1.0),
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(10.0, 0.0),
+        );
+        assert_eq!(off, Intersection::None);
+    }
+
+    #[test]
+    fn raycast_returns_closest_hit()
+    {
+        let terrain = vec![
+            Vec2d::new(0.0, 100.0),
+            Vec2d::new(10.0, 50.0),
+            Vec2d::new(20.0, 100.0),
+            Vec2d::new(30.0, 100.0),
+        ];
+        let (hit, segment) =
+            collision::raycast(Vec2d::new(0.0, 75.0), Vec2d::new(30.0, 75.0), &terrain).unwrap();
+        assert!((hit.x - 5.0).abs() < 0.001);
+        assert_eq!(segment.1.x, 10.0);
+
+        let (hit, segment) =
+            collision::raycast(Vec2d::new(25.0, 0.0), Vec2d::new(25.0, 200.0), &terrain).unwrap();
+        assert_eq!(hit.y, 100.0);
+        assert_eq!(segment.0.x, 20.0);
+
+        assert!(collision::raycast(Vec2d::new(25.0, 0.0), Vec2d::new(25.0, 40.0), &terrain).is_none());
+    }
+
+    #[test]
+    fn random_intersections_are_symmetric_and_on_both_segments()
+    {
+        let mut rng = StdRng::seed_from_u64(26);
+        for _ in 0..NUM_SAMPLES {
+            let (a, b, c, d) = (
+                random_point(&mut rng),
+                random_point(&mut rng),
+                random_point(&mut rng),
+                random_point(&mut rng),
+            );
+            let forward = collision::segment_intersection(a, b, c, d);
+            let backward = collision::segment_intersection(c, d, a, b);
+            assert_eq!(forward == Intersection::None, backward == Intersection::None);
+
+            if let Intersection::Point(p) = forward {
+                assert!(is_finite(p));
+                assert!(distance_to_segment(p, a, b) < 0.01, "{:?}", (a, b, c, d, p));
+                assert!(distance_to_segment(p, c, d) < 0.01, "{:?}", (a, b, c, d, p));
+            }
+        }
+    }
+
+    #[test]
+    fn random_crossing_segments_meet_in_constructed_point()
+    {
+        let mut rng = StdRng::seed_from_u64(27);
+        for _ in 0..NUM_SAMPLES {
+            let center = random_point(&mut rng);
+            let angle_a: f32 = rng.gen_range(0.0..std::f32::consts::PI);
+            // keep a minimum angle between both segments, otherwise the
+            // result is dominated by f32 rounding of the inputs
+            let angle_b = angle_a + rng.gen_range(0.1..(std::f32::consts::PI - 0.1));
+            let dir_a = Vec2d::from_angle(angle_a);
+            let dir_b = Vec2d::from_angle(angle_b);
+
+            let a = center - dir_a * rng.gen_range(1.0..100.0);
+            let b = center + dir_a * rng.gen_range(1.0..100.0);
+            let c = center - dir_b * rng.gen_range(1.0..100.0);
+            let d = center + dir_b * rng.gen_range(1.0..100.0);
+
+            match collision::segment_intersection(a, b, c, d) {
+                Intersection::Point(p) => assert!((p - center).len() < 0.01),
+                other => panic!("expected crossing, got {:?}", other),
+            }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn random_parallel_segments_never_produce_nan()
+    {
+        let mut rng = StdRng::seed_from_u64(28);
+        for _ in 0..NUM_SAMPLES {
+            let a = random_point(&mut rng);
+            let b = random_point(&mut rng);
+            let dir = b - a;
+            let normal = Vec2d::new(-dir.y, dir.x).normalized();
+            let offset = normal * rng.gen_range(1.0..50.0);
+            let shift = dir * rng.gen_range(-2.0..2.0);
+
+            let result = collision::segment_intersection(a, b, a + offset + shift, b + offset + shift);
+            assert_eq!(result, Intersection::None, "{:?}", (a, b, offset, shift));
+        }
+    }
+
+    #[test]
+    fn random_collinear_sub_segments_overlap()
+    {
+        let mut rng = StdRng::seed_from_u64(29);
+        for _ in 0..NUM_SAMPLES {
+            // Axis aligned and diagonal lines keep the inputs exactly
+            // collinear in f32, just like flat landing pads
+            let y: f32 = rng.gen_range(-500..500) as f32;
+            let x0: f32 = rng.gen_range(-500..0) as f32;
+            let x1: f32 = rng.gen_range(1..500) as f32;
+            let s0: f32 = rng.gen_range(-600..(x1 as i32)) as f32;
+            let s1: f32 = s0 + rng.gen_range(1..200) as f32;
+
+            for (a, b, c, d) in [
+                (Vec2d::new(x0, y), Vec2d::new(x1, y), Vec2d::new(s0, y), Vec2d::new(s1, y)),
+                (Vec2d::new(y, x0), Vec2d::new(y, x1), Vec2d::new(y, s0), Vec2d::new(y, s1)),
+                (Vec2d::new(x0, x0), Vec2d::new(x1, x1), Vec2d::new(s0, s0), Vec2d::new(s1, s1)),
+            ] {
+                let result = collision::segment_intersection(a, b, c, d);
+                let lo = x0.max(s0);
+                let hi = x1.min(s1);
+                if hi < lo {
+                    assert_eq!(result, Intersection::None);
+                } else if hi == lo {
+                    assert!(matches!(result, Intersection::Point(_)), "{:?}", result);
+                } else if let Intersection::Overlap(p, q) = result {
+                    assert!(is_finite(p) && is_finite(q));
+                    assert!(distance_to_segment(p, a, b) < 0.01);
+                    assert!(distance_to_segment(q, c, d) < 0.01);
+                    assert!(((p - q).len() - (hi - lo) * (b - a).len() / (x1 - x0)).abs() < 0.01);
+                } else {
+                    panic!("expected overlap, got {:?}", result);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+mod watcher;
+
+pub use watcher::ConfigWatcher;
+
+// Every tunable value of the game. The defaults are the values the game
+// was originally tuned with, a config file only needs to name the values it
+// changes:
+//
+//     # comment
+//     [physics]
+//     gravity = 12.5
+//
+//     [lander]
+//     thrust = 150.0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub physics: PhysicsConfig,
+    pub lander: LanderConfig,
+    pub terrain: TerrainConfig,
+    pub atmosphere: AtmosphereConfig,
+    pub hud: HudConfig,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsConfig {
+    pub gravity: f32, // in pixel per second squared
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LanderConfig {
+    pub thrust: f32,         // in pixel per second squared at full throttle
+    pub starting_fuel: f32,  // in seconds at full throttle
+    pub rotation_speed: f32, // in radians per second
+    pub crash_speed: f32,    // impact speed that destroys the lander
+    pub safe_slope: f32,     // in radians, steeper ground counts as a crash
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainConfig {
+    pub x_max_delta: f32,     // segments shorter than this are not split again
+    pub y_max_delta: f32,     // height variation of the first split
+    pub y_delta_divider: f32, // the variation shrinks by this with every split
+}
+
+// Airless bodies have a density of 0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtmosphereConfig {
+    pub density: f32,      // at the bottom of the window
+    pub scale_height: f32, // in pixel, the density halves about every 0.7 of it
+    pub drag: f32,         // drag coefficient of the lander
+    pub wind_speed: f32,   // in pixel per second, negative blows to the left
+    pub gustiness: f32,    // how much gusts vary the wind, 0.0 for steady wind
+}
+
+// The HUD turns values red once they cross these
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HudConfig {
+    pub warn_speed: f32, // in pixel per second
+    pub low_fuel: f32,   // in seconds
+}
+
+// Which values may be set in a file, with "section.key" names
+const KEYS: [&str; 16] = [
+    "physics.gravity",
+    "lander.thrust",
+    "lander.starting_fuel",
+    "lander.rotation_speed",
+    "lander.crash_speed",
+    "lander.safe_slope",
+    "terrain.x_max_delta",
+    "terrain.y_max_delta",
+    "terrain.y_delta_divider",
+    "atmosphere.density",
+    "atmosphere.scale_height",
+    "atmosphere.drag",
+    "atmosphere.wind_speed",
+    "atmosphere.gustiness",
+    "hud.warn_speed",
+    "hud.low_fuel",
+];
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            physics: PhysicsConfig { gravity: 9.81 * 25.0 / 24.0 },
+            lander: LanderConfig {
+                thrust: 125.0,
+                starting_fuel: 20.0,
+                rotation_speed: 5.0,
+                crash_speed: 25.0,
+                safe_slope: PI / 16.0,
+            },
+            terrain: TerrainConfig {
+                x_max_delta: 10.0,
+                y_max_delta: 200.0,
+                y_delta_divider: 1.70,
+            },
+            atmosphere: AtmosphereConfig {
+                density: 0.0,
+                scale_height: 150.0,
+                drag: 0.3,
+                wind_speed: 0.0,
+                gustiness: 0.5,
+            },
+            hud: HudConfig {
+                warn_speed: 15.0,
+                low_fuel: 5.0,
+            },
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_text(&text).map_err(|e| format!("{}:{}", path.display(), e))
+    }
+
+    // Errors start with the line number, e.g. "3: unknown key 'lander.fuel'"
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(format!("{}: expected 'key = value' or '[section]'", line_no))?;
+            let key = if section.is_empty() {
+                key.trim().to_string()
+            } else {
+                format!("{}.{}", section, key.trim())
+            };
+            config
+                .set(&key, value.trim())
+                .map_err(|e| format!("{}: {}", line_no, e))?;
+        }
+        Ok(config)
+    }
+
+    pub fn to_text(self) -> String {
+        let mut text = String::new();
+        let mut section = "";
+        for (key, value) in self.values() {
+            let (key_section, name) = key.split_once('.').unwrap();
+            if key_section != section {
+                if !section.is_empty() {
+                    text += "\n";
+                }
+                text += &format!("[{}]\n", key_section);
+                section = key_section;
+            }
+            text += &format!("{} = {}\n", name, value);
+        }
+        text
+    }
+
+    // All values with their full "section.key" name
+    pub fn values(&self) -> Vec<(&'static str, f32)> {
+        let mut config = *self;
+        KEYS.iter().map(|key| (*key, *config.field(key).unwrap())).collect()
+    }
+
+    // Sets a single value, rejecting unknown keys and values out of range
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let field = self.field(key).ok_or(format!("unknown key '{}'", key))?;
+        let v: f32 = value
+            .parse()
+            .map_err(|_| format!("invalid value '{}' for '{}'", value, key))?;
+        if !v.is_finite() {
+            return Err(format!("invalid value '{}' for '{}'", value, key));
+        }
+        let valid = match key {
+            "physics.gravity" | "lander.starting_fuel" | "hud.low_fuel" => v >= 0.0,
+            "atmosphere.density" | "atmosphere.drag" | "atmosphere.gustiness" => v >= 0.0,
+            "atmosphere.wind_speed" => true,
+            "lander.safe_slope" => (0.0..PI / 2.0).contains(&v),
+            _ => v > 0.0,
+        };
+        if !valid {
+            return Err(format!("'{}' is out of range for '{}'", value, key));
+        }
+        *field = v;
+        Ok(())
+    }
+
+    fn field(&mut self, key: &str) -> Option<&mut f32> {
+        let field = match key {
+            "physics.gravity" => &mut self.physics.gravity,
+            "lander.thrust" => &mut self.lander.thrust,
+            "lander.starting_fuel" => &mut self.lander.starting_fuel,
+            "lander.rotation_speed" => &mut self.lander.rotation_speed,
+            "lander.crash_speed" => &mut self.lander.crash_speed,
+            "lander.safe_slope" => &mut self.lander.safe_slope,
+            "terrain.x_max_delta" => &mut self.terrain.x_max_delta,
+            "terrain.y_max_delta" => &mut self.terrain.y_max_delta,
+            "terrain.y_delta_divider" => &mut self.terrain.y_delta_divider,
+            "atmosphere.density" => &mut self.atmosphere.density,
+            "atmosphere.scale_height" => &mut self.atmosphere.scale_height,
+            "atmosphere.drag" => &mut self.atmosphere.drag,
+            "atmosphere.wind_speed" => &mut self.atmosphere.wind_speed,
+            "atmosphere.gustiness" => &mut self.atmosphere.gustiness,
+            "hud.warn_speed" => &mut self.hud.warn_speed,
+            "hud.low_fuel" => &mut self.hud.low_fuel,
+            _ => return None,
+        };
+        Some(field)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+pub const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // Scales gravity and fuel of the given config
+    pub fn apply(&self, config: Config) -> Config {
+        let (gravity, fuel) = match self {
+            Difficulty::Easy => (0.8, 1.5),
+            Difficulty::Normal => (1.0, 1.0),
+            Difficulty::Hard => (1.25, 0.6),
+        };
+        let mut config = config;
+        config.physics.gravity *= gravity;
+        config.lander.starting_fuel *= fuel;
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::*;
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        assert_eq!(Config::from_text("").unwrap(), Config::default());
+        assert_eq!(Config::from_text("# nothing\n\n[lander]\n").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn values_are_read_per_section() {
+        let text = "[physics]\ngravity = 1.5 # strong\n\n[lander]\nthrust=7\n\n[terrain]\ny_delta_divider = 2\n";
+        let config = Config::from_text(text).unwrap();
+        assert_eq!(config.physics.gravity, 1.5);
+        assert_eq!(config.lander.thrust, 7.0);
+        assert_eq!(config.terrain.y_delta_divider, 2.0);
+        assert_eq!(config.lander.starting_fuel, Config::default().lander.starting_fuel);
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut config = Config::default();
+        config.set("lander.crash_speed", "3.25").unwrap();
+        let text = config.to_text();
+        assert!(text.starts_with("[physics]\ngravity = "));
+        assert_eq!(Config::from_text(&text).unwrap(), config);
+        assert_eq!(config.values().len(), KEYS.len());
+    }
+
+    #[test]
+    fn errors_name_line_and_key() {
+        let err = Config::from_text("[lander]\nfuel = 3\n").unwrap_err();
+        assert_eq!(err, "2: unknown key 'lander.fuel'");
+        let err = Config::from_text("\n[lander]\nthrust = strong\n").unwrap_err();
+        assert_eq!(err, "3: invalid value 'strong' for 'lander.thrust'");
+        let err = Config::from_text("[terrain]\nx_max_delta = 0\n").unwrap_err();
+        assert_eq!(err, "2: '0' is out of range for 'terrain.x_max_delta'");
+        let err = Config::from_text("[lander]\nsafe_slope = 2\n").unwrap_err();
+        assert_eq!(err, "2: '2' is out of range for 'lander.safe_slope'");
+        let err = Config::from_text("gravity 3\n").unwrap_err();
+        assert_eq!(err, "1: expected 'key = value' or '[section]'");
+    }
+
+    #[test]
+    fn difficulty_scales_gravity_and_fuel() {
+        let normal = Config::default();
+        let easy = Difficulty::from_name("easy").unwrap().apply(normal);
+        let hard = Difficulty::from_name("hard").unwrap().apply(normal);
+        assert_eq!(Difficulty::Normal.apply(normal), normal);
+        assert!(Difficulty::from_name("insane").is_none());
+        assert!(easy.physics.gravity < normal.physics.gravity && hard.physics.gravity > normal.physics.gravity);
+        assert!(easy.lander.starting_fuel > hard.lander.starting_fuel);
+        assert_eq!(easy.lander.thrust, hard.lander.thrust);
+        for difficulty in DIFFICULTIES {
+            assert_eq!(Difficulty::from_name(difficulty.name()), Some(difficulty));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::Config;
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+// Polls a config file for changes. Editors tend to replace files instead of
+// writing them in place, so besides the modification time the size is
+// compared as well.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+    last_check: Instant,
+}
+
+impl ConfigWatcher {
+    // The current state of the file counts as seen, only later changes are
+    // reported
+    pub fn new(path: &Path) -> Self {
+        ConfigWatcher {
+            path: path.to_path_buf(),
+            stamp: stamp(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Like check, but looks at the file at most every CHECK_INTERVAL
+    pub fn poll(&mut self) -> Option<Result<Config, String>> {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+        self.check()
+    }
+
+    // Returns the reloaded config if the file changed since the last call.
+    // A file that vanished is not a change, the game keeps its config.
+    pub fn check(&mut self) -> Option<Result<Config, String>> {
+        let stamp = stamp(&self.path);
+        if stamp.is_none() || stamp == self.stamp {
+            return None;
+        }
+        self.stamp = stamp;
+        Some(Config::load(&self.path))
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    use crate::config::ConfigWatcher;
+
+    fn touch(path: &std::path::Path, content: &str, age_s: u64) {
+        fs::write(path, content).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_s)).unwrap();
+    }
+
+    #[test]
+    fn changes_are_reported_once() {
+        let path = std::env::temp_dir().join(format!("lunar_watch_{}.toml", std::process::id()));
+        touch(&path, "[lander]\nthrust = 4\n", 100);
+        let mut watcher = ConfigWatcher::new(&path);
+        assert!(watcher.check().is_none());
+
+        touch(&path, "[lander]\nthrust = 6\n", 50);
+        let config = watcher.check().unwrap().unwrap();
+        assert_eq!(config.lander.thrust, 6.0);
+        assert!(watcher.check().is_none());
+
+        touch(&path, "[lander]\nthrust = fast\n", 10);
+        let err = watcher.check().unwrap().unwrap_err();
+        assert!(err.ends_with("2: invalid value 'fast' for 'lander.thrust'"), "{}", err);
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.check().is_none());
+    }
+
+    #[test]
+    fn file_created_later_is_picked_up() {
+        let path = std::env::temp_dir().join(format!("lunar_watch_new_{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut watcher = ConfigWatcher::new(&path);
+        assert!(watcher.check().is_none());
+        touch(&path, "[physics]\ngravity = 1\n", 0);
+        assert_eq!(watcher.check().unwrap().unwrap().physics.gravity, 1.0);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+use std::f32::consts::PI;
+
+use crate::control::{Controller, Observation};
+use crate::level::Pad;
+use crate::simulation::Controls;
+
+// Height above the terrain kept while flying over to the pad, in pixel
+const SAFE_HEIGHT: f32 = 40.0;
+// Below this altitude the lander only goes down, about upright
+const FLARE_ALTITUDE: f32 = 12.0;
+// Speed limits, in pixel per second
+const MAX_SIDE_SPEED: f32 = 40.0;
+const MAX_CLIMB_SPEED: f32 = 20.0;
+const MAX_DESCENT_SPEED: f32 = 30.0;
+const TOUCHDOWN_SPEED: f32 = 5.0;
+// Furthest the lander leans over to move sideways, in radians
+const MAX_TILT: f32 = 0.9;
+const FLARE_TILT: f32 = 0.1;
+// Part of gravity the engine always counters, so the lander can lean into
+// a turn even while it wants to drop
+const MIN_LIFT: f32 = 0.8;
+// Part of gravity the integral terms may make up for
+const INTEGRAL_SHARE: f32 = 0.5;
+// Part of the side acceleration planned for braking
+const BRAKING: f32 = 0.4;
+// The descent starts once the lander is this close to the pad center,
+// relative to the pad width
+const OVER_PAD: f32 = 0.25;
+
+// Proportional, integral and derivative control of a single value
+#[derive(Clone, Copy, Debug)]
+pub struct Pid {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    pub integral_limit: f32,
+    integral: f32,
+    last_error: Option<f32>,
+}
+
+impl Pid {
+    pub fn new(kp: f32, ki: f32, kd: f32) -> Self {
+        Pid {
+            kp,
+            ki,
+            kd,
+            integral_limit: f32::MAX,
+            integral: 0.0,
+            last_error: None,
+        }
+    }
+
+    pub fn update(&mut self, error: f32, time_in_s: f32) -> f32 {
+        self.integral = (self.integral + error * time_in_s).clamp(-self.integral_limit, self.integral_limit);
+        let derivative = match self.last_error {
+            Some(last) if time_in_s > 0.0 => (error - last) / time_in_s,
+            _ => 0.0,
+        };
+        self.last_error = Some(error);
+        self.kp * error + self.ki * self.integral + self.kd * derivative
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_error = None;
+    }
+}
+
+// Lands on the pad closest to where it takes over. It keeps clear of the
+// terrain on the way, stops over the pad and then descends slower and
+// slower. Without pads it lands straight below.
+//
+// Each axis has its own speed controller. Together they ask for an
+// acceleration, which decides the lean of the lander and the throttle.
+pub struct Autopilot {
+    target: Option<f32>, // x to land at, picked on the first tick
+    descending: bool,    // over the pad, from there on it only goes down
+    horizontal: Pid,
+    vertical: Pid,
+    attitude: Pid,
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Autopilot {
+    pub fn new() -> Self {
+        Autopilot {
+            target: None,
+            descending: false,
+            horizontal: Pid::new(3.0, 0.5, 0.0),
+            vertical: Pid::new(6.0, 1.5, 0.0),
+            attitude: Pid::new(10.0, 0.0, 0.0),
+        }
+    }
+
+    fn pick_target(observation: &Observation) -> f32 {
+        observation
+            .nearest_pad()
+            .map_or(observation.position.x, |pad: Pad| pad.center())
+    }
+
+    // Speed the lander should have, y grows downwards. Sideways it goes no
+    // faster than it can brake with half of its lean.
+    fn wanted_velocity(&mut self, observation: &Observation, target: f32) -> (f32, f32) {
+        let position = observation.position;
+        let dx = target - position.x;
+        let pad_width = observation
+            .pads
+            .iter()
+            .find(|p| p.contains(target))
+            .map_or(20.0, |p| p.right - p.left);
+        let settled = dx.abs() < pad_width * OVER_PAD && observation.velocity.x.abs() < 2.5;
+        self.descending |= settled;
+
+        let braking = BRAKING * observation.gravity * MAX_TILT.tan();
+        let speed = (2.0 * braking * dx.abs()).sqrt().min(dx.abs() * 1.5).min(MAX_SIDE_SPEED);
+        let vx = speed * dx.signum();
+        let vy = if self.descending && (settled || observation.altitude > 2.0 * FLARE_ALTITUDE) {
+            (observation.altitude * 0.5).clamp(TOUCHDOWN_SPEED, MAX_DESCENT_SPEED)
+        } else if self.descending {
+            // Drifted off, wait above the flare until back over the pad
+            ((2.0 * FLARE_ALTITUDE - observation.altitude) * -0.5).clamp(-MAX_CLIMB_SPEED, MAX_DESCENT_SPEED)
+        } else {
+            // Keep clear of everything between here and the pad
+            let ground = observation
+                .highest_ground(position.x, target)
+                .unwrap_or(position.y + SAFE_HEIGHT);
+            let foot_depth = observation.ground_at(position.x).unwrap_or(ground) - position.y - observation.altitude;
+            let wanted_y = ground - SAFE_HEIGHT - foot_depth;
+            ((wanted_y - position.y) * 0.5).clamp(-MAX_CLIMB_SPEED, MAX_DESCENT_SPEED)
+        };
+        (vx, vy)
+    }
+}
+
+impl Controller for Autopilot {
+    fn control(&mut self, observation: &Observation, time_in_s: f32) -> Controls {
+        let idle = Controls {
+            throttle: 0.0,
+            rotation: 0.0,
+        };
+        if observation.grounded || observation.fuel <= 0.0 {
+            self.horizontal.reset();
+            self.vertical.reset();
+            return idle;
+        }
+        let target = *self.target.get_or_insert_with(|| Self::pick_target(observation));
+        let (vx, vy) = self.wanted_velocity(observation, target);
+        // The integral only trims, it never asks for more than the engine
+        // can turn into a correction under this gravity
+        for pid in [&mut self.horizontal, &mut self.vertical] {
+            pid.integral_limit = INTEGRAL_SHARE * observation.gravity / pid.ki;
+        }
+        let ax = self.horizontal.update(vx - observation.velocity.x, time_in_s);
+        let ay = self.vertical.update(vy - observation.velocity.y, time_in_s);
+
+        // The engine provides everything but gravity, within the lean limit
+        // Straightens up over the flare, but may still lean into the wind
+        let max_tilt = (MAX_TILT * observation.altitude / FLARE_ALTITUDE).clamp(FLARE_TILT, MAX_TILT);
+        let up = (observation.gravity - ay).max(MIN_LIFT * observation.gravity);
+        let ax = ax.clamp(-up * max_tilt.tan(), up * max_tilt.tan());
+        let wanted_orientation = PI / 2.0 + ax.atan2(up);
+
+        let error = wanted_orientation - observation.orientation;
+        let rotation = (self.attitude.update(error, time_in_s) / observation.rotation_speed.max(1e-3)).clamp(-1.0, 1.0);
+        // Only push once the engine points about the right way
+        let force = (ax * ax + up * up).sqrt();
+        let aligned = (-observation.facing.x * ax + observation.facing.y * up) / force.max(1e-6);
+        let throttle = (force / observation.thrust.max(1e-3) * aligned.max(0.0)).clamp(0.0, 1.0);
+        Controls { throttle, rotation }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::control::autopilot::Pid;
+    use crate::control::{Autopilot, Controller};
+    use crate::level::{Level, Pad};
+    use crate::mainloop::{SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+    use crate::planet::Body;
+    use crate::simulation::{State, World};
+    use crate::vecmath::Vec2d;
+
+    fn fly(world: &mut World) -> u32 {
+        let mut pilot = Autopilot::new();
+        let mut ticks = 0;
+        while *world.state() == State::Running && ticks < 5000 {
+            let controls = pilot.control(&world.observe(), SIM_TICK_MS / 1000.0);
+            world.apply_controls(controls);
+            world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn pid_terms_add_up() {
+        let mut pid = Pid::new(2.0, 1.0, 0.5);
+        assert_eq!(pid.update(1.0, 0.5), 2.0 + 0.5);
+        assert_eq!(pid.update(2.0, 0.5), 4.0 + 1.5 + 1.0);
+        pid.reset();
+        assert_eq!(pid.update(1.0, 0.5), 2.5);
+    }
+
+    #[test]
+    fn lands_on_the_pad_of_a_level() {
+        let mut level = Level::new(
+            Body::Moon,
+            vec![
+                Vec2d::new(0.0, 550.0),
+                Vec2d::new(300.0, 420.0),
+                Vec2d::new(500.0, 520.0),
+                Vec2d::new(560.0, 520.0),
+                Vec2d::new(800.0, 560.0),
+            ],
+        );
+        level.pads.push(Pad {
+            left: 500.0,
+            right: 560.0,
+            multiplier: 2,
+        });
+        level.spawn = Vec2d::new(100.0, 300.0);
+        let mut world = World::from_level(&level, Config::default());
+        fly(&mut world);
+        assert!(*world.state() == State::Won);
+        assert!(world.score() > 0);
+    }
+
+    // Balancing check: the reference pilot lands generated maps on every
+    // body. Gusts on Titan can still blow a landed lander over, so a single
+    // miss per body is accepted.
+    #[test]
+    fn lands_on_generated_maps() {
+        for body in crate::planet::BODIES {
+            let landed = (1..=10)
+                .filter(|seed| {
+                    let mut world = World::new(800, 600, *seed, Config::default(), body);
+                    fly(&mut world);
+                    *world.state() == State::Won
+                })
+                .count();
+            assert!(landed >= 9, "{:?} landed {} of 10", body, landed);
+        }
+    }
+}
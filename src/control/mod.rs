@@ -0,0 +1,59 @@
+use crate::level::Pad;
+use crate::map::PointList;
+use crate::simulation::Controls;
+use crate::vecmath::Vec2d;
+
+mod autopilot;
+mod script;
+
+pub use autopilot::Autopilot;
+pub use script::Script;
+
+// Flies the lander instead of the keyboard. The World is observed before
+// every tick and the returned controls are applied for that tick.
+pub trait Controller {
+    fn control(&mut self, observation: &Observation, time_in_s: f32) -> Controls;
+}
+
+// The state of the lander and what it can know about the level. Positions
+// are in window pixels with y growing downwards.
+pub struct Observation<'a> {
+    pub position: Vec2d,
+    pub velocity: Vec2d,    // in pixel per second
+    pub orientation: f32,   // in radians, PI / 2 is upright
+    pub facing: Vec2d,      // where the engine points, thrust pushes the other way
+    pub fuel: f32,          // in seconds at full throttle
+    pub altitude: f32,      // of the lower foot above the ground below it
+    pub grounded: bool,     // at least one foot touches the ground
+    pub gravity: f32,       // in pixel per second squared
+    pub thrust: f32,        // in pixel per second squared at full throttle
+    pub rotation_speed: f32, // in radians per second at full rotation
+    pub pads: &'a [Pad],
+    pub(crate) map: &'a PointList,
+}
+
+impl Observation<'_> {
+    // Terrain height at x, None outside of the map
+    pub fn ground_at(&self, x: f32) -> Option<f32> {
+        self.map.surface_at(x).map(|(height, _)| height)
+    }
+
+    // The smallest y of the terrain between both x, i.e. its highest point
+    pub fn highest_ground(&self, from: f32, to: f32) -> Option<f32> {
+        let (from, to) = if from <= to { (from, to) } else { (to, from) };
+        let inner = self.map.get_values().iter().filter(|p| p.x > from && p.x < to).map(|p| p.y);
+        [self.ground_at(from), self.ground_at(to)]
+            .into_iter()
+            .flatten()
+            .chain(inner)
+            .reduce(f32::min)
+    }
+
+    pub fn nearest_pad(&self) -> Option<Pad> {
+        self.pads.iter().copied().min_by(|a, b| {
+            let a = (a.center() - self.position.x).abs();
+            let b = (b.center() - self.position.x).abs();
+            a.partial_cmp(&b).unwrap()
+        })
+    }
+}
@@ -0,0 +1,26 @@
+use crate::control::{Controller, Observation};
+use crate::simulation::Controls;
+
+// Flies a fixed sequence of controls, e.g. the inputs of a replay, no
+// matter what it observes. It idles once the sequence ran out.
+pub struct Script {
+    controls: Vec<Controls>,
+    tick: usize,
+}
+
+impl Script {
+    pub fn new(controls: Vec<Controls>) -> Self {
+        Script { controls, tick: 0 }
+    }
+}
+
+impl Controller for Script {
+    fn control(&mut self, _observation: &Observation, _time_in_s: f32) -> Controls {
+        let controls = self.controls.get(self.tick).copied().unwrap_or(Controls {
+            throttle: 0.0,
+            rotation: 0.0,
+        });
+        self.tick += 1;
+        controls
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::config::Config;
+use crate::planet::{Body, BODIES};
+
+// Counted attempts at the challenge of the day
+pub const ATTEMPTS: u32 = 3;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// Fuel in seconds at full throttle and wind in pixel per second, the
+// challenge of a day picks from these ranges
+const FUEL_RANGE: (f32, f32) = (10.0, 25.0);
+const MAX_WIND: f32 = 20.0;
+
+// A day of the proleptic Gregorian calendar, in UTC so that everyone has
+// the same date at the same time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32, // 1 - 12
+    pub day: u32,   // 1 - 31
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Self::from_days((seconds / SECONDS_PER_DAY) as i64)
+    }
+
+    // The date that many days after 1970-01-01
+    pub fn from_days(days: i64) -> Self {
+        // Counts in eras of 400 years from 0000-03-01, so that leap days
+        // end the year
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153; // from March
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    // Days since 1970-01-01, negative before
+    pub fn days(&self) -> i64 {
+        let year = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let month = self.month as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    // "YYYY-MM-DD"
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid date '{}', use YYYY-MM-DD", text);
+        let parts: Vec<&str> = text.split('-').collect();
+        let [year, month, day] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let date = Date {
+            year: year.parse().map_err(|_| invalid())?,
+            month: month.parse().map_err(|_| invalid())?,
+            day: day.parse().map_err(|_| invalid())?,
+        };
+        // Days past the end of the month roll over into the next one
+        if date.month == 0 || date.month > 12 || date.day == 0 || Date::from_days(date.days()) != date {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// The level everyone flies on a date. Starts from the default config, so
+// tuning files do not change the map or the modifiers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Challenge {
+    pub date: Date,
+    pub seed: u64,
+    pub body: Body, // the gravity preset
+    pub fuel: f32,  // in seconds at full throttle
+    pub wind: f32,  // in pixel per second, 0.0 on bodies without air
+}
+
+impl Challenge {
+    pub fn for_date(date: Date) -> Self {
+        let mut rng = StdRng::seed_from_u64(date.days() as u64);
+        let seed = rng.gen();
+        let body = BODIES[rng.gen_range(0..BODIES.len())];
+        // Rounded, so the HUD shows what the challenge says
+        let fuel = (rng.gen_range(FUEL_RANGE.0..=FUEL_RANGE.1) * 2.0).round() / 2.0;
+        // The body brings its own wind where the config has none, so
+        // still air is rolled again
+        let wind = loop {
+            let wind = (rng.gen_range(-MAX_WIND..=MAX_WIND) * 10.0).round() / 10.0;
+            if wind != 0.0 {
+                break wind;
+            }
+        };
+        let airless = body.apply(Config::default()).atmosphere.density <= 0.0;
+        Challenge {
+            date,
+            seed,
+            body,
+            fuel,
+            wind: if airless { 0.0 } else { wind },
+        }
+    }
+
+    // The body applies itself when the World is made
+    pub fn config(&self) -> Config {
+        let mut config = Config::default();
+        config.lander.starting_fuel = self.fuel;
+        config.atmosphere.wind_speed = self.wind;
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::daily::*;
+
+    #[test]
+    fn dates_count_days_from_the_epoch() {
+        let dates = [
+            (0, "1970-01-01"),
+            (-1, "1969-12-31"),
+            (59, "1970-03-01"),
+            (11_016, "2000-02-29"),
+            (19_782, "2024-02-29"),
+            (20_744, "2026-10-18"),
+        ];
+        for (days, text) in dates {
+            let date = Date::parse(text).unwrap();
+            assert_eq!(date.days(), days, "{}", text);
+            assert_eq!(Date::from_days(days), date);
+            assert_eq!(date.to_string(), text);
+        }
+        assert!(Date::today() > Date::parse("2020-01-01").unwrap());
+    }
+
+    #[test]
+    fn broken_dates_are_rejected() {
+        for text in ["", "2026-10", "2026-13-01", "2026-00-10", "2025-02-29", "2026-04-31", "2026-10-x", "2026-10-18-1"] {
+            assert!(Date::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn every_day_has_its_own_challenge() {
+        let date = Date::parse("2026-10-18").unwrap();
+        let challenge = Challenge::for_date(date);
+        assert_eq!(Challenge::for_date(date), challenge);
+        assert_ne!(Challenge::for_date(Date::from_days(date.days() + 1)).seed, challenge.seed);
+
+        for days in 0..2000 {
+            let challenge = Challenge::for_date(Date::from_days(days));
+            assert!(challenge.fuel >= FUEL_RANGE.0 && challenge.fuel <= FUEL_RANGE.1);
+            assert!(challenge.wind.abs() <= MAX_WIND);
+            if challenge.body == Body::Moon {
+                assert_eq!(challenge.wind, 0.0);
+            }
+            let config = challenge.config();
+            assert_eq!(config.lander.starting_fuel, challenge.fuel);
+            assert_eq!(config.physics, Config::default().physics);
+            // The HUD shows the wind the lander flies in
+            assert_eq!(challenge.body.apply(config).atmosphere.wind_speed, challenge.wind);
+        }
+    }
+}
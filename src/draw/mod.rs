@@ -4,10 +4,22 @@ use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::ttf;
 use sdl2::video::Window;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use crate::vecmath::Vec2d;
 
+static ASSET_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Where fonts are loaded from, "assets" unless set once at startup
+pub fn set_asset_dir(dir: &Path) {
+    let _ = ASSET_DIR.set(dir.to_path_buf());
+}
+
+fn asset_path(name: &str) -> PathBuf {
+    ASSET_DIR.get().map_or(Path::new("assets"), |dir| dir.as_path()).join(name)
+}
+
 pub fn draw_line(
     canvas: &mut Canvas<Window>,
     from: &Vec2d,
@@ -28,19 +40,12 @@ pub fn draw_lines(
 {
     canvas.set_draw_color(color);
 
-    for idx in 1..points.len() {
-        if let Err(error) = draw_line(canvas, &points[idx - 1], &points[idx], color) {
-            return Err(error);
-        }
+    // A single call for the whole polyline
+    let mut pixels: Vec<Point> = points.iter().map(|p| Point::new(p.x as i32, p.y as i32)).collect();
+    if close && pixels.len() > 1 {
+        pixels.push(pixels[0]);
     }
-
-    if close {
-        if let Err(error) = draw_line(canvas, &points[points.len() - 1], &points[0], color) {
-            return Err(error);
-        }
-    }
-
-    Ok(())
+    canvas.draw_lines(pixels.as_slice())
 }
 
 pub fn draw_rect<T>(
@@ -91,7 +96,7 @@ fn draw_text_raw(
 ) -> Result<(), String> {
     let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
 
-    let font_path = Path::new("assets/DejaVuSansMono.ttf");
+    let font_path = asset_path("DejaVuSansMono.ttf");
     let font = ttf_context.load_font(font_path, font_size)?;
 
     let surface = font.render(text).solid(color).map_err(|e| e.to_string())?;
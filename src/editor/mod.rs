@@ -0,0 +1,388 @@
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::level::{Level, Pad};
+use crate::planet::Body;
+use crate::vecmath::{TransformationMatrix, Vec2d};
+use crate::{draw, graphics, window_height, window_width};
+
+// How close, in pixel, the mouse has to be to grab a vertex
+const GRAB_RADIUS: f32 = 6.0;
+const VERTEX_SIZE: u32 = 5;
+const DEFAULT_MULTIPLIER: u32 = 2;
+const FUEL_STEP: f32 = 1.0; // in seconds
+
+// Edits a level with the mouse:
+//
+//     left click        add a vertex, or drag the one under the mouse
+//     right click       delete the vertex under the mouse
+//     P                 make the segment under the mouse a pad, or remove it
+//     1 - 9             multiplier of the pad under the mouse
+//     L                 put the lander spawn at the mouse
+//     Up / Down         more or less starting fuel
+//
+// The first and last vertex stay at the left and right edge of the window
+// and pads are kept flat, so the edited level is always a valid one.
+pub struct Editor {
+    level: Level,
+    path: PathBuf,
+    default_fuel: f32, // shown until the level sets its own fuel
+    mouse: Vec2d,
+    drag: Option<usize>, // index of the vertex being dragged
+}
+
+impl Editor {
+    pub fn new(level: Level, path: PathBuf, default_fuel: f32) -> Self {
+        Editor {
+            level,
+            path,
+            default_fuel,
+            mouse: Vec2d::default(),
+            drag: None,
+        }
+    }
+
+    // Flat ground over the whole window
+    pub fn empty_level() -> Level {
+        let ground = window_height as f32 - 100.0;
+        Level::new(
+            Body::Moon,
+            vec![Vec2d::new(0.0, ground), Vec2d::new(window_width as f32, ground)],
+        )
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        self.level.save(&self.path)
+    }
+
+    // Mouse positions are expected in virtual resolution, see viewport
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                self.mouse = Vec2d::new(*x as f32, *y as f32);
+                if let Some(idx) = self.drag {
+                    self.move_vertex(idx, self.mouse);
+                }
+            }
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } => {
+                self.mouse = Vec2d::new(*x as f32, *y as f32);
+                match mouse_btn {
+                    MouseButton::Left => match self.vertex_at(self.mouse) {
+                        Some(idx) => self.drag = Some(idx),
+                        None => self.add_vertex(self.mouse),
+                    },
+                    MouseButton::Right => {
+                        if let Some(idx) = self.vertex_at(self.mouse) {
+                            self.delete_vertex(idx);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.drag = None,
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::P => self.toggle_pad(self.mouse.x),
+                Keycode::L => self.level.spawn = self.mouse,
+                Keycode::Up => self.change_fuel(FUEL_STEP),
+                Keycode::Down => self.change_fuel(-FUEL_STEP),
+                _ => {
+                    if let Some(multiplier) = multiplier(*keycode) {
+                        self.set_multiplier(self.mouse.x, multiplier);
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    pub fn fuel(&self) -> f32 {
+        self.level.fuel.unwrap_or(self.default_fuel)
+    }
+
+    fn change_fuel(&mut self, delta: f32) {
+        self.level.fuel = Some((self.fuel() + delta).max(0.0));
+    }
+
+    fn vertex_at(&self, position: Vec2d) -> Option<usize> {
+        self.level
+            .points
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| (idx, (*p - position).len()))
+            .filter(|(_, distance)| *distance <= GRAB_RADIUS)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(idx, _)| idx)
+    }
+
+    // Index of the vertex left of x
+    fn segment_at(&self, x: f32) -> Option<usize> {
+        let points = &self.level.points;
+        (0..points.len() - 1).find(|idx| x >= points[*idx].x && x < points[idx + 1].x)
+    }
+
+    fn add_vertex(&mut self, position: Vec2d) {
+        let position = clamp_to_window(position);
+        let points = &mut self.level.points;
+        let idx = points.partition_point(|p| p.x < position.x);
+        if idx == 0 || idx == points.len() || points[idx].x == position.x {
+            return;
+        }
+        points.insert(idx, position);
+        self.fix_pads();
+    }
+
+    // Vertices can not pass their neighbours, the outer ones only move up
+    // and down
+    fn move_vertex(&mut self, idx: usize, position: Vec2d) {
+        let position = clamp_to_window(position);
+        let points = &mut self.level.points;
+        let old = points[idx];
+        let x = if idx == 0 || idx == points.len() - 1 {
+            old.x
+        } else {
+            position.x.clamp(points[idx - 1].x + 1.0, points[idx + 1].x - 1.0)
+        };
+        points[idx] = Vec2d::new(x, position.y);
+        // Pads move along with their ends
+        for pad in self.level.pads.iter_mut() {
+            if pad.left == old.x {
+                pad.left = x;
+            }
+            if pad.right == old.x {
+                pad.right = x;
+            }
+        }
+        // The dragged vertex decides the height of its pad
+        if let Some(pad) = self.level.pads.iter().find(|p| p.contains(x)).copied() {
+            for point in self.level.points.iter_mut().filter(|p| pad.contains(p.x)) {
+                point.y = position.y;
+            }
+        }
+        self.fix_pads();
+    }
+
+    fn delete_vertex(&mut self, idx: usize) {
+        let points = &mut self.level.points;
+        if idx == 0 || idx == points.len() - 1 {
+            return;
+        }
+        points.remove(idx);
+        self.fix_pads();
+    }
+
+    fn toggle_pad(&mut self, x: f32) {
+        let idx = match self.segment_at(x) {
+            Some(idx) => idx,
+            None => return,
+        };
+        let (left, right) = (self.level.points[idx], self.level.points[idx + 1]);
+        let before = self.level.pads.len();
+        self.level.pads.retain(|p| !p.contains((left.x + right.x) / 2.0));
+        if self.level.pads.len() == before {
+            self.level.pads.push(Pad {
+                left: left.x,
+                right: right.x,
+                multiplier: DEFAULT_MULTIPLIER,
+            });
+            self.fix_pads();
+        }
+    }
+
+    fn set_multiplier(&mut self, x: f32, multiplier: u32) {
+        if let Some(pad) = self.level.pads.iter_mut().find(|p| p.contains(x)) {
+            pad.multiplier = multiplier;
+        }
+    }
+
+    // Drops pads that lost one of their ends and flattens the others to
+    // the height of their left end
+    fn fix_pads(&mut self) {
+        let points = &mut self.level.points;
+        self.level.pads.retain(|pad| {
+            points.iter().any(|p| p.x == pad.left) && points.iter().any(|p| p.x == pad.right)
+        });
+        for pad in self.level.pads.iter() {
+            let height = points.iter().find(|p| p.x == pad.left).unwrap().y;
+            for point in points.iter_mut().filter(|p| pad.contains(p.x)) {
+                point.y = height;
+            }
+        }
+    }
+
+    pub fn render(&self, canvas: &mut Canvas<Window>) {
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let level = &self.level;
+        draw::draw_lines(canvas, &level.points, level.body.palette().terrain, false).unwrap();
+
+        for pad in level.pads.iter() {
+            let height = level.points.iter().find(|p| p.x == pad.left).map_or(0.0, |p| p.y);
+            let (left, right) = (Vec2d::new(pad.left, height), Vec2d::new(pad.right, height));
+            draw::draw_line(canvas, &left, &right, Color::RGB(0, 255, 0)).unwrap();
+            let center = Point::new(((pad.left + pad.right) / 2.0) as i32, height as i32 + 4);
+            draw::draw_text_centered(canvas, &format!("x{}", pad.multiplier), 12, center, Color::RGB(0, 255, 0))
+                .unwrap();
+        }
+        for hazard in level.hazards.iter() {
+            let (width, height) = (hazard.size.x as u32, hazard.size.y as u32);
+            draw::draw_rect::<u32>(canvas, &hazard.position, width, height, Color::RGB(255, 0, 0), false).unwrap();
+        }
+
+        let hovered = self.drag.or(self.vertex_at(self.mouse));
+        for (idx, point) in level.points.iter().enumerate() {
+            let color = if Some(idx) == hovered {
+                Color::RGB(255, 255, 0)
+            } else {
+                Color::RGB(0, 160, 255)
+            };
+            let half = (VERTEX_SIZE / 2) as f32;
+            let corner = *point - Vec2d::new(half, half);
+            draw::draw_rect::<u32>(canvas, &corner, VERTEX_SIZE, VERTEX_SIZE, color, true).unwrap();
+        }
+
+        // The lander as it starts, facing up
+        let transform = TransformationMatrix::translation_v(level.spawn)
+            * TransformationMatrix::rotate(PI)
+            * TransformationMatrix::scale(graphics::LanderScale.x, graphics::LanderScale.y);
+        for part in [&graphics::LanderTop, &graphics::LanderMiddle, &graphics::LanderBottom] {
+            let geometry = transform.transform_many(&part.to_vec());
+            draw::draw_lines(canvas, &geometry, Color::RGB(0, 160, 255), true).unwrap();
+        }
+        if level.velocity.len() > 0.0 {
+            let tip = level.spawn + level.velocity;
+            draw::draw_line(canvas, &level.spawn, &tip, Color::RGB(0, 160, 255)).unwrap();
+        }
+
+        let status = format!(
+            "{}  fuel {:.0}  {} vertices  {} pads",
+            self.path.display(),
+            self.fuel(),
+            level.points.len(),
+            level.pads.len()
+        );
+        let help = "click: add/drag  right click: delete  P: pad  1-9: multiplier  L: spawn  Up/Down: fuel";
+        let keys = "ENTER: test fly   S: save   ESC: menu";
+        let green = Color::RGB(0, 255, 0);
+        draw::draw_text(canvas, &status, 12, Point::new(4, 2), green).unwrap();
+        draw::draw_text(canvas, help, 10, Point::new(4, 18), green).unwrap();
+        draw::draw_text(canvas, keys, 10, Point::new(4, 30), green).unwrap();
+    }
+}
+
+fn clamp_to_window(position: Vec2d) -> Vec2d {
+    Vec2d::new(
+        position.x.clamp(0.0, window_width as f32),
+        position.y.clamp(0.0, window_height as f32),
+    )
+}
+
+fn multiplier(key: Keycode) -> Option<u32> {
+    let multiplier = match key {
+        Keycode::Num1 | Keycode::Kp1 => 1,
+        Keycode::Num2 | Keycode::Kp2 => 2,
+        Keycode::Num3 | Keycode::Kp3 => 3,
+        Keycode::Num4 | Keycode::Kp4 => 4,
+        Keycode::Num5 | Keycode::Kp5 => 5,
+        Keycode::Num6 | Keycode::Kp6 => 6,
+        Keycode::Num7 | Keycode::Kp7 => 7,
+        Keycode::Num8 | Keycode::Kp8 => 8,
+        Keycode::Num9 | Keycode::Kp9 => 9,
+        _ => return None,
+    };
+    Some(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::editor::Editor;
+    use crate::level::Level;
+    use crate::vecmath::Vec2d;
+
+    fn editor() -> Editor {
+        Editor::new(Editor::empty_level(), PathBuf::from("test.txt"), 20.0)
+    }
+
+    #[test]
+    fn vertices_are_added_in_order() {
+        let mut e = editor();
+        e.add_vertex(Vec2d::new(300.0, 400.0));
+        e.add_vertex(Vec2d::new(100.0, 450.0));
+        let xs: Vec<f32> = e.level.points.iter().map(|p| p.x).collect();
+        assert_eq!(xs, [0.0, 100.0, 300.0, 800.0]);
+        // The window edges already have their vertices
+        e.add_vertex(Vec2d::new(-50.0, 400.0));
+        assert_eq!(e.level.points.len(), 4);
+    }
+
+    #[test]
+    fn vertices_can_be_dragged_and_deleted() {
+        let mut e = editor();
+        e.add_vertex(Vec2d::new(300.0, 400.0));
+        assert_eq!(e.vertex_at(Vec2d::new(302.0, 403.0)), Some(1));
+        e.move_vertex(1, Vec2d::new(900.0, 350.0));
+        assert_eq!(e.level.points[1], Vec2d::new(799.0, 350.0));
+        e.move_vertex(0, Vec2d::new(100.0, 300.0));
+        assert_eq!(e.level.points[0], Vec2d::new(0.0, 300.0));
+        e.delete_vertex(0);
+        e.delete_vertex(1);
+        assert_eq!(e.level.points.len(), 2);
+    }
+
+    #[test]
+    fn pads_stay_flat() {
+        let mut e = editor();
+        e.add_vertex(Vec2d::new(300.0, 400.0));
+        e.add_vertex(Vec2d::new(360.0, 450.0));
+        e.toggle_pad(330.0);
+        e.set_multiplier(330.0, 5);
+        assert_eq!(e.level.pads.len(), 1);
+        assert_eq!(e.level.pads[0].multiplier, 5);
+        assert_eq!(e.level.points[2].y, 400.0);
+
+        e.move_vertex(2, Vec2d::new(370.0, 420.0));
+        assert_eq!((e.level.pads[0].right, e.level.points[1].y), (370.0, 420.0));
+        // Only valid levels come out of the editor
+        let saved = Level::from_text(&e.level.to_text()).unwrap();
+        assert_eq!(saved.pads, e.level.pads);
+
+        e.delete_vertex(1);
+        assert!(e.level.pads.is_empty());
+        e.toggle_pad(330.0);
+        e.toggle_pad(330.0);
+        assert!(e.level.pads.is_empty());
+    }
+
+    #[test]
+    fn fuel_starts_from_the_config() {
+        let mut e = editor();
+        assert_eq!(e.fuel(), 20.0);
+        e.change_fuel(-25.0);
+        assert_eq!(e.level.fuel, Some(0.0));
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::mainloop::{SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+use crate::planet::Body;
+use crate::replay::Replay;
+use crate::simulation::{Pose, State, World};
+use crate::vecmath::Vec2d;
+use crate::{window_height, window_width};
+
+// The personal best of a seed, flown again from its replay. Knows where
+// its lander was after every tick, so it can be drawn next to the player
+// and tell how far ahead or behind the player is.
+pub struct Ghost {
+    poses: Vec<Pose>, // the first before any tick, the last at touch down
+    remaining: Vec<f32>, // distance to the touch down spot, by tick
+}
+
+impl Ghost {
+    pub fn from_replay(replay: &Replay) -> Self {
+        let mut world = World::new(window_width, window_height, replay.seed, replay.config, replay.body);
+        let mut poses = vec![world.pose_of(0)];
+        for controls in replay.controls() {
+            if *world.state() != State::Running {
+                break;
+            }
+            world.apply_controls(*controls);
+            world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+            poses.push(world.pose_of(0));
+        }
+        let finish = poses.last().unwrap().position;
+        let remaining = poses.iter().map(|pose| (pose.position - finish).len()).collect();
+        Ghost { poses, remaining }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Ok(Self::from_replay(&Replay::load(path)?))
+    }
+
+    // Ticks the run took to land
+    pub fn ticks(&self) -> usize {
+        self.poses.len() - 1
+    }
+
+    // Once landed the ghost stays on its pad
+    pub fn pose_at(&self, tick: usize) -> Pose {
+        self.poses[tick.min(self.poses.len() - 1)]
+    }
+
+    // Seconds the player at the position after that many ticks is behind
+    // the ghost, negative when ahead. Compares with the first tick the
+    // ghost was as close to its touch down spot.
+    pub fn delta_at(&self, tick: usize, position: Vec2d) -> f32 {
+        let remaining = (position - self.poses.last().unwrap().position).len();
+        let ghost_tick = self.remaining.iter().position(|r| *r <= remaining).unwrap_or(self.ticks());
+        (tick as f32 - ghost_tick as f32) * SIM_TICK_MS / 1000.0
+    }
+}
+
+// Where the ghost of a generated level lives. Another config can mean
+// other terrain and other physics, so every config has ghosts of its own.
+pub fn path_for(dir: &Path, seed: u64, body: Body, config: &Config) -> PathBuf {
+    dir.join(format!("ghost-{}-{}-{:016x}.txt", body.name(), seed, config_hash(config)))
+}
+
+// FNV-1a over the bits of every value
+fn config_hash(config: &Config) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (_, value) in config.values() {
+        for byte in value.to_bits().to_le_bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+// Keeps the run of a landing as the ghost of its level if it landed
+// quicker than the ghost there. Returns whether it did.
+pub fn save_if_best(dir: &Path, run: &Replay) -> Result<bool, String> {
+    let path = path_for(dir, run.seed, run.body, &run.config);
+    if let Ok(best) = Replay::load(&path) {
+        if best.config == run.config && best.controls().len() <= run.controls().len() {
+            return Ok(false);
+        }
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    run.save(&path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::config::{Config, Difficulty};
+    use crate::control::{Autopilot, Controller};
+    use crate::ghost::*;
+    use crate::mainloop::SIM_TICK_MS;
+    use crate::simulation::SPAWN_POSITION;
+
+    // The autopilot lands the level
+    fn landing(seed: u64) -> Replay {
+        let mut world = World::new(window_width, window_height, seed, Config::default(), Body::Moon);
+        let mut replay = Replay::new(seed, Config::default(), Body::Moon);
+        let mut pilot = Autopilot::new();
+        while *world.state() == State::Running && replay.controls().len() < 5000 {
+            world.apply_controls(pilot.control(&world.observe(), SIM_TICK_MS / 1000.0));
+            replay.record(world.controls());
+            world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+        }
+        assert_eq!(*world.state(), State::Won);
+        replay
+    }
+
+    #[test]
+    fn ghosts_fly_the_replay_again() {
+        let replay = landing(4);
+        let ghost = Ghost::from_replay(&replay);
+        assert_eq!(ghost.ticks(), replay.controls().len());
+        assert_eq!(ghost.pose_at(0).position, SPAWN_POSITION);
+        let landed = ghost.pose_at(ghost.ticks());
+        assert_eq!(ghost.pose_at(ghost.ticks() + 100), landed);
+        assert_eq!(landed.throttle, 0.0);
+
+        // On the ghost's track the player is as quick as the ghost
+        assert_eq!(ghost.delta_at(0, SPAWN_POSITION), 0.0);
+        let halfway = ghost.ticks() / 2;
+        let position = ghost.pose_at(halfway).position;
+        assert!(ghost.delta_at(halfway, position) <= 0.0);
+        assert!(ghost.delta_at(halfway + 20, position) > 0.0);
+    }
+
+    #[test]
+    fn only_quicker_landings_replace_the_ghost() {
+        let dir = std::env::temp_dir().join(format!("lunar-ghosts-{}", std::process::id()));
+        let quick = landing(4);
+        let mut slow = quick.clone();
+        slow.record(*quick.controls().last().unwrap());
+
+        assert_eq!(save_if_best(&dir, &slow), Ok(true));
+        assert_eq!(save_if_best(&dir, &slow), Ok(false));
+        assert_eq!(save_if_best(&dir, &quick), Ok(true));
+        assert_eq!(save_if_best(&dir, &slow), Ok(false));
+        let best = Replay::load(&path_for(&dir, 4, Body::Moon, &Config::default())).unwrap();
+        assert_eq!(best, quick);
+
+        // A slow landing on easy does not compete with the normal ones
+        let mut easy = slow.clone();
+        easy.config = Difficulty::Easy.apply(Config::default());
+        assert_eq!(save_if_best(&dir, &easy), Ok(true));
+        assert_eq!(Replay::load(&path_for(&dir, 4, Body::Moon, &Config::default())).unwrap(), quick);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
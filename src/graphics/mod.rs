@@ -44,11 +44,28 @@ pub const BBox: [Vec2d; 4] = [
     Vec2d::new(-3.0, -3.0),
 ];
 
+// Outline of the crew cabin and the engine block. Any contact of these
+// with the terrain is a crash, only the feet of the legs may touch down.
+pub const LanderHull: [Vec2d; 10] = [
+    Vec2d::new(-1.0, 4.0),
+    Vec2d::new(1.0, 4.0),
+    Vec2d::new(2.0, 3.0),
+    Vec2d::new(3.0, 0.0),
+    Vec2d::new(2.0, 0.0),
+    Vec2d::new(2.0, -2.0),
+    Vec2d::new(-2.0, -2.0),
+    Vec2d::new(-2.0, 0.0),
+    Vec2d::new(-3.0, 0.0),
+    Vec2d::new(-2.0, 3.0),
+];
 
 pub const LeftLeg: [Vec2d; 2] = [Vec2d::new(-2.0, 0.0), Vec2d::new(-3.0, -3.0)];
 
 pub const RightLeg: [Vec2d; 2] = [Vec2d::new(2.0, 0.0), Vec2d::new(3.0, -3.0)];
 
+// Where the exhaust leaves the engine
+pub const LanderNozzle: Vec2d = Vec2d::new(0.0, -3.0);
+
 pub const FlameA: [Vec2d; 3] = [
     Vec2d::new(-1.0, -3.0),
     Vec2d::new(1.0, -3.0),
@@ -0,0 +1,312 @@
+use std::f32::consts::PI;
+
+use crate::config::Config;
+use crate::mainloop::{SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+use crate::planet::Body;
+use crate::simulation::{Controls, State, World};
+use crate::{window_height, window_width};
+
+// Length of the feature vector an agent observes
+pub const OBSERVATION_SIZE: usize = 8;
+
+// What the discrete actions do: nothing, thrust, rotate left, rotate
+// right and thrust while rotating either way
+pub const DISCRETE_ACTIONS: [Controls; 6] = [
+    Controls {
+        throttle: 0.0,
+        rotation: 0.0,
+    },
+    Controls {
+        throttle: 1.0,
+        rotation: 0.0,
+    },
+    Controls {
+        throttle: 0.0,
+        rotation: -1.0,
+    },
+    Controls {
+        throttle: 0.0,
+        rotation: 1.0,
+    },
+    Controls {
+        throttle: 1.0,
+        rotation: -1.0,
+    },
+    Controls {
+        throttle: 1.0,
+        rotation: 1.0,
+    },
+];
+
+// An episode that still flies after this many steps is cut off, a minute
+// of game time
+const DEFAULT_MAX_STEPS: u32 = 60 * 1000 / SIM_TICK_MS as u32;
+
+// Scaled lander state, all values roughly within -1.0 - 1.0:
+// distance to the nearest pad center, altitude, horizontal and vertical
+// speed, tilt, fuel left, grounded and gravity relative to the thrust
+pub type Features = [f32; OBSERVATION_SIZE];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Discrete(usize), // index into DISCRETE_ACTIONS, panics beyond it
+    Continuous { throttle: f32, rotation: f32 },
+}
+
+impl Action {
+    pub fn controls(&self) -> Controls {
+        match *self {
+            Action::Discrete(idx) => DISCRETE_ACTIONS[idx],
+            Action::Continuous { throttle, rotation } => Controls {
+                throttle: throttle.clamp(0.0, 1.0),
+                rotation: rotation.clamp(-1.0, 1.0),
+            },
+        }
+    }
+}
+
+// Weights of the reward. Every step pays for the burnt fuel and for the
+// change of the shaping terms, so getting closer to the pad, slower and
+// more upright earns reward and moving away gives it back. The episode
+// ends with the landed or crashed reward, the first touchdown pays for
+// its speed and tilt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rewards {
+    pub distance: f32,  // per pixel to the nearest pad center
+    pub speed: f32,     // per pixel per second
+    pub tilt: f32,      // per radian away from upright
+    pub fuel: f32,      // per second of fuel burnt
+    pub touchdown: f32, // per pixel per second and radian at touchdown
+    pub landed: f32,
+    pub crashed: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Rewards {
+            distance: 0.5,
+            speed: 0.4,
+            tilt: 10.0,
+            fuel: 3.0,
+            touchdown: 2.0,
+            landed: 100.0,
+            crashed: -100.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Info {
+    pub state: State,
+    pub score: u32,
+    pub steps: u32,
+    pub truncated: bool, // cut off by the step limit instead of ending
+}
+
+// A headless World for training agents, in the style of a Gym
+// environment. Every step is one simulation tick of the game, so agents
+// fly the exact physics of the game.
+pub struct Env {
+    world: World,
+    config: Config,
+    body: Body,
+    rewards: Rewards,
+    max_steps: u32,
+    steps: u32,
+    shaping: f32,
+    touched_down: bool,
+}
+
+impl Env {
+    // Ready for the first episode, on the terrain of seed 0
+    pub fn new(config: Config, body: Body) -> Self {
+        let world = World::new(window_width, window_height, 0, config, body);
+        let mut env = Env {
+            world,
+            config,
+            body,
+            rewards: Rewards::default(),
+            max_steps: DEFAULT_MAX_STEPS,
+            steps: 0,
+            shaping: 0.0,
+            touched_down: false,
+        };
+        env.shaping = env.shaping();
+        env
+    }
+
+    pub fn set_rewards(&mut self, rewards: Rewards) {
+        self.rewards = rewards;
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    // For a look at everything the features leave out
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    // Starts a new episode on the terrain of the seed
+    pub fn reset(&mut self, seed: u64) -> Features {
+        self.world = World::new(window_width, window_height, seed, self.config, self.body);
+        self.steps = 0;
+        self.touched_down = false;
+        self.shaping = self.shaping();
+        self.features()
+    }
+
+    // Flies one tick. Returns the features afterwards, the reward for the
+    // step, whether the episode is over and how it went.
+    pub fn step(&mut self, action: Action) -> (Features, f32, bool, Info) {
+        let before = self.world.observe();
+        let (fuel, velocity, tilt) = (before.fuel, before.velocity, before.orientation - PI / 2.0);
+
+        self.world.apply_controls(action.controls());
+        self.world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+        self.steps += 1;
+
+        let after = self.world.observe();
+        let mut reward = -self.rewards.fuel * (fuel - after.fuel);
+        let shaping = self.shaping();
+        reward += shaping - self.shaping;
+        self.shaping = shaping;
+        if after.grounded && !self.touched_down {
+            self.touched_down = true;
+            reward -= self.rewards.touchdown * (velocity.len() + tilt.abs());
+        }
+
+        let state = *self.world.state();
+        reward += match state {
+            State::Running => 0.0,
+            State::Won => self.rewards.landed,
+            State::Lost => self.rewards.crashed,
+        };
+        let truncated = state == State::Running && self.steps >= self.max_steps;
+        let info = Info {
+            state,
+            score: self.world.score(),
+            steps: self.steps,
+            truncated,
+        };
+        (self.features(), reward, state != State::Running || truncated, info)
+    }
+
+    pub fn features(&self) -> Features {
+        let o = self.world.observe();
+        let dx = o.nearest_pad().map_or(0.0, |pad| pad.center() - o.position.x);
+        [
+            dx / window_width as f32,
+            o.altitude / window_height as f32,
+            o.velocity.x / 50.0,
+            o.velocity.y / 50.0,
+            o.orientation - PI / 2.0,
+            o.fuel / self.config.lander.starting_fuel.max(1e-3),
+            if o.grounded { 1.0 } else { 0.0 },
+            o.gravity / o.thrust.max(1e-3),
+        ]
+    }
+
+    fn shaping(&self) -> f32 {
+        let o = self.world.observe();
+        let distance = o.nearest_pad().map_or(0.0, |pad| (pad.center() - o.position.x).abs());
+        -self.rewards.distance * distance
+            - self.rewards.speed * o.velocity.len()
+            - self.rewards.tilt * (o.orientation - PI / 2.0).abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::control::{Autopilot, Controller};
+    use crate::gym::*;
+
+    #[test]
+    fn seeds_repeat_their_episodes() {
+        let mut env = Env::new(Config::default(), Body::Moon);
+        let fly = |env: &mut Env| {
+            let mut features = env.reset(7);
+            for idx in 0..100 {
+                features = env.step(Action::Discrete(idx % DISCRETE_ACTIONS.len())).0;
+            }
+            features
+        };
+        let first = fly(&mut env);
+        assert_eq!(first, fly(&mut env));
+    }
+
+    #[test]
+    fn burning_fuel_costs_reward() {
+        let mut env = Env::new(Config::default(), Body::Moon);
+        env.set_rewards(Rewards {
+            distance: 0.0,
+            speed: 0.0,
+            tilt: 0.0,
+            ..Rewards::default()
+        });
+        env.reset(1);
+        let (_, idle, _, _) = env.step(Action::Continuous {
+            throttle: 0.0,
+            rotation: 0.0,
+        });
+        let (_, thrust, _, _) = env.step(Action::Discrete(1));
+        assert_eq!(idle, 0.0);
+        assert!(thrust < 0.0);
+    }
+
+    #[test]
+    fn free_fall_crashes() {
+        let mut env = Env::new(Config::default(), Body::Moon);
+        env.reset(3);
+        let mut total = 0.0;
+        loop {
+            let (_, reward, done, info) = env.step(Action::Discrete(0));
+            total += reward;
+            if done {
+                assert_eq!(info.state, State::Lost);
+                assert!(!info.truncated);
+                break;
+            }
+        }
+        assert!(total < -100.0);
+    }
+
+    #[test]
+    fn landings_pay_off() {
+        let mut env = Env::new(Config::default(), Body::Moon);
+        env.reset(1);
+        let mut pilot = Autopilot::new();
+        let mut total = 0.0;
+        loop {
+            let controls = pilot.control(&env.world().observe(), SIM_TICK_MS / 1000.0);
+            let (_, reward, done, info) = env.step(Action::Continuous {
+                throttle: controls.throttle,
+                rotation: controls.rotation,
+            });
+            total += reward;
+            if done {
+                assert_eq!(info.state, State::Won);
+                assert!(info.score > 0);
+                break;
+            }
+        }
+        assert!(total > 0.0);
+    }
+
+    #[test]
+    fn long_episodes_are_cut_off() {
+        let mut config = Config::default();
+        config.physics.gravity = 0.0;
+        let mut env = Env::new(config, Body::Moon);
+        env.set_max_steps(10);
+        env.reset(1);
+        for _ in 0..9 {
+            assert!(!env.step(Action::Discrete(0)).2);
+        }
+        let (_, _, done, info) = env.step(Action::Discrete(0));
+        assert!(done && info.truncated);
+        assert_eq!(info.steps, 10);
+    }
+}
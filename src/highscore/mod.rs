@@ -0,0 +1,274 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::daily::Date;
+
+const MAX_ENTRIES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub score: u32,
+    pub seed: u64,
+}
+
+// The daily challenge of a date, best score 0 until it was landed
+#[derive(Clone, Debug, PartialEq)]
+pub struct DailyEntry {
+    pub date: Date,
+    pub score: u32,
+    pub attempts: u32,
+}
+
+// The best landings, sorted from best to worst, and the results of the
+// daily challenges. If the list was loaded from a file, every new entry is
+// written back to it.
+pub struct HighScores {
+    path: Option<PathBuf>,
+    entries: Vec<Entry>,
+    daily: Vec<DailyEntry>,
+    problem: Option<String>, // of the last load or save, until taken
+}
+
+impl Default for HighScores {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HighScores {
+    // Keeps the scores in memory only
+    pub fn new() -> Self {
+        HighScores {
+            path: None,
+            entries: Vec::new(),
+            daily: Vec::new(),
+            problem: None,
+        }
+    }
+
+    // A missing or unreadable file simply starts a new list
+    pub fn load(path: &Path) -> Self {
+        let mut scores = HighScores {
+            path: Some(path.to_path_buf()),
+            entries: Vec::new(),
+            daily: Vec::new(),
+            problem: None,
+        };
+        if let Ok(content) = fs::read_to_string(path) {
+            for (idx, line) in content.lines().enumerate() {
+                let parsed = match line.strip_prefix("daily ") {
+                    Some(daily) => parse_daily(daily).map(|entry| scores.daily.push(entry)),
+                    None => parse_line(line).map(|entry| scores.entries.push(entry)),
+                };
+                if parsed.is_none() && scores.problem.is_none() {
+                    scores.problem = Some(format!("{}:{}: ignoring broken high score entry", path.display(), idx + 1));
+                }
+            }
+        }
+        scores.sort();
+        scores
+    }
+
+    // The first broken entry of the file or the last failed save, once
+    pub fn take_problem(&mut self) -> Option<String> {
+        self.problem.take()
+    }
+
+    pub fn entries(&self) -> &Vec<Entry> {
+        &self.entries
+    }
+
+    // Returns the rank of the new entry, None if it did not make it
+    // into the list.
+    pub fn add(&mut self, score: u32, seed: u64) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        self.entries.push(Entry { score, seed });
+        self.sort();
+        let rank = self.entries.iter().position(|e| e.score == score && e.seed == seed);
+        if rank.is_some() {
+            self.save_or_report();
+        }
+        rank
+    }
+
+    pub fn daily(&self, date: Date) -> Option<&DailyEntry> {
+        self.daily.iter().find(|e| e.date == date)
+    }
+
+    // Counts an attempt at the challenge of the date, returns the attempts
+    // so far
+    pub fn add_daily_attempt(&mut self, date: Date) -> u32 {
+        let attempts = match self.daily.iter_mut().find(|e| e.date == date) {
+            Some(entry) => {
+                entry.attempts += 1;
+                entry.attempts
+            }
+            None => {
+                self.daily.push(DailyEntry { date, score: 0, attempts: 1 });
+                1
+            }
+        };
+        self.save_or_report();
+        attempts
+    }
+
+    // Keeps the score of a landing on the challenge of the date if it is
+    // the best of the day. Returns whether it was.
+    pub fn add_daily_score(&mut self, date: Date, score: u32) -> bool {
+        let entry = match self.daily.iter_mut().find(|e| e.date == date) {
+            Some(entry) if entry.score < score => entry,
+            Some(_) => return false,
+            None => {
+                self.daily.push(DailyEntry { date, score: 0, attempts: 1 });
+                self.daily.last_mut().unwrap()
+            }
+        };
+        entry.score = score;
+        self.save_or_report();
+        true
+    }
+
+    fn sort(&mut self) {
+        // stable, so older entries win ties
+        self.entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    fn save_or_report(&mut self) {
+        if let Err(e) = self.save() {
+            self.problem = Some(format!("Could not save high scores: {}", e));
+        }
+    }
+
+    // "score seed" per entry, then "daily date score attempts" per day
+    fn save(&self) -> Result<(), String> {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut content: String = self
+            .entries
+            .iter()
+            .map(|e| format!("{} {}\n", e.score, e.seed))
+            .collect();
+        for e in &self.daily {
+            content += &format!("daily {} {} {}\n", e.date, e.score, e.attempts);
+        }
+        fs::write(path, content).map_err(|e| e.to_string())
+    }
+}
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let mut parts = line.split_whitespace();
+    let score = parts.next()?.parse().ok()?;
+    let seed = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Entry { score, seed })
+}
+
+fn parse_daily(line: &str) -> Option<DailyEntry> {
+    let mut parts = line.split_whitespace();
+    let date = Date::parse(parts.next()?).ok()?;
+    let score = parts.next()?.parse().ok()?;
+    let attempts = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(DailyEntry { date, score, attempts })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highscore::*;
+
+    #[test]
+    fn entries_are_sorted_and_limited() {
+        let mut scores = HighScores::new();
+        for idx in 0..(MAX_ENTRIES as u32 + 5) {
+            scores.add(100 + idx, idx as u64);
+        }
+        assert_eq!(scores.entries().len(), MAX_ENTRIES);
+        assert_eq!(scores.entries()[0].score, 100 + MAX_ENTRIES as u32 + 4);
+        assert_eq!(scores.add(1, 99), None);
+        assert_eq!(scores.add(1000, 99), Some(0));
+    }
+
+    #[test]
+    fn zero_is_not_a_high_score() {
+        let mut scores = HighScores::new();
+        assert_eq!(scores.add(0, 1), None);
+        assert!(scores.entries().is_empty());
+    }
+
+    #[test]
+    fn scores_survive_a_reload() {
+        let path = std::env::temp_dir().join(format!("lunar_highscores_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        {
+            let mut scores = HighScores::load(&path);
+            scores.add(500, 7);
+            scores.add(700, 8);
+        }
+        let scores = HighScores::load(&path);
+        assert_eq!(
+            scores.entries(),
+            &vec![Entry { score: 700, seed: 8 }, Entry { score: 500, seed: 7 }]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn problems_are_kept_for_the_app_to_show() {
+        let path = std::env::temp_dir().join(format!("lunar_highscores_broken_{}.txt", std::process::id()));
+        fs::write(&path, "500 7\nlots 8\n300 9\n").unwrap();
+        let mut scores = HighScores::load(&path);
+        assert_eq!(scores.entries().len(), 2);
+        assert_eq!(scores.take_problem(), Some(format!("{}:2: ignoring broken high score entry", path.display())));
+        assert_eq!(scores.take_problem(), None);
+        fs::remove_file(&path).unwrap();
+
+        // A directory can not be written like a file
+        let mut scores = HighScores::load(&std::env::temp_dir());
+        scores.add(500, 7);
+        assert!(scores.take_problem().unwrap().starts_with("Could not save high scores: "));
+    }
+
+    #[test]
+    fn broken_lines_are_skipped() {
+        assert_eq!(parse_line("12 34"), Some(Entry { score: 12, seed: 34 }));
+        assert_eq!(parse_line("12"), None);
+        assert_eq!(parse_line("x 34"), None);
+        assert_eq!(parse_line("12 34 56"), None);
+        assert_eq!(parse_daily("2026-02-30 12 1"), None);
+        assert_eq!(parse_daily("2026-02-28 12"), None);
+    }
+
+    #[test]
+    fn daily_results_are_kept_by_date() {
+        let path = std::env::temp_dir().join(format!("lunar_daily_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let (today, yesterday) = (Date::parse("2026-10-18").unwrap(), Date::parse("2026-10-17").unwrap());
+        {
+            let mut scores = HighScores::load(&path);
+            scores.add(500, 7);
+            assert_eq!(scores.add_daily_attempt(today), 1);
+            assert!(scores.add_daily_score(today, 300));
+            assert_eq!(scores.add_daily_attempt(today), 2);
+            assert!(!scores.add_daily_score(today, 200));
+            scores.add_daily_attempt(yesterday);
+        }
+        let scores = HighScores::load(&path);
+        assert_eq!(scores.entries(), &vec![Entry { score: 500, seed: 7 }]);
+        assert_eq!(
+            scores.daily(today),
+            Some(&DailyEntry { date: today, score: 300, attempts: 2 })
+        );
+        assert_eq!(scores.daily(yesterday).unwrap().score, 0);
+        assert_eq!(scores.daily(Date::parse("2026-10-19").unwrap()), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
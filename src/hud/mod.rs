@@ -1,3 +1,4 @@
+use crate::config::{Config, HudConfig};
 use crate::draw;
 use crate::vecmath::Vec2d;
 use sdl2::pixels::Color;
@@ -10,6 +11,9 @@ pub struct Hud {
     direction: Vec2d,
     fuel: f32,
     deaths: u32,
+    thresholds: HudConfig,
+    body: &'static str,
+    wind: Option<f32>, // horizontal wind speed, None without atmosphere
 }
 
 impl Hud {
@@ -19,6 +23,9 @@ impl Hud {
             direction: Vec2d::new(0.0, 0.0),
             fuel: 0.0,
             deaths: 0,
+            thresholds: Config::default().hud,
+            body: "",
+            wind: None,
         }
     }
 
@@ -28,6 +35,9 @@ impl Hud {
             direction,
             fuel,
             deaths,
+            thresholds: Config::default().hud,
+            body: "",
+            wind: None,
         }
     }
 
@@ -38,6 +48,18 @@ impl Hud {
         self.deaths = deaths;
     }
 
+    pub fn set_thresholds(&mut self, thresholds: HudConfig) {
+        self.thresholds = thresholds;
+    }
+
+    pub fn set_body(&mut self, body: &'static str) {
+        self.body = body;
+    }
+
+    pub fn set_wind(&mut self, wind: Option<f32>) {
+        self.wind = wind;
+    }
+
     pub fn updatePosition(&mut self, position: Vec2d) {
         self.position = position;
     }
@@ -62,6 +84,9 @@ impl Hud {
         );
         let hud_fuel = format!("Fuel: {}", self.fuel);
         let hud_deaths = format!("Deaths: {}", self.deaths);
+        let hud_body = format!("Body: {}", self.body);
+        let direction_color = warn_color(self.direction.len() > self.thresholds.warn_speed);
+        let fuel_color = warn_color(self.fuel < self.thresholds.low_fuel);
 
         draw::draw_text(
             canvas,
@@ -76,7 +101,7 @@ impl Hud {
             &hud_direction,
             10,
             Point::new(0, 10),
-            Color::RGB(0, 255, 0),
+            direction_color,
         )
         .unwrap();
         draw::draw_text(
@@ -84,7 +109,7 @@ impl Hud {
             &hud_fuel,
             10,
             Point::new(0, 20),
-            Color::RGB(0, 255, 0),
+            fuel_color,
         )
         .unwrap();
         draw::draw_text(
@@ -95,5 +120,55 @@ impl Hud {
             Color::RGB(0, 255, 0),
         )
         .unwrap();
+        draw::draw_text(
+            canvas,
+            &hud_body,
+            10,
+            Point::new(0, 40),
+            Color::RGB(0, 255, 0),
+        )
+        .unwrap();
+        if let Some(wind) = self.wind {
+            draw::draw_text(
+                canvas,
+                &format!("Wind: {}", wind_indicator(wind)),
+                10,
+                Point::new(0, 50),
+                Color::RGB(0, 255, 0),
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn warn_color(warn: bool) -> Color {
+    if warn {
+        Color::RGB(255, 0, 0)
+    } else {
+        Color::RGB(0, 255, 0)
+    }
+}
+
+// An arrow pointing where the wind blows, one head per pixel per second,
+// followed by the speed
+fn wind_indicator(wind: f32) -> String {
+    let heads = (wind.abs().round() as usize).clamp(1, 5);
+    let arrow = if wind < 0.0 {
+        format!("{}-", "<".repeat(heads))
+    } else {
+        format!("-{}", ">".repeat(heads))
+    };
+    format!("{} {:.1}", arrow, wind.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hud::wind_indicator;
+
+    #[test]
+    fn wind_arrow_shows_direction_and_strength() {
+        assert_eq!(wind_indicator(2.2), "->> 2.2");
+        assert_eq!(wind_indicator(-0.1), "<- 0.1");
+        assert_eq!(wind_indicator(-40.0), "<<<<<- 40.0");
     }
 }
@@ -0,0 +1,335 @@
+use std::fs;
+use std::path::Path;
+
+use crate::map::PointList;
+use crate::planet::Body;
+use crate::simulation::SPAWN_POSITION;
+use crate::vecmath::Vec2d;
+
+mod reach;
+
+pub use reach::fuel_to_reach;
+
+const HEADER: &str = "lunar-level 1";
+
+// A hand-made level. Coordinates are in window pixels with y growing
+// downwards, like everything the World draws:
+//
+//     lunar-level 1
+//     # comment
+//     body Moon
+//     gravity 12.5
+//     spawn 200 100
+//     velocity 50 0
+//     fuel 15
+//     point 0 500
+//     point 120 450
+//     point 160 450
+//     point 800 520
+//     pad 120 160 2
+//     hazard 300 200 50 80
+//
+// Points run left to right. Gravity and fuel are optional and otherwise
+// come from the config.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Level {
+    pub body: Body,
+    pub gravity: Option<f32>, // in pixel per second squared, replaces the body's
+    pub spawn: Vec2d,
+    pub velocity: Vec2d,
+    pub fuel: Option<f32>, // in seconds
+    pub points: Vec<Vec2d>,
+    pub pads: Vec<Pad>,
+    pub hazards: Vec<Hazard>,
+}
+
+// A flat stretch of terrain that multiplies the score of a landing on it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pad {
+    pub left: f32,
+    pub right: f32,
+    pub multiplier: u32,
+}
+
+// A zone that destroys the lander as soon as the hull enters it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hazard {
+    pub position: Vec2d, // top left corner
+    pub size: Vec2d,
+}
+
+impl Pad {
+    // Pads without a bonus on the landing areas of a generated map
+    pub fn landing_areas(map: &PointList) -> Vec<Pad> {
+        map.landing_areas()
+            .into_iter()
+            .map(|(left, right)| Pad {
+                left,
+                right,
+                multiplier: 1,
+            })
+            .collect()
+    }
+
+    pub fn center(&self) -> f32 {
+        (self.left + self.right) / 2.0
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        x >= self.left && x <= self.right
+    }
+}
+
+impl Hazard {
+    pub fn contains(&self, point: Vec2d) -> bool {
+        let end = self.position + self.size;
+        point.x >= self.position.x && point.x <= end.x && point.y >= self.position.y && point.y <= end.y
+    }
+}
+
+impl Level {
+    pub fn new(body: Body, points: Vec<Vec2d>) -> Self {
+        Level {
+            body,
+            gravity: None,
+            spawn: SPAWN_POSITION,
+            velocity: Vec2d::default(),
+            fuel: None,
+            points,
+            pads: Vec::new(),
+            hazards: Vec::new(),
+        }
+    }
+
+    // Keeps a generated map. Its landing areas become pads without a bonus.
+    pub fn from_map(map: &PointList, body: Body) -> Self {
+        Level {
+            pads: Pad::landing_areas(map),
+            ..Self::new(body, map.get_values().clone())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_text(&text).map_err(|e| format!("{}:{}", path.display(), e))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\nbody {}\n", HEADER, self.body.name());
+        if let Some(gravity) = self.gravity {
+            text += &format!("gravity {}\n", gravity);
+        }
+        text += &format!("spawn {} {}\n", self.spawn.x, self.spawn.y);
+        text += &format!("velocity {} {}\n", self.velocity.x, self.velocity.y);
+        if let Some(fuel) = self.fuel {
+            text += &format!("fuel {}\n", fuel);
+        }
+        for point in self.points.iter() {
+            text += &format!("point {} {}\n", point.x, point.y);
+        }
+        for pad in self.pads.iter() {
+            text += &format!("pad {} {} {}\n", pad.left, pad.right, pad.multiplier);
+        }
+        for hazard in self.hazards.iter() {
+            text += &format!(
+                "hazard {} {} {} {}\n",
+                hazard.position.x, hazard.position.y, hazard.size.x, hazard.size.y
+            );
+        }
+        text
+    }
+
+    // Errors start with the line number and name the field, e.g.
+    // "7: invalid pad multiplier 'lots'"
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.trim() == HEADER => (),
+            _ => return Err(format!("1: expected '{}'", HEADER)),
+        }
+
+        let mut level = Level::new(Body::Moon, Vec::new());
+        // Pads are checked against the terrain once all points are read
+        let mut pad_lines = Vec::new();
+        for (idx, line) in lines {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap();
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [] => (),
+                ["body", name] => {
+                    level.body = Body::from_name(name).ok_or(format!("{}: invalid body '{}'", line_no, name))?
+                }
+                ["gravity", gravity] => level.gravity = Some(non_negative(gravity, line_no, "gravity")?),
+                ["spawn", x, y] => level.spawn = point(x, y, line_no, "spawn")?,
+                ["velocity", x, y] => level.velocity = point(x, y, line_no, "velocity")?,
+                ["fuel", fuel] => level.fuel = Some(non_negative(fuel, line_no, "fuel")?),
+                ["point", x, y] => {
+                    let point = point(x, y, line_no, "point")?;
+                    if level.points.last().is_some_and(|last| last.x >= point.x) {
+                        return Err(format!("{}: point x must grow from left to right", line_no));
+                    }
+                    level.points.push(point);
+                }
+                ["pad", left, right, multiplier] => {
+                    let pad = Pad {
+                        left: parse(left, line_no, "pad left")?,
+                        right: parse(right, line_no, "pad right")?,
+                        multiplier: parse(multiplier, line_no, "pad multiplier")?,
+                    };
+                    if pad.left >= pad.right {
+                        return Err(format!("{}: pad left must be less than pad right", line_no));
+                    }
+                    if pad.multiplier == 0 {
+                        return Err(format!("{}: invalid pad multiplier '{}'", line_no, multiplier));
+                    }
+                    pad_lines.push(line_no);
+                    level.pads.push(pad);
+                }
+                ["hazard", x, y, width, height] => {
+                    let hazard = Hazard {
+                        position: point(x, y, line_no, "hazard")?,
+                        size: Vec2d::new(
+                            parse(width, line_no, "hazard width")?,
+                            parse(height, line_no, "hazard height")?,
+                        ),
+                    };
+                    if hazard.size.x <= 0.0 || hazard.size.y <= 0.0 {
+                        return Err(format!("{}: hazard size must be positive", line_no));
+                    }
+                    level.hazards.push(hazard);
+                }
+                [key, ..] if usage(key).is_some() => {
+                    return Err(format!("{}: expected '{}'", line_no, usage(key).unwrap()))
+                }
+                _ => return Err(format!("{}: unexpected line '{}'", line_no, line.trim())),
+            }
+        }
+
+        if level.points.len() < 2 {
+            return Err(format!("{}: a level needs at least two points", text.lines().count()));
+        }
+        let map = PointList::from_points(level.points.clone());
+        for (pad, line_no) in level.pads.iter().zip(pad_lines) {
+            if !is_flat(&map, pad) {
+                return Err(format!("{}: pad {} - {} is not on flat terrain", line_no, pad.left, pad.right));
+            }
+        }
+        Ok(level)
+    }
+}
+
+// The terrain between both ends of the pad is one height
+fn is_flat(map: &PointList, pad: &Pad) -> bool {
+    let (left, right) = match (map.surface_at(pad.left), map.surface_at(pad.right)) {
+        (Some((left, _)), Some((right, _))) => (left, right),
+        _ => return false,
+    };
+    (left - right).abs() < 1e-3
+        && map
+            .get_values()
+            .iter()
+            .filter(|p| pad.contains(p.x))
+            .all(|p| (p.y - left).abs() < 1e-3)
+}
+
+fn usage(key: &str) -> Option<&'static str> {
+    let usage = match key {
+        "body" => "body <name>",
+        "gravity" => "gravity <px/s^2>",
+        "spawn" => "spawn <x> <y>",
+        "velocity" => "velocity <x> <y>",
+        "fuel" => "fuel <seconds>",
+        "point" => "point <x> <y>",
+        "pad" => "pad <left> <right> <multiplier>",
+        "hazard" => "hazard <x> <y> <width> <height>",
+        _ => return None,
+    };
+    Some(usage)
+}
+
+fn point(x: &str, y: &str, line_no: usize, field: &str) -> Result<Vec2d, String> {
+    Ok(Vec2d::new(
+        parse(x, line_no, &format!("{} x", field))?,
+        parse(y, line_no, &format!("{} y", field))?,
+    ))
+}
+
+fn non_negative(value: &str, line_no: usize, field: &str) -> Result<f32, String> {
+    let v: f32 = parse(value, line_no, field)?;
+    if v < 0.0 {
+        return Err(format!("{}: invalid {} '{}'", line_no, field, value));
+    }
+    Ok(v)
+}
+
+fn parse<T: std::str::FromStr>(value: &str, line_no: usize, field: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: invalid {} '{}'", line_no, field, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::level::*;
+
+    const LEVEL: &str = "lunar-level 1
+# a single pad in a valley
+body Mars
+gravity 0.5
+spawn 150 100
+velocity 5 0
+fuel 12.5
+point 0 400
+point 100 500
+point 160 500 # pad
+point 300 350
+pad 100 160 3
+hazard 200 100 40 40
+";
+
+    #[test]
+    fn text_round_trip() {
+        let level = Level::from_text(LEVEL).unwrap();
+        assert_eq!(level.body, Body::Mars);
+        assert_eq!(level.gravity, Some(0.5));
+        assert_eq!(level.spawn, Vec2d::new(150.0, 100.0));
+        assert_eq!(level.fuel, Some(12.5));
+        assert_eq!(level.points.len(), 4);
+        assert_eq!(level.pads[0].multiplier, 3);
+        assert!(level.hazards[0].contains(Vec2d::new(220.0, 139.0)));
+        assert_eq!(Level::from_text(&level.to_text()).unwrap(), level);
+    }
+
+    #[test]
+    fn generated_maps_can_be_kept() {
+        let mut map = PointList::from_seed(800.0, 200.0, 7, &Config::default().terrain);
+        map.set_window_height(600.0);
+        let level = Level::from_map(&map, Body::Moon);
+        assert!(!level.pads.is_empty());
+        assert!(level.gravity.is_none() && level.fuel.is_none());
+        let loaded = Level::from_text(&level.to_text()).unwrap();
+        assert_eq!(&loaded.points, map.get_values());
+        assert_eq!(loaded.pads, level.pads);
+    }
+
+    #[test]
+    fn errors_name_line_and_field() {
+        let err = |text: &str| Level::from_text(&format!("lunar-level 1\n{}", text)).unwrap_err();
+        assert_eq!(err("point 0 1\npad 0 40 lots\n"), "3: invalid pad multiplier 'lots'");
+        assert_eq!(err("spawn 10 high\n"), "2: invalid spawn y 'high'");
+        assert_eq!(err("fuel -1\n"), "2: invalid fuel '-1'");
+        assert_eq!(err("pad 0 40\n"), "2: expected 'pad <left> <right> <multiplier>'");
+        assert_eq!(err("point 10 0\npoint 5 0\n"), "3: point x must grow from left to right");
+        assert_eq!(err("point 0 0\npoint 50 10\npad 0 40 2\n"), "4: pad 0 - 40 is not on flat terrain");
+        assert_eq!(err("point 0 0\n"), "2: a level needs at least two points");
+        assert_eq!(err("body Pluto\n"), "2: invalid body 'Pluto'");
+        assert_eq!(err("lava 1 2\n"), "2: unexpected line 'lava 1 2'");
+        assert!(Level::from_text("point 0 0").is_err());
+    }
+}
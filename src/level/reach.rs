@@ -0,0 +1,123 @@
+use crate::level::Pad;
+use crate::map::PointList;
+use crate::vecmath::Vec2d;
+
+// Flight times tried for the transfer, in seconds
+const MAX_FLIGHT_TIME: f32 = 300.0;
+const FLIGHT_TIME_STEP: f32 = 0.5;
+
+// A lower bound of the fuel, in seconds at full throttle, to fly from
+// position with velocity to a standstill on the pad. The lander drifts
+// over at constant speed, climbs over terrain that is higher than its
+// start and brakes its fall at the very last moment. A tilted engine
+// changes both speeds with the same burn, so the sideways and the
+// vertical change of a flight add up like the sides of a right triangle,
+// no pilot gets by with less than the diagonal. Turning and every other
+// loss are ignored, so a pad that needs more fuel than there is can not
+// be reached by any pilot.
+pub fn fuel_to_reach(map: &PointList, position: Vec2d, velocity: Vec2d, pad: &Pad, gravity: f32, thrust: f32) -> f32 {
+    let target = Vec2d::new(pad.center(), map.surface_at(pad.center()).map_or(position.y, |(y, _)| y));
+    let dx = target.x - position.x;
+
+    // Climbing stops the fall and lifts the start over the highest ground
+    // on the way, y grows downwards
+    let highest = map
+        .get_values()
+        .iter()
+        .filter(|p| p.x > position.x.min(target.x) && p.x < position.x.max(target.x) && !pad.contains(p.x))
+        .map(|p| p.y)
+        .fold(target.y, f32::min);
+    let climb = (position.y - highest).max(0.0);
+    let (lift, vy, start_y) = if climb > 0.0 {
+        (velocity.y.max(0.0) + (2.0 * gravity * climb).sqrt(), 0.0, highest)
+    } else {
+        (0.0, velocity.y, position.y)
+    };
+
+    // Falling onto the pad takes this long and ends this fast
+    let height = (target.y - start_y).max(0.0);
+    let impact = (vy * vy + 2.0 * gravity * height).sqrt();
+    let fall_time = if gravity > 0.0 { (impact - vy) / gravity } else { f32::MAX };
+
+    // Slow transfers save on drifting and pay for hovering
+    let mut best = f32::MAX;
+    let mut time = FLIGHT_TIME_STEP;
+    while time <= MAX_FLIGHT_TIME {
+        let cruise = dx / time;
+        let sideways = (cruise - velocity.x).abs() + cruise.abs();
+        let vertical = lift + impact + gravity * (time - fall_time).max(0.0);
+        best = best.min(Vec2d::new(sideways, vertical).len());
+        time += FLIGHT_TIME_STEP;
+    }
+    best / thrust.max(1e-3)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::control::{Autopilot, Controller};
+    use crate::level::{fuel_to_reach, Pad};
+    use crate::map::PointList;
+    use crate::mainloop::{SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+    use crate::planet::Body;
+    use crate::simulation::{State, World};
+    use crate::vecmath::Vec2d;
+
+    fn valley(peak: f32) -> PointList {
+        PointList::from_points(vec![
+            Vec2d::new(0.0, 500.0),
+            Vec2d::new(300.0, peak),
+            Vec2d::new(500.0, 500.0),
+            Vec2d::new(540.0, 500.0),
+            Vec2d::new(800.0, 500.0),
+        ])
+    }
+
+    #[test]
+    fn far_and_high_pads_need_more_fuel() {
+        let pad = Pad {
+            left: 500.0,
+            right: 540.0,
+            multiplier: 1,
+        };
+        let config = Config::default();
+        let (gravity, thrust) = (config.physics.gravity, config.lander.thrust);
+        let fuel = |map: &PointList, x: f32| fuel_to_reach(map, Vec2d::new(x, 300.0), Vec2d::default(), &pad, gravity, thrust);
+        let low = valley(450.0);
+        assert!(fuel(&low, 520.0) < fuel(&low, 100.0));
+        assert!(fuel(&low, 100.0) < fuel(&valley(100.0), 100.0));
+    }
+
+    // Stopping a diagonal fall right over the pad takes a single burn
+    #[test]
+    fn tilted_burns_count_once() {
+        let map = PointList::from_points(vec![Vec2d::new(0.0, 500.0), Vec2d::new(800.0, 500.0)]);
+        let pad = Pad {
+            left: 400.0,
+            right: 440.0,
+            multiplier: 1,
+        };
+        let velocity = Vec2d::new(30.0, 40.0);
+        let delta_v = fuel_to_reach(&map, Vec2d::new(420.0, 500.0), velocity, &pad, 10.0, 1.0);
+        assert!(delta_v >= velocity.len() && delta_v < velocity.x + velocity.y, "{}", delta_v);
+    }
+
+    // The autopilot is a real pilot, it never gets by with less
+    #[test]
+    fn autopilot_needs_at_least_the_bound() {
+        for seed in 1..=5 {
+            let mut world = World::new(800, 600, seed, Config::default(), Body::Moon);
+            let o = world.observe();
+            let pad = o.nearest_pad().unwrap();
+            let bound = fuel_to_reach(world.map(), o.position, o.velocity, &pad, o.gravity, o.thrust);
+            let fuel = o.fuel;
+            let mut pilot = Autopilot::new();
+            while *world.state() == State::Running {
+                let controls = pilot.control(&world.observe(), SIM_TICK_MS / 1000.0);
+                world.apply_controls(controls);
+                world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+            }
+            assert!(fuel - world.observe().fuel >= bound, "seed {}", seed);
+        }
+    }
+}
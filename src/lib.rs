@@ -0,0 +1,33 @@
+use vecmath::Vec2d;
+
+pub mod app;
+pub mod batch;
+mod camera;
+pub mod cli;
+mod collision;
+pub mod config;
+pub mod control;
+pub mod daily;
+pub mod draw;
+mod editor;
+pub mod ghost;
+mod graphics;
+pub mod gym;
+pub mod highscore;
+mod hud;
+pub mod level;
+pub mod mainloop;
+mod map;
+pub mod mode;
+pub mod net;
+pub mod planet;
+pub mod replay;
+pub mod simulation;
+mod terrain;
+pub mod vecmath;
+pub mod viewport;
+
+// The virtual resolution everything is laid out for, see viewport
+pub const window_width: u32 = 800;
+pub const window_height: u32 = 600;
+pub const window_center: Vec2d = Vec2d::new(window_width as f32 / 2.0, window_height as f32 / 2.0);
@@ -1,106 +1,304 @@
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::Color;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use vecmath::Vec2d;
+use sdl2::rect::Point;
+use sdl2::video::FullscreenType;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-mod collision;
-mod draw;
-mod graphics;
-mod hud;
-mod map;
-mod simulation;
-mod vecmath;
+use lunar::control::Controller;
+use lunar::{app, cli, config, control, draw, highscore, level, mainloop, net, planet, replay, simulation, viewport};
+use lunar::{window_height, window_width};
 
-pub const window_width: u32 = 800;
-pub const window_height: u32 = 600;
-pub const window_center: Vec2d = Vec2d::new(window_width as f32 / 2.0, window_height as f32 / 2.0);
+const DEFAULT_CONFIG_PATH: &str = "lunar.toml";
+// The quickest landing on every seed, raced as a ghost
+const GHOST_DIR: &str = "ghosts";
+
+// A headless run gives up after five minutes of game time
+const HEADLESS_MAX_TICKS: u32 = 5 * 60 * mainloop::SIM_RATE_HZ;
+// How long a network race waits for the other player to show up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// The session with the other player, the seed and the body to race on
+type OnlineRace = (net::Session<Box<dyn net::Transport>>, u64, planet::Body, config::Config);
 
 pub fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match cli::parse(&args) {
+        Ok(cli::Command::Run(options)) => *options,
+        Ok(cli::Command::Help) => {
+            print!("{}", cli::HELP);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::HELP);
+            std::process::exit(2);
+        }
+    };
+    let config = options.apply(load_config(&options)?);
+    if options.print_config {
+        print!("{}", config.to_text());
+        return Ok(());
+    }
+    let replay = match options.play.as_ref() {
+        Some(path) => Some(replay::Replay::load(path)?),
+        None => None,
+    };
+    let level = match options.level.as_ref() {
+        Some(path) => Some(level::Level::load(path)?),
+        None => None,
+    };
+
+    if options.headless {
+        return run_headless(&options, config, replay, level);
+    }
+    let online = connect_online(&options, config)?;
+
+    draw::set_asset_dir(&options.asset_dir);
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
 
-    let window = video_subsystem
-        .window("rust-sdl2 demo: Video", window_width, window_height)
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let mut window_builder = video_subsystem.window("rust-sdl2 demo: Video", options.width, options.height);
+    window_builder.position_centered().resizable().opengl();
+    if options.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build().map_err(|e| e.to_string())?;
 
-    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    let mut main_loop = mainloop::MainLoop::new(options.pacing, mainloop::SIM_RATE_HZ);
+    let mut canvas_builder = window.into_canvas();
+    if main_loop.uses_vsync() {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let mut canvas = canvas_builder.build().map_err(|e| e.to_string())?;
+    let (output_width, output_height) = canvas.output_size()?;
+    let mut viewport = viewport::Viewport::new(output_width, output_height);
 
     canvas.set_draw_color(Color::RGB(0, 0, 0));
     canvas.clear();
     canvas.present();
     let mut event_pump = sdl_context.event_pump()?;
+    // Connected gamepads announce themselves as added at startup, they
+    // only send events while they are kept open
+    let game_controllers = sdl_context.game_controller()?;
+    let mut gamepads = Vec::new();
 
-    let mut sim = simulation::World::new(window_width, window_height);
+    let mut app = app::App::new(highscore::HighScores::load(Path::new("highscores.txt")), config);
+    app.set_start_body(options.body);
+    app.set_mode(options.mode);
+    if let Some(date) = options.daily {
+        app.set_daily_date(date);
+    }
+    app.set_autopilot(options.autopilot);
+    app.set_ghost_dir(PathBuf::from(GHOST_DIR));
+    if let Some(seed) = options.seed {
+        app.set_start_seed(seed);
+    }
+    if let Some(path) = options.record.as_ref() {
+        app.record_to(path.clone());
+    }
+    if let Some(replay) = replay {
+        app.play(replay);
+    }
+    if let (Some(level), Some(path)) = (level, options.level.as_ref()) {
+        app.set_level(level, path.clone());
+    }
+    if let Some((session, seed, body, config)) = online {
+        app.race_online(session, seed, body, config);
+    }
+    let mut show_stats = false;
+    let config_path = options.config.clone().unwrap_or(PathBuf::from(DEFAULT_CONFIG_PATH));
+    let mut config_watcher = config::ConfigWatcher::new(&config_path);
 
     'running: loop {
+        let sim_ticks = main_loop.begin_frame();
+
         for event in event_pump.poll_iter() {
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => break 'running,
                 Event::KeyDown {
-                    keycode: Some(Keycode::Space),
+                    keycode: Some(Keycode::F3),
                     ..
                 } => {
-                    sim.thrust_toggle(true);
+                    show_stats = !show_stats;
+                    continue;
                 }
-                Event::KeyUp {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => {
-                    sim.thrust_toggle(false);
-                }
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::Left),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
                     ..
                 } => {
-                    sim.rotation_left_toggle(false);
+                    toggle_fullscreen(canvas.window_mut())?;
+                    continue;
                 }
-
                 Event::KeyDown {
-                    keycode: Some(Keycode::Left),
+                    keycode: Some(Keycode::Return),
+                    keymod,
                     ..
-                } => {
-                    sim.rotation_left_toggle(true);
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    toggle_fullscreen(canvas.window_mut())?;
+                    continue;
                 }
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::Right),
-                    ..
-                } => {
-                    sim.rotation_right_toggle(false);
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Ok(gamepad) = game_controllers.open(which) {
+                        gamepads.push(gamepad);
+                    }
                 }
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::Right),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
                     ..
                 } => {
-                    sim.rotation_right_toggle(true);
+                    // The output size differs from the window size on high DPI displays
+                    let (width, height) = canvas.output_size()?;
+                    viewport.resize(width, height);
                 }
-                _ => {}
+                _ => (),
+            }
+            app.handle_event(&viewport.to_virtual(event));
+            if app.should_quit() {
+                break 'running;
             }
         }
 
-        canvas.clear();
+        match config_watcher.poll() {
+            Some(Ok(config)) => {
+                app.notify(format!("Reloaded {}", config_watcher.path().display()));
+                app.set_config(options.apply(config));
+            }
+            Some(Err(e)) => app.notify(e),
+            None => (),
+        }
+
+        for _ in 0..sim_ticks {
+            app.tick(mainloop::SIM_TICK_MS, mainloop::SIM_TICK_RESOLUTION_MS);
+        }
 
+        // Clearing ignores the viewport, so the letterbox bars are cleared too
         canvas.set_draw_color(Color::RGB(0, 0, 0));
         canvas.clear();
-        sim.render(&mut canvas);
+        viewport.apply(&mut canvas)?;
+        app.render(&mut canvas);
+
+        if show_stats {
+            let stats = main_loop.stats();
+            let text = format!(
+                "{:.0} fps  frame {:.1}ms (min {:.1} / max {:.1})",
+                stats.fps, stats.avg_frame_ms, stats.min_frame_ms, stats.max_frame_ms
+            );
+            draw::draw_text(
+                &mut canvas,
+                &text,
+                10,
+                Point::new(0, window_height as i32 - 12),
+                Color::RGB(0, 255, 0),
+            )?;
+        }
 
         canvas.present();
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
-        // The rest of the game loop goes here...
-        sim.tick(50.0, 10.0);
+        main_loop.end_frame();
+    }
+
+    Ok(())
+}
+
+// An explicitly given config file has to exist, the default one is optional
+fn load_config(options: &cli::Options) -> Result<config::Config, String> {
+    match options.config.as_ref() {
+        Some(path) => config::Config::load(path),
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => config::Config::load(Path::new(DEFAULT_CONFIG_PATH)),
+        None => Ok(config::Config::default()),
     }
+}
+
+// Waits for the other player of a network race, before the window opens.
+// The host decides the seed, the body, the config and how to keep in sync.
+fn connect_online(options: &cli::Options, config: config::Config) -> Result<Option<OnlineRace>, String> {
+    let transport: Box<dyn net::Transport> = match (options.host, options.join.as_ref()) {
+        (Some(port), _) => Box::new(net::UdpTransport::bind(&format!("0.0.0.0:{}", port))?),
+        (None, Some(address)) => Box::new(net::UdpTransport::connect(address)?),
+        (None, None) => return Ok(None),
+    };
+    let transport: Box<dyn net::Transport> = if options.net_loss > 0.0 || options.net_latency > 0 {
+        let latency = Duration::from_millis(options.net_latency as u64);
+        Box::new(net::LossyTransport::new(transport, options.net_loss, latency, latency / 2, rand::random()))
+    } else {
+        transport
+    };
+    match options.host {
+        Some(port) => {
+            let seed = options.seed.unwrap_or_else(rand::random);
+            let sync = net::Sync::from_parts(options.net_delay, options.rollback);
+            println!("Waiting for the other player on port {}", port);
+            let session = net::host(transport, seed, options.body, config, sync, HANDSHAKE_TIMEOUT)?;
+            Ok(Some((session, seed, options.body, config)))
+        }
+        None => {
+            println!("Joining {}", options.join.as_deref().unwrap_or_default());
+            let (session, seed, body, config) = net::join(transport, HANDSHAKE_TIMEOUT)?;
+            Ok(Some((session, seed, body, config)))
+        }
+    }
+}
+
+fn toggle_fullscreen(window: &mut sdl2::video::Window) -> Result<(), String> {
+    let state = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    window.set_fullscreen(state)
+}
+
+// Flies a level without a window, as fast as possible, and prints how it
+// ended. Without a replay or the autopilot the lander just falls.
+fn run_headless(
+    options: &cli::Options,
+    config: config::Config,
+    replay: Option<replay::Replay>,
+    level: Option<level::Level>,
+) -> Result<(), String> {
+    let (seed, config, body) = match (replay.as_ref(), level.as_ref()) {
+        (Some(replay), _) => (replay.seed, replay.config, replay.body),
+        (None, Some(level)) => (0, config, level.body),
+        (None, None) => (options.seed.unwrap_or_else(rand::random), config, options.body),
+    };
+    let mut world = match level.as_ref() {
+        Some(level) => simulation::World::from_level(level, config),
+        None => simulation::World::new(window_width, window_height, seed, config, body),
+    };
+    let mut recording = replay::Replay::new(seed, config, body);
+    let mut pilot = control::Autopilot::new();
 
+    let mut ticks = 0;
+    while *world.state() == simulation::State::Running && ticks < HEADLESS_MAX_TICKS {
+        if let Some(controls) = replay.as_ref().and_then(|r| r.controls_at(ticks as usize)) {
+            world.apply_controls(controls);
+        }
+        if options.autopilot {
+            let controls = pilot.control(&world.observe(), mainloop::SIM_TICK_MS / 1000.0);
+            world.apply_controls(controls);
+        }
+        recording.record(world.controls());
+        world.tick(mainloop::SIM_TICK_MS, mainloop::SIM_TICK_RESOLUTION_MS);
+        ticks += 1;
+    }
+
+    if let Some(path) = options.record.as_ref() {
+        recording.save(path)?;
+    }
+    let outcome = match world.state() {
+        simulation::State::Running => "timeout",
+        simulation::State::Won => "landed",
+        simulation::State::Lost => "crashed",
+    };
+    let map = match options.level.as_ref() {
+        Some(path) => path.display().to_string(),
+        None => format!("seed {}", seed),
+    };
+    println!(
+        "{} on {}: {} after {} ticks, score {}",
+        map,
+        body.name(),
+        outcome,
+        ticks,
+        world.score()
+    );
     Ok(())
 }
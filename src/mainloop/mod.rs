@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant};
+
+// The game was tuned for 30 world ticks per second, each advancing the
+// world by 50ms in 10ms steps. The simulation keeps this pace no matter how
+// fast frames are rendered.
+pub const SIM_RATE_HZ: u32 = 30;
+pub const SIM_TICK_MS: f32 = 50.0;
+pub const SIM_TICK_RESOLUTION_MS: f32 = 10.0;
+
+// Longer gaps (e.g. while the window is dragged) are not caught up, the
+// game just continues where it stopped.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pacing {
+    VSync,
+    Capped(u32), // frames per second
+    Uncapped,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+    pub fps: f32,
+    pub avg_frame_ms: f32,
+    pub min_frame_ms: f32,
+    pub max_frame_ms: f32,
+}
+
+// Measures the real time between frames and turns it into a number of fixed
+// simulation ticks to run.
+pub struct MainLoop {
+    pacing: Pacing,
+    sim_step: Duration,
+    accumulator: Duration,
+    frame_start: Option<Instant>,
+    stats: FrameStats,
+    // Collects frame times until the stats window is full
+    window_time: Duration,
+    window_frames: u32,
+    window_min: Duration,
+    window_max: Duration,
+}
+
+impl MainLoop {
+    pub fn new(pacing: Pacing, sim_rate_hz: u32) -> Self {
+        MainLoop {
+            pacing,
+            sim_step: Duration::from_secs(1) / sim_rate_hz,
+            accumulator: Duration::ZERO,
+            frame_start: None,
+            stats: FrameStats::default(),
+            window_time: Duration::ZERO,
+            window_frames: 0,
+            window_min: Duration::MAX,
+            window_max: Duration::ZERO,
+        }
+    }
+
+    pub fn uses_vsync(&self) -> bool {
+        self.pacing == Pacing::VSync
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    // Starts a new frame and returns how many simulation ticks are due
+    pub fn begin_frame(&mut self) -> u32 {
+        let now = Instant::now();
+        let frame_time = match self.frame_start {
+            Some(start) => now - start,
+            None => Duration::ZERO,
+        };
+        self.frame_start = Some(now);
+        self.advance(frame_time)
+    }
+
+    // Sleeps for the rest of the frame if the frame rate is capped. With
+    // vsync presenting the canvas already waited for us.
+    pub fn end_frame(&self) {
+        if let (Pacing::Capped(fps), Some(start)) = (self.pacing, self.frame_start) {
+            let target = Duration::from_secs(1) / fps.max(1);
+            let elapsed = start.elapsed();
+            if elapsed < target {
+                std::thread::sleep(target - elapsed);
+            }
+        }
+    }
+
+    fn advance(&mut self, frame_time: Duration) -> u32 {
+        self.record(frame_time);
+
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let mut ticks = 0;
+        while self.accumulator >= self.sim_step {
+            self.accumulator -= self.sim_step;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    fn record(&mut self, frame_time: Duration) {
+        self.window_time += frame_time;
+        self.window_frames += 1;
+        self.window_min = self.window_min.min(frame_time);
+        self.window_max = self.window_max.max(frame_time);
+
+        if self.window_time >= STATS_WINDOW {
+            let window_s = self.window_time.as_secs_f32();
+            self.stats = FrameStats {
+                fps: self.window_frames as f32 / window_s,
+                avg_frame_ms: window_s * 1000.0 / self.window_frames as f32,
+                min_frame_ms: self.window_min.as_secs_f32() * 1000.0,
+                max_frame_ms: self.window_max.as_secs_f32() * 1000.0,
+            };
+            self.window_time = Duration::ZERO;
+            self.window_frames = 0;
+            self.window_min = Duration::MAX;
+            self.window_max = Duration::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::mainloop::*;
+
+    #[test]
+    fn slow_frames_run_several_ticks() {
+        let mut l = MainLoop::new(Pacing::Uncapped, 10);
+        assert_eq!(l.advance(Duration::from_millis(100)), 1);
+        assert_eq!(l.advance(Duration::from_millis(200)), 2);
+    }
+
+    #[test]
+    fn fast_frames_accumulate() {
+        let mut l = MainLoop::new(Pacing::Uncapped, 10);
+        for _ in 0..9 {
+            assert_eq!(l.advance(Duration::from_millis(10)), 0);
+        }
+        assert_eq!(l.advance(Duration::from_millis(10)), 1);
+    }
+
+    #[test]
+    fn huge_gaps_are_clamped() {
+        let mut l = MainLoop::new(Pacing::Uncapped, 100);
+        let ticks = l.advance(Duration::from_secs(5));
+        assert_eq!(ticks, (MAX_FRAME_TIME.as_millis() / 10) as u32);
+    }
+
+    #[test]
+    fn stats_cover_the_last_window() {
+        let mut l = MainLoop::new(Pacing::VSync, 30);
+        assert!(l.uses_vsync());
+        for idx in 0..62 {
+            let ms = if idx == 0 { 5 } else if idx == 1 { 45 } else { 1000 / 60 };
+            l.advance(Duration::from_millis(ms));
+        }
+        let stats = l.stats();
+        assert!(stats.fps > 55.0 && stats.fps < 65.0, "{:?}", stats);
+        assert_eq!(stats.min_frame_ms, 5.0);
+        assert_eq!(stats.max_frame_ms, 45.0);
+    }
+}
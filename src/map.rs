@@ -1,6 +1,9 @@
+use crate::config::{Config, TerrainConfig};
+use crate::terrain::{MidpointDisplacement, TerrainGenerator, PADS};
 use crate::vecmath::Vec2d;
 use rand::distributions::Uniform;
 use rand::prelude::*;
+use rand::rngs::StdRng;
 
 #[derive(Debug)]
 pub struct PointList {
@@ -8,50 +11,27 @@ pub struct PointList {
     values: Vec<Vec2d>,
 }
 
-const X_MAX_DELTA: f32 = 10.0;
-const Y_MAX_DELTA: f32 = 200.0;
-const Y_DELTA_DIVIDOR: f32 = 1.70;
 const X_START_POINTS: usize = 5;
-const X_LANDING_AREA_SIZE: f32 = 40.0;
+pub(crate) const X_LANDING_AREA_SIZE: f32 = 40.0;
 
 impl PointList {
     pub fn new(maxX: f32, maxY: f32) -> Self {
-        let mut start_points: Vec<Vec2d> = Vec::new();
-        let xStepSize = maxX / (X_START_POINTS as f32);
-        assert!(xStepSize > X_LANDING_AREA_SIZE);
-        for stepIdx in 0..=X_START_POINTS {
-            start_points.push(Vec2d::new(xStepSize * (stepIdx as f32), randomY(0.0, maxY)))
-        }
-        let landingPointsIdx =
-            Self::genLandingPointsIdx(2, Uniform::new(1, start_points.len() - 1));
-
-        let mut gen_map = Vec::new();
-
-        for idx in 0..(start_points.len() - 1) {
-            gen_map.push(start_points[idx].clone());
-            if landingPointsIdx.contains(&idx) {
-                let secLandingPoint =
-                    gen_map.last().unwrap().clone() + Vec2d::new(X_LANDING_AREA_SIZE, 0.0);
-                println!(
-                    "Landing platform, {:?} {:?}",
-                    gen_map.last().unwrap().clone(),
-                    secLandingPoint.clone()
-                );
-                gen_map.push(secLandingPoint);
-            }
-            split(
-                gen_map.last().unwrap().clone(),
-                start_points[idx + 1].clone(),
-                &mut gen_map,
-                X_MAX_DELTA,
-                Y_MAX_DELTA,
-            );
-        }
-        gen_map.push(start_points.last().unwrap().clone());
+        Self::from_seed(maxX, maxY, rand::random(), &Config::default().terrain)
+    }
+
+    // The same seed always generates the same map
+    pub fn from_seed(max_x: f32, max_y: f32, seed: u64, terrain: &TerrainConfig) -> Self {
+        Self::generate(&MidpointDisplacement::new(*terrain), max_x, max_y, seed)
+    }
 
+    pub fn generate(generator: &dyn TerrainGenerator, max_x: f32, max_y: f32, seed: u64) -> Self {
+        Self::from_points(generator.generate(max_x, max_y, seed))
+    }
+
+    pub fn from_points(values: Vec<Vec2d>) -> Self {
         let mut n = PointList {
             window_height: None,
-            values: Vec::from(gen_map),
+            values,
         };
         n.sort();
         n
@@ -74,49 +54,151 @@ impl PointList {
         &self.values
     }
 
+    // Returns the terrain height at x together with the upwards pointing
+    // normal of the segment below x, or None if x is outside of the map.
+    pub fn surface_at(&self, x: f32) -> Option<(f32, Vec2d)> {
+        let first = self.values.first()?;
+        let last = self.values.last()?;
+        if x < first.x || x > last.x {
+            return None;
+        }
+        let idx = self.values.partition_point(|p| p.x <= x).clamp(1, self.values.len() - 1);
+        let a = self.values[idx - 1];
+        let b = self.values[idx];
+        let t = if b.x > a.x { (x - a.x) / (b.x - a.x) } else { 0.0 };
+        let dir = (b - a).normalized();
+        Some((a.y + (b.y - a.y) * t, Vec2d::new(dir.y, -dir.x)))
+    }
+
+    // The flat landing areas the generators build, as (left, right) x
+    pub fn landing_areas(&self) -> Vec<(f32, f32)> {
+        self.values
+            .windows(2)
+            .filter(|w| w[0].y == w[1].y && (w[1].x - w[0].x - X_LANDING_AREA_SIZE).abs() < 1e-3)
+            .map(|w| (w[0].x, w[1].x))
+            .collect()
+    }
+
     fn sort(self: &mut Self) {
         self.values.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
     }
 
-    fn genLandingPointsIdx(numLandings: usize, select: Uniform<usize>) -> Vec<usize> {
-        let mut rng = rand::thread_rng();
-        rng.sample_iter(select).take(numLandings).collect()
+    // Flattens a pad from left on at the terrain height there and cuts the
+    // terrain beside it down below the slope, so nothing walls the pad in.
+    // Only for maps in window coordinates, y grows downwards.
+    pub(crate) fn carve_pad(&mut self, left: f32, corridor_width: f32, corridor_slope: f32) {
+        let right = left + X_LANDING_AREA_SIZE;
+        let Some((y, _)) = self.surface_at(left) else {
+            return;
+        };
+        self.values.retain(|p| p.x < left || p.x > right);
+        for p in self.values.iter_mut() {
+            let distance = if p.x < left { left - p.x } else { p.x - right };
+            if distance <= corridor_width {
+                p.y = p.y.max(y - corridor_slope * distance);
+            }
+        }
+        self.values.push(Vec2d::new(left, y));
+        self.values.push(Vec2d::new(right, y));
+        self.sort();
+    }
+
+    // Distinct indices, a start point drawn twice would build a single pad
+    fn genLandingPointsIdx(rng: &mut impl Rng, numLandings: usize, select: Uniform<usize>) -> Vec<usize> {
+        let mut landings = Vec::new();
+        let mut draws = rng.sample_iter(select);
+        while landings.len() < numLandings {
+            let idx = draws.next().unwrap();
+            if !landings.contains(&idx) {
+                landings.push(idx);
+            }
+        }
+        landings
     }
 }
 
-fn randomY(minValue: f32, maxValue: f32) -> f32 {
+// The original terrain: recursive midpoint displacement between a few
+// random start points, with landing pads inserted at some of them
+pub(crate) fn midpoint_displacement(max_x: f32, max_y: f32, seed: u64, terrain: &TerrainConfig) -> Vec<Vec2d> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut start_points: Vec<Vec2d> = Vec::new();
+    let xStepSize = max_x / (X_START_POINTS as f32);
+    assert!(xStepSize > X_LANDING_AREA_SIZE);
+    for stepIdx in 0..=X_START_POINTS {
+        start_points.push(Vec2d::new(xStepSize * (stepIdx as f32), randomY(&mut rng, 0.0, max_y)))
+    }
+    let landingPointsIdx = PointList::genLandingPointsIdx(
+        &mut rng,
+        PADS.min(start_points.len() - 2),
+        Uniform::new(1, start_points.len() - 1),
+    );
+
+    let mut gen_map = Vec::new();
+
+    for idx in 0..(start_points.len() - 1) {
+        gen_map.push(start_points[idx].clone());
+        if landingPointsIdx.contains(&idx) {
+            let secLandingPoint =
+                gen_map.last().unwrap().clone() + Vec2d::new(X_LANDING_AREA_SIZE, 0.0);
+            gen_map.push(secLandingPoint);
+        }
+        split(
+            &mut rng,
+            gen_map.last().unwrap().clone(),
+            start_points[idx + 1].clone(),
+            &mut gen_map,
+            terrain.x_max_delta,
+            terrain.y_max_delta,
+            terrain.y_delta_divider,
+        );
+    }
+    gen_map.push(start_points.last().unwrap().clone());
+
+    gen_map
+}
+
+fn randomY(rng: &mut impl Rng, minValue: f32, maxValue: f32) -> f32 {
     loop {
-        let mut rng = rand::thread_rng();
         let distY = Uniform::new_inclusive(minValue, maxValue);
         let num = rng.sample(distY);
         if num >= 0.0 {
             return num;
-        } else {
-            println!("randomY got illegal value below 0");
         }
     }
 }
 
-fn split(a: Vec2d, b: Vec2d, list: &mut Vec<Vec2d>, xMinDist: f32, yMaxDelta: f32) {
+fn split(
+    rng: &mut impl Rng,
+    a: Vec2d,
+    b: Vec2d,
+    list: &mut Vec<Vec2d>,
+    xMinDist: f32,
+    yMaxDelta: f32,
+    y_delta_divider: f32,
+) {
     assert!(a.x < b.x);
     let deltaX = (b.x - a.x) / 2.0;
     let center = (a + b) / 2.0;
-    let newY = randomY(center.y - (yMaxDelta / 2.0), center.y + (yMaxDelta / 2.0));
+    let newY = randomY(rng, center.y - (yMaxDelta / 2.0), center.y + (yMaxDelta / 2.0));
     let newPoint = Vec2d::new(a.x + deltaX, newY);
     if deltaX > xMinDist {
         split(
+            rng,
             a,
             newPoint.clone(),
             list,
             xMinDist,
-            yMaxDelta / Y_DELTA_DIVIDOR,
+            yMaxDelta / y_delta_divider,
+            y_delta_divider,
         );
         split(
+            rng,
             newPoint.clone(),
             b,
             list,
             xMinDist,
-            yMaxDelta / Y_DELTA_DIVIDOR,
+            yMaxDelta / y_delta_divider,
+            y_delta_divider,
         );
     }
     list.push(newPoint);
@@ -131,7 +213,6 @@ mod tests {
     #[test]
     fn test_list_gen() {
         let li = PointList::new(150.0, 20.0);
-        println!("Point list: {:?}", li);
         assert!(li.values.first().unwrap().x == 0.0);
         assert!(li.values.last().unwrap().x == 150.0);
     }
@@ -139,25 +220,52 @@ mod tests {
     #[test]
     fn test_list_gen_2() {
         let li = PointList::new(300.0, 20.0);
-        println!("Point list: {:?}", li);
         assert_eq!(li.values.first().unwrap().x == 0.0, true);
         assert_eq!(li.values.last().unwrap().x == 300.0, true);
     }
 
     #[test]
     fn test_split() {
+        let mut rng = rand::thread_rng();
         let mut list = Vec::from([
-            Vec2d::new(0.0, randomY(0.0, 100.0)),
-            Vec2d::new(100.0, randomY(0.0, 100.0)),
+            Vec2d::new(0.0, randomY(&mut rng, 0.0, 100.0)),
+            Vec2d::new(100.0, randomY(&mut rng, 0.0, 100.0)),
+        ]);
+        split(&mut rng, list[0], list[1], &mut list, 5.0, 20.0, 1.7);
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_map() {
+        let a = PointList::from_seed(X_SIZE, Y_SIZE, 42, &Config::default().terrain);
+        let b = PointList::from_seed(X_SIZE, Y_SIZE, 42, &Config::default().terrain);
+        let c = PointList::from_seed(X_SIZE, Y_SIZE, 43, &Config::default().terrain);
+        assert_eq!(a.values, b.values);
+        assert_ne!(a.values, c.values);
+    }
+
+    #[test]
+    fn test_surface_at_interpolates_height() {
+        let list = PointList::from_points(vec![
+            Vec2d::new(0.0, 100.0),
+            Vec2d::new(10.0, 100.0),
+            Vec2d::new(20.0, 90.0),
         ]);
-        split(list[0], list[1], &mut list, 5.0, 20.0);
-        println!("Point list: {:?}", list);
+        let (height, normal) = list.surface_at(5.0).unwrap();
+        assert_eq!(height, 100.0);
+        assert_eq!(normal.x, 0.0);
+        assert_eq!(normal.y, -1.0);
+
+        let (height, normal) = list.surface_at(15.0).unwrap();
+        assert_eq!(height, 95.0);
+        assert!(normal.x < 0.0 && normal.y < 0.0);
+
+        assert!(list.surface_at(-1.0).is_none());
+        assert!(list.surface_at(21.0).is_none());
     }
 
     #[test]
     fn test_list_is_sortet_in_x_direction() {
         let list = PointList::new(X_SIZE, Y_SIZE);
-        println!("Point list (sorted): {:?}", list);
         for idx in 1..list.values.len() {
             assert_eq!(list.values[idx - 1].x < list.values[idx].x, true);
         }
@@ -0,0 +1,56 @@
+use crate::level::fuel_to_reach;
+use crate::mode::GameMode;
+use crate::simulation::{Touchdown, World};
+
+// Fuel the lander gets on top of the least any pilot needs, as part of it
+const FUEL_MARGIN: f32 = 0.5;
+// And a little more for the touch down, in seconds
+const FUEL_RESERVE: f32 = 1.0;
+
+// Starts with barely the fuel it takes to get to the cheapest pad
+#[derive(Default)]
+pub struct FuelChallenge {
+    fuel: f32, // in seconds
+}
+
+impl GameMode for FuelChallenge {
+    fn start(&mut self, world: &mut World, _level: u32) {
+        let o = world.observe();
+        let least = o
+            .pads
+            .iter()
+            .map(|pad| fuel_to_reach(o.map, o.position, o.velocity, pad, o.gravity, o.thrust))
+            .fold(f32::MAX, f32::min);
+        self.fuel = (least * (1.0 + FUEL_MARGIN) + FUEL_RESERVE).min(o.fuel);
+        world.set_fuel_of(0, self.fuel);
+    }
+
+    fn judge(&mut self, _world: &mut World, _touchdowns: &[Touchdown]) {}
+
+    fn hud(&self, _world: &World) -> Vec<String> {
+        vec![format!("Fuel challenge - {:.1} s of fuel", self.fuel)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::level::fuel_to_reach;
+    use crate::mode::tests::world;
+    use crate::mode::*;
+
+    #[test]
+    fn starts_with_little_more_than_needed() {
+        let mut world = world();
+        let full = world.observe().fuel;
+        let mut mode = FuelChallenge::default();
+        mode.start(&mut world, 1);
+        let o = world.observe();
+        assert!(o.fuel < full);
+        assert_eq!(o.fuel, mode.fuel);
+        let reachable = o
+            .pads
+            .iter()
+            .any(|pad| fuel_to_reach(o.map, o.position, o.velocity, pad, o.gravity, o.thrust) <= o.fuel);
+        assert!(reachable);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::mode::GameMode;
+use crate::simulation::{State, Touchdown, World};
+
+// One life for as many levels as the pilot gets through, the scores of
+// all landings add up
+#[derive(Default)]
+pub struct Hardcore {
+    level: u32,
+    total: u32,
+    counted: bool, // the landing of this level is in the total
+}
+
+impl GameMode for Hardcore {
+    fn start(&mut self, _world: &mut World, level: u32) {
+        if level == 1 {
+            self.total = 0;
+        }
+        self.level = level;
+        self.counted = false;
+    }
+
+    fn judge(&mut self, world: &mut World, _touchdowns: &[Touchdown]) {
+        if !self.counted && world.lander_state(0) == State::Won {
+            self.total += world.score();
+            self.counted = true;
+        }
+    }
+
+    fn hud(&self, _world: &World) -> Vec<String> {
+        vec![format!("Hardcore - level {} - total {}", self.level, self.total)]
+    }
+
+    fn retry(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mode::tests::world;
+    use crate::mode::*;
+    use crate::simulation::State;
+
+    #[test]
+    fn one_life_and_the_total_adds_up() {
+        let mut world = world();
+        let mut mode = Hardcore::default();
+        assert!(!mode.retry() && !mode.ranked());
+        mode.start(&mut world, 1);
+        mode.total = 500;
+        mode.judge(&mut world, &[]);
+        assert_eq!(mode.total, 500);
+        world.decide_lander(0, State::Won);
+        mode.judge(&mut world, &[]);
+        mode.judge(&mut world, &[]);
+        assert_eq!(mode.total, 500 + world.score());
+        assert!(world.score() > 0);
+
+        mode.start(&mut world, 2);
+        assert_eq!(mode.hud(&world), vec![format!("Hardcore - level 2 - total {}", 500 + world.score())]);
+        mode.start(&mut world, 1);
+        assert_eq!(mode.total, 0);
+    }
+}
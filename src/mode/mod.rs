@@ -0,0 +1,138 @@
+use crate::simulation::{Touchdown, World};
+
+mod fuel;
+mod hardcore;
+mod precision;
+mod time_attack;
+
+pub use fuel::FuelChallenge;
+pub use hardcore::Hardcore;
+pub use precision::Precision;
+pub use time_attack::TimeAttack;
+
+// The rules a level is flown by. The World always decides crashes, the
+// mode decides what counts as a win and what the HUD shows about it.
+pub trait GameMode {
+    // Sets up a level before its first tick, levels count from 1
+    fn start(&mut self, world: &mut World, level: u32);
+    // Decides the player's lander after every tick, touchdowns are only
+    // reported if the mode turned on touch and go
+    fn judge(&mut self, world: &mut World, touchdowns: &[Touchdown]);
+    // Lines the HUD shows for the mode
+    fn hud(&self, world: &World) -> Vec<String>;
+    // Whether a lost level may be flown again
+    fn retry(&self) -> bool {
+        true
+    }
+    // Whether landings count for the high scores and ghosts, which only
+    // know the classic rules
+    fn ranked(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Classic,
+    TimeAttack,
+    FuelChallenge,
+    Precision,
+    Hardcore,
+}
+
+pub const MODES: [Mode; 5] = [Mode::Classic, Mode::TimeAttack, Mode::FuelChallenge, Mode::Precision, Mode::Hardcore];
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        MODES.iter().copied().find(|mode| mode.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::TimeAttack => "time-attack",
+            Mode::FuelChallenge => "fuel",
+            Mode::Precision => "precision",
+            Mode::Hardcore => "hardcore",
+        }
+    }
+
+    // For menus
+    pub fn title(&self) -> &'static str {
+        match self {
+            Mode::Classic => "Classic",
+            Mode::TimeAttack => "Time attack",
+            Mode::FuelChallenge => "Fuel challenge",
+            Mode::Precision => "Precision",
+            Mode::Hardcore => "Hardcore",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let idx = MODES.iter().position(|mode| mode == self).unwrap();
+        MODES[(idx + 1) % MODES.len()]
+    }
+
+    pub fn create(&self) -> Box<dyn GameMode> {
+        match self {
+            Mode::Classic => Box::new(Classic),
+            Mode::TimeAttack => Box::new(TimeAttack::default()),
+            Mode::FuelChallenge => Box::new(FuelChallenge::default()),
+            Mode::Precision => Box::new(Precision::default()),
+            Mode::Hardcore => Box::new(Hardcore::default()),
+        }
+    }
+}
+
+// Touch down on any spot flat enough, once. Pads multiply the score.
+pub struct Classic;
+
+impl GameMode for Classic {
+    fn start(&mut self, _world: &mut World, _level: u32) {}
+
+    fn judge(&mut self, _world: &mut World, _touchdowns: &[Touchdown]) {}
+
+    fn hud(&self, _world: &World) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn ranked(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::mode::*;
+    use crate::planet::Body;
+    use crate::simulation::State;
+
+    pub(crate) fn world() -> World {
+        World::new(800, 600, 7, Config::default(), Body::Moon)
+    }
+
+    pub(crate) fn touchdown(x: f32, pad: Option<usize>) -> Touchdown {
+        Touchdown { lander: 0, x, pad }
+    }
+
+    #[test]
+    fn modes_have_names() {
+        for mode in MODES {
+            assert_eq!(Mode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(Mode::from_name("easy"), None);
+        assert_eq!(Mode::Hardcore.next(), Mode::Classic);
+    }
+
+    #[test]
+    fn classic_leaves_it_to_the_world() {
+        let mut world = world();
+        let mut mode = Mode::Classic.create();
+        mode.start(&mut world, 1);
+        mode.judge(&mut world, &[touchdown(100.0, None)]);
+        assert_eq!(world.lander_state(0), State::Running);
+        assert!(mode.ranked() && mode.retry());
+        assert!(mode.hud(&world).is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::mode::GameMode;
+use crate::simulation::{State, Touchdown, World};
+
+// Width of the zone to land in, in pixel, about a lander
+const ZONE_WIDTH: f32 = 12.0;
+
+// Touch down within the marked middle of the best pad, anywhere else the
+// landing counts as lost
+#[derive(Default)]
+pub struct Precision {
+    zone: Option<(f32, f32)>, // from and to x
+}
+
+impl GameMode for Precision {
+    fn start(&mut self, world: &mut World, _level: u32) {
+        world.set_touch_and_go(true);
+        let start = world.pose_of(0).position.x;
+        // The highest multiplier, the closest of those
+        let pad = world.pads().iter().copied().min_by(|a, b| {
+            b.multiplier
+                .cmp(&a.multiplier)
+                .then((a.center() - start).abs().total_cmp(&(b.center() - start).abs()))
+        });
+        self.zone = pad.map(|pad| (pad.center() - ZONE_WIDTH / 2.0, pad.center() + ZONE_WIDTH / 2.0));
+        world.set_highlights(self.zone.into_iter().collect());
+    }
+
+    fn judge(&mut self, world: &mut World, touchdowns: &[Touchdown]) {
+        if let Some(touchdown) = touchdowns.iter().find(|t| t.lander == 0) {
+            let hit = self.zone.is_none_or(|(from, to)| touchdown.x >= from && touchdown.x <= to);
+            world.decide_lander(0, if hit { State::Won } else { State::Lost });
+        }
+    }
+
+    fn hud(&self, _world: &World) -> Vec<String> {
+        vec![String::from("Precision - land in the marked zone")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mode::tests::{touchdown, world};
+    use crate::mode::*;
+    use crate::simulation::State;
+
+    #[test]
+    fn only_the_zone_counts() {
+        let mut world = world();
+        let mut mode = Precision::default();
+        mode.start(&mut world, 1);
+        let (from, to) = mode.zone.unwrap();
+        assert_eq!(to - from, 12.0);
+        mode.judge(&mut world, &[]);
+        assert_eq!(world.lander_state(0), State::Running);
+        mode.judge(&mut world, &[touchdown((from + to) / 2.0, Some(0))]);
+        assert_eq!(world.lander_state(0), State::Won);
+
+        let mut missed = crate::mode::tests::world();
+        mode.start(&mut missed, 1);
+        mode.judge(&mut missed, &[touchdown(to + 1.0, Some(0))]);
+        assert_eq!(missed.lander_state(0), State::Lost);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::mainloop::SIM_TICK_MS;
+use crate::mode::GameMode;
+use crate::simulation::{State, Touchdown, World};
+
+// Touch down on every pad, the closest one to the start first, as quick
+// as possible. The next pad is marked.
+#[derive(Default)]
+pub struct TimeAttack {
+    order: Vec<usize>, // pads to land on
+    next: usize,       // in the order
+    ticks: u32,        // flown so far
+}
+
+impl TimeAttack {
+    fn mark_next(&self, world: &mut World) {
+        let marked = self.order.get(self.next).map(|idx| world.pads()[*idx]);
+        world.set_highlights(marked.map(|pad| (pad.left, pad.right)).into_iter().collect());
+    }
+
+    pub fn time_in_s(&self) -> f32 {
+        self.ticks as f32 * SIM_TICK_MS / 1000.0
+    }
+}
+
+impl GameMode for TimeAttack {
+    fn start(&mut self, world: &mut World, _level: u32) {
+        world.set_touch_and_go(true);
+        let start = world.pose_of(0).position.x;
+        let pads = world.pads();
+        self.order = (0..pads.len()).collect();
+        self.order
+            .sort_by(|a, b| (pads[*a].center() - start).abs().total_cmp(&(pads[*b].center() - start).abs()));
+        self.next = 0;
+        self.ticks = 0;
+        self.mark_next(world);
+    }
+
+    fn judge(&mut self, world: &mut World, touchdowns: &[Touchdown]) {
+        if world.lander_state(0) != State::Running {
+            return;
+        }
+        self.ticks += 1;
+        for touchdown in touchdowns.iter().filter(|t| t.lander == 0) {
+            if touchdown.pad.is_some() && touchdown.pad == self.order.get(self.next).copied() {
+                self.next += 1;
+                self.mark_next(world);
+            }
+            if self.next >= self.order.len() {
+                world.decide_lander(0, State::Won);
+                return;
+            }
+            // Stranded between the pads
+            if world.observe().fuel <= 0.0 {
+                world.decide_lander(0, State::Lost);
+                return;
+            }
+        }
+    }
+
+    fn hud(&self, _world: &World) -> Vec<String> {
+        let pad = (self.next + 1).min(self.order.len());
+        vec![format!("Time attack - pad {}/{} - {:.1} s", pad, self.order.len(), self.time_in_s())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mode::tests::{touchdown, world};
+    use crate::mode::*;
+    use crate::simulation::State;
+
+    #[test]
+    fn pads_have_to_be_landed_on_in_order() {
+        let mut world = world();
+        let mut mode = TimeAttack::default();
+        mode.start(&mut world, 1);
+        assert!(world.pads().len() >= 2);
+        let (first, second) = (mode.order[0], mode.order[1]);
+        assert!(mode.hud(&world)[0].starts_with("Time attack - pad 1/"));
+
+        mode.judge(&mut world, &[touchdown(0.0, Some(second))]);
+        mode.judge(&mut world, &[touchdown(0.0, None)]);
+        assert_eq!(mode.next, 0);
+        mode.judge(&mut world, &[touchdown(0.0, Some(first))]);
+        assert_eq!(mode.next, 1);
+        for pad in mode.order.clone().into_iter().skip(1) {
+            assert_eq!(world.lander_state(0), State::Running);
+            mode.judge(&mut world, &[touchdown(0.0, Some(pad))]);
+        }
+        assert_eq!(world.lander_state(0), State::Won);
+        assert_eq!(mode.ticks, 2 + mode.order.len() as u32);
+
+        // The clock stops with the landing
+        mode.judge(&mut world, &[]);
+        assert_eq!(mode.ticks, 2 + mode.order.len() as u32);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::config::Config;
+use crate::planet::Body;
+use crate::simulation::Controls;
+
+mod session;
+mod transport;
+
+pub use session::{host, join, Session, Sync};
+pub use transport::{LossyTransport, Transport, UdpTransport};
+
+// Most inputs a single packet carries, the rest follows once these are
+// acknowledged
+const MAX_INPUTS_PER_PACKET: usize = 32;
+
+// What peers send each other, one line of text per datagram
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Packet {
+    // A peer wants to race, sent until the host answers
+    Join,
+    // The race the host sets up, the joiner flies it as the second lander.
+    // The host's config goes along, it shapes the terrain and the physics.
+    Start {
+        seed: u64,
+        body: Body,
+        sync: Sync,
+        config: Config,
+    },
+    // Inputs of the sender from first_tick on, repeated until the peer
+    // acknowledged them, so a lost packet costs nothing but latency. ack
+    // is the number of the peer's inputs the sender has, hash the state
+    // hash after the last tick the sender simulated with final input.
+    Inputs {
+        first_tick: u32,
+        ack: u32,
+        hash: Option<(u32, u64)>,
+        controls: Vec<Controls>,
+    },
+}
+
+impl Packet {
+    // "join", "start <seed> <body> <delay> <rollback window> <key> <value> ..."
+    // with every config value or
+    // "inputs <first tick> <ack> <hash tick> <hash> <throttle> <rotation> ..."
+    // with "- -" for no hash yet
+    pub(crate) fn to_text(&self) -> String {
+        match self {
+            Packet::Join => String::from("join"),
+            Packet::Start { seed, body, sync, config } => {
+                let (delay, window) = sync.parts();
+                let mut text = format!("start {} {} {} {}", seed, body.name(), delay, window);
+                for (key, value) in config.values() {
+                    text += &format!(" {} {}", key, value);
+                }
+                text
+            }
+            Packet::Inputs { first_tick, ack, hash, controls } => {
+                let hash = hash.map_or(String::from("- -"), |(tick, hash)| format!("{} {}", tick, hash));
+                let mut text = format!("inputs {} {} {}", first_tick, ack, hash);
+                for c in controls {
+                    text += &format!(" {} {}", c.throttle, c.rotation);
+                }
+                text
+            }
+        }
+    }
+
+    pub(crate) fn from_text(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        match parts.as_slice() {
+            ["join"] => Ok(Packet::Join),
+            ["start", seed, body, delay, window, values @ ..] if values.len() % 2 == 0 => {
+                let mut config = Config::default();
+                for pair in values.chunks(2) {
+                    config.set(pair[0], pair[1])?;
+                }
+                Ok(Packet::Start {
+                    seed: parse(seed, "seed")?,
+                    body: Body::from_name(body).ok_or(format!("unknown body '{}'", body))?,
+                    sync: Sync::from_parts(parse(delay, "delay")?, parse(window, "rollback window")?),
+                    config,
+                })
+            }
+            ["inputs", first_tick, ack, hash_tick, hash, controls @ ..] if controls.len() % 2 == 0 => {
+                let hash = match (*hash_tick, *hash) {
+                    ("-", "-") => None,
+                    (tick, hash) => Some((parse(tick, "hash tick")?, parse(hash, "hash")?)),
+                };
+                let controls = controls
+                    .chunks(2)
+                    .map(|c| {
+                        Ok(Controls {
+                            throttle: parse(c[0], "throttle")?,
+                            rotation: parse(c[1], "rotation")?,
+                        })
+                    })
+                    .collect::<Result<Vec<Controls>, String>>()?;
+                Ok(Packet::Inputs {
+                    first_tick: parse(first_tick, "first tick")?,
+                    ack: parse(ack, "ack")?,
+                    hash,
+                    controls,
+                })
+            }
+            _ => Err(format!("unknown packet '{}'", text)),
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, field: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid {} '{}'", field, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Config, Difficulty};
+    use crate::net::*;
+    use crate::planet::Body;
+    use crate::simulation::Controls;
+
+    #[test]
+    fn packets_round_trip() {
+        let packets = [
+            Packet::Join,
+            Packet::Start {
+                seed: 42,
+                body: Body::Mars,
+                sync: Sync::Rollback { delay: 1, max_rollback: 8 },
+                config: Config::default(),
+            },
+            Packet::Start {
+                seed: u64::MAX,
+                body: Body::Moon,
+                sync: Sync::Lockstep { delay: 3 },
+                config: Difficulty::Hard.apply(Config::default()),
+            },
+            Packet::Inputs {
+                first_tick: 7,
+                ack: 5,
+                hash: Some((4, 0xdead_beef_0123_4567)),
+                controls: vec![
+                    Controls { throttle: 0.1, rotation: -1.0 },
+                    Controls { throttle: 1.0 / 3.0, rotation: 0.0 },
+                ],
+            },
+            Packet::Inputs {
+                first_tick: 0,
+                ack: 0,
+                hash: None,
+                controls: Vec::new(),
+            },
+        ];
+        for packet in packets {
+            assert_eq!(Packet::from_text(&packet.to_text()), Ok(packet));
+        }
+        assert!(Packet::from_text("inputs 1 2 - - 0.5").is_err());
+        assert!(Packet::from_text("start 1 pluto 2 0").is_err());
+        assert!(Packet::from_text("start 1 moon 2 0 physics.gravity").is_err());
+        assert!(Packet::from_text("start 1 moon 2 0 physics.gravity -1").is_err());
+        assert!(Packet::from_text("hello").is_err());
+    }
+}
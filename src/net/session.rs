@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::mainloop::{SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+use crate::net::transport::Transport;
+use crate::net::{Packet, MAX_INPUTS_PER_PACKET};
+use crate::planet::Body;
+use crate::simulation::{Controls, Snapshot, State, World};
+
+// Without a word from the peer for this long the race is over
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+// Handshake packets go out this often until answered
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+// Own state hashes kept for the peer's to compare with, in ticks
+const HASH_HISTORY: u32 = 256;
+
+const IDLE: Controls = Controls {
+    throttle: 0.0,
+    rotation: 0.0,
+};
+
+// How both peers keep their Worlds the same. Either way the own input is
+// only applied delay ticks after it was made, which gives it that long
+// to reach the peer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sync {
+    // Every tick waits for the input of both, the game stalls while the
+    // peer's input is late
+    Lockstep { delay: u32 },
+    // Up to max_rollback ticks run ahead with the peer's input predicted.
+    // Once its real input arrives and differs, those ticks are simulated
+    // again from a snapshot.
+    Rollback { delay: u32, max_rollback: u32 },
+}
+
+impl Sync {
+    pub(crate) fn parts(&self) -> (u32, u32) {
+        match *self {
+            Sync::Lockstep { delay } => (delay, 0),
+            Sync::Rollback { delay, max_rollback } => (delay, max_rollback),
+        }
+    }
+
+    // Without ticks to roll back it is lockstep
+    pub fn from_parts(delay: u32, max_rollback: u32) -> Self {
+        if max_rollback == 0 {
+            Sync::Lockstep { delay }
+        } else {
+            Sync::Rollback { delay, max_rollback }
+        }
+    }
+}
+
+// A tick simulated with the peer's input predicted
+struct Prediction {
+    before: Snapshot, // the World before the tick
+    remote: Controls, // the peer's input it was simulated with
+    hash: u64,        // of the World after the tick
+    state: State,     // of the World after the tick
+}
+
+// Races two Worlds on two machines. Each peer flies its own lander, the
+// host the first and the joiner the second, and sends its input to the
+// other. Both Worlds tick with the same input, so they stay the same.
+// Every peer also sends the state hash of its latest final tick, a
+// differing hash means the Worlds went apart.
+pub struct Session<T: Transport> {
+    transport: T,
+    sync: Sync,
+    local: usize,                     // the lander of this peer
+    tick: u32,                        // the next one the World simulates
+    inputs: [Vec<Controls>; 2],       // of each lander, by tick
+    remote_ack: u32,                  // the peer has our inputs before this tick
+    confirmed: u32,                   // ticks before ran with final input
+    predictions: VecDeque<Prediction>, // the ticks from confirmed on
+    hashes: BTreeMap<u32, u64>,       // own ones, by tick
+    remote_hashes: BTreeMap<u32, u64>, // the peer's, until ours are known
+    latest_hash: Option<(u32, u64)>,
+    outcome: State, // after the last final tick
+    desync: Option<u32>,
+    heard: Instant, // when the last packet of the peer arrived
+}
+
+impl<T: Transport> Session<T> {
+    // The World of both peers has to start out the same, with a lander
+    // for each. The first delay ticks are idle for both.
+    pub fn new(transport: T, local: usize, sync: Sync) -> Self {
+        let (delay, _) = sync.parts();
+        let idle = vec![IDLE; delay as usize];
+        Session {
+            transport,
+            sync,
+            local,
+            tick: 0,
+            inputs: [idle.clone(), idle],
+            remote_ack: 0,
+            confirmed: 0,
+            predictions: VecDeque::new(),
+            hashes: BTreeMap::new(),
+            remote_hashes: BTreeMap::new(),
+            latest_hash: None,
+            outcome: State::Running,
+            desync: None,
+            heard: Instant::now(),
+        }
+    }
+
+    pub fn local_player(&self) -> usize {
+        self.local
+    }
+
+    pub fn sync(&self) -> Sync {
+        self.sync
+    }
+
+    // The next tick the World simulates
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    // Ticks that ran with the input of both, they never change again
+    pub fn confirmed(&self) -> u32 {
+        self.confirmed
+    }
+
+    // The state of the World after the last final tick. Unlike the World's
+    // own it never turns out wrong, a race is only over once this says so.
+    pub fn outcome(&self) -> State {
+        self.outcome
+    }
+
+    // The first tick after which both Worlds differed, if any did
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    // Own state hash after a final tick, while it is kept
+    pub fn hash_at(&self, tick: u32) -> Option<u64> {
+        self.hashes.get(&tick).copied()
+    }
+
+    // To be called once per simulation tick with the local input. Exchanges
+    // input with the peer and ticks the World as far as the input allows,
+    // returns how many ticks it moved on. Zero means it waits for the peer.
+    pub fn step(&mut self, world: &mut World, controls: Controls) -> Result<u32, String> {
+        while let Some(datagram) = self.transport.recv()? {
+            let text = String::from_utf8_lossy(&datagram);
+            // Stray handshake packets and garbage are dropped, like any
+            // lost packet
+            if let Ok(packet) = Packet::from_text(&text) {
+                self.handle(packet);
+            }
+        }
+        if self.heard.elapsed() > PEER_TIMEOUT {
+            return Err(String::from("Lost the connection to the other player"));
+        }
+
+        let (delay, _) = self.sync.parts();
+        if self.inputs[self.local].len() as u32 <= self.tick + delay {
+            self.inputs[self.local].push(controls);
+        }
+        let start = self.tick;
+        self.advance(world);
+        self.send()?;
+        Ok(self.tick - start)
+    }
+
+    fn remote(&self) -> usize {
+        1 - self.local
+    }
+
+    fn handle(&mut self, packet: Packet) {
+        let Packet::Inputs { first_tick, ack, hash, controls } = packet else {
+            return;
+        };
+        self.heard = Instant::now();
+        self.remote_ack = self.remote_ack.max(ack);
+        let remote = &mut self.inputs[1 - self.local];
+        let known = remote.len() as u32;
+        // Packets that come out of order leave a gap, a later one fills it
+        if first_tick <= known {
+            remote.extend(controls.iter().skip((known - first_tick) as usize));
+        }
+        if let Some((tick, hash)) = hash {
+            match self.hashes.get(&tick) {
+                Some(own) => self.compare(tick, *own, hash),
+                None if tick >= self.confirmed => {
+                    self.remote_hashes.insert(tick, hash);
+                }
+                None => (),
+            }
+        }
+    }
+
+    fn compare(&mut self, tick: u32, own: u64, remote: u64) {
+        if own != remote && self.desync.is_none_or(|first| tick < first) {
+            self.desync = Some(tick);
+        }
+    }
+
+    // The peer's input of a tick, or the one it gave last as a prediction
+    fn remote_input(&self, tick: u32) -> Controls {
+        let remote = &self.inputs[self.remote()];
+        remote.get(tick as usize).or(remote.last()).copied().unwrap_or(IDLE)
+    }
+
+    fn advance(&mut self, world: &mut World) {
+        let (_, max_rollback) = self.sync.parts();
+        let remote_known = self.inputs[self.remote()].len() as u32;
+
+        // A wrong prediction sends the World back to the tick it was made
+        // for, from there on it is simulated again
+        let wrong = self.predictions.iter().enumerate().position(|(idx, p)| {
+            let tick = self.confirmed + idx as u32;
+            tick < remote_known && self.remote_input(tick) != p.remote
+        });
+        if let Some(idx) = wrong {
+            world.restore(&self.predictions[idx].before);
+            self.predictions.truncate(idx);
+            self.tick = self.confirmed + idx as u32;
+        }
+        self.confirm();
+
+        let local_known = self.inputs[self.local].len() as u32;
+        while self.tick < local_known && (self.tick < remote_known || self.tick < self.confirmed + max_rollback) {
+            let remote = self.remote_input(self.tick);
+            let fin = self.predictions.is_empty() && self.tick < remote_known;
+            let before = (!fin).then(|| world.snapshot());
+            world.apply_controls_to(self.local, self.inputs[self.local][self.tick as usize]);
+            world.apply_controls_to(self.remote(), remote);
+            world.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+            let hash = world.state_hash();
+            let state = *world.state();
+            match before {
+                None => {
+                    self.record_hash(self.tick, hash);
+                    self.outcome = state;
+                    self.confirmed += 1;
+                }
+                Some(before) => self.predictions.push_back(Prediction { before, remote, hash, state }),
+            }
+            self.tick += 1;
+        }
+        self.confirm();
+    }
+
+    // Predictions that turned out right are final
+    fn confirm(&mut self) {
+        let remote_known = self.inputs[self.remote()].len() as u32;
+        while self.confirmed < remote_known && !self.predictions.is_empty() {
+            let prediction = self.predictions.pop_front().unwrap();
+            self.record_hash(self.confirmed, prediction.hash);
+            self.outcome = prediction.state;
+            self.confirmed += 1;
+        }
+    }
+
+    fn record_hash(&mut self, tick: u32, hash: u64) {
+        self.hashes.insert(tick, hash);
+        self.latest_hash = Some((tick, hash));
+        if let Some(remote) = self.remote_hashes.remove(&tick) {
+            self.compare(tick, hash, remote);
+        }
+        let oldest = tick.saturating_sub(HASH_HISTORY);
+        self.hashes = self.hashes.split_off(&oldest);
+        self.remote_hashes = self.remote_hashes.split_off(&oldest);
+    }
+
+    // Everything the peer has not acknowledged yet, every time
+    fn send(&mut self) -> Result<(), String> {
+        let own = &self.inputs[self.local];
+        let first = (self.remote_ack as usize).min(own.len());
+        let last = own.len().min(first + MAX_INPUTS_PER_PACKET);
+        let packet = Packet::Inputs {
+            first_tick: first as u32,
+            ack: self.inputs[self.remote()].len() as u32,
+            hash: self.latest_hash,
+            controls: own[first..last].to_vec(),
+        };
+        self.transport.send(packet.to_text().as_bytes())
+    }
+}
+
+// Waits for a peer to join and tells it the race, returns the session of
+// the first lander once the peer started its own
+pub fn host<T: Transport>(
+    mut transport: T,
+    seed: u64,
+    body: Body,
+    config: Config,
+    sync: Sync,
+    timeout: Duration,
+) -> Result<Session<T>, String> {
+    let start = Packet::Start { seed, body, sync, config };
+    let deadline = Instant::now() + timeout;
+    loop {
+        while let Some(datagram) = transport.recv()? {
+            match Packet::from_text(&String::from_utf8_lossy(&datagram)) {
+                Ok(Packet::Join) => transport.send(start.to_text().as_bytes())?,
+                Ok(packet @ Packet::Inputs { .. }) => {
+                    let mut session = Session::new(transport, 0, sync);
+                    session.handle(packet);
+                    return Ok(session);
+                }
+                _ => (),
+            }
+        }
+        if Instant::now() > deadline {
+            return Err(String::from("Nobody joined"));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+// Asks the host to race until it answers with the seed, body and config
+// to fly, returns the session of the second lander
+pub fn join<T: Transport>(mut transport: T, timeout: Duration) -> Result<(Session<T>, u64, Body, Config), String> {
+    let deadline = Instant::now() + timeout;
+    let mut asked: Option<Instant> = None;
+    loop {
+        if asked.is_none_or(|at| at.elapsed() >= RESEND_INTERVAL) {
+            transport.send(Packet::Join.to_text().as_bytes())?;
+            asked = Some(Instant::now());
+        }
+        while let Some(datagram) = transport.recv()? {
+            if let Ok(Packet::Start { seed, body, sync, config }) = Packet::from_text(&String::from_utf8_lossy(&datagram)) {
+                return Ok((Session::new(transport, 1, sync), seed, body, config));
+            }
+        }
+        if Instant::now() > deadline {
+            return Err(String::from("The host did not answer"));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use sdl2::pixels::Color;
+
+    use crate::config::{Config, Difficulty};
+    use crate::mainloop::{SIM_TICK_MS, SIM_TICK_RESOLUTION_MS};
+    use crate::net::*;
+    use crate::planet::Body;
+    use crate::simulation::{Controls, World, SPAWN_POSITION};
+    use crate::vecmath::Vec2d;
+
+    const TICKS: u32 = 120;
+
+    fn race_world(seed: u64, body: Body, config: Config) -> World {
+        let mut world = World::new(800, 600, seed, config, body);
+        world.add_lander(SPAWN_POSITION + Vec2d::new(300.0, 0.0), Color::RGB(255, 0, 0));
+        world
+    }
+
+    // A pilot that changes its mind every few ticks, differently for each
+    // lander, so predictions go wrong
+    fn pilot(player: usize, tick: u32) -> Controls {
+        let phase = (tick / (3 + 2 * player as u32)) % 4;
+        Controls {
+            throttle: if phase < 2 { 0.6 } else { 0.0 },
+            rotation: phase as f32 / 2.0 - 0.75,
+        }
+    }
+
+    // Flies until both peers have TICKS final ticks, the one that got
+    // there first keeps sending its input for the other. Returns the
+    // World's hash after TICKS and the session.
+    fn fly<T: Transport>(mut session: Session<T>, mut world: World, done: Arc<AtomicUsize>) -> (u64, Session<T>) {
+        let mut counted = false;
+        while done.load(Ordering::SeqCst) < 2 {
+            let controls = pilot(session.local_player(), session.tick());
+            session.step(&mut world, controls).unwrap();
+            if !counted && session.confirmed() > TICKS {
+                done.fetch_add(1, Ordering::SeqCst);
+                counted = true;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        (session.hash_at(TICKS).unwrap(), session)
+    }
+
+    fn race(sync: Sync, loss: f32, latency: Duration) {
+        let host_transport = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host_transport.local_addr().unwrap().to_string();
+        let done = Arc::new(AtomicUsize::new(0));
+        let host_done = done.clone();
+        let host_side = thread::spawn(move || {
+            let lossy = LossyTransport::new(host_transport, loss, latency, latency / 2, 1);
+            let session = host(lossy, 11, Body::Mars, Config::default(), sync, Duration::from_secs(10)).unwrap();
+            fly(session, race_world(11, Body::Mars, Config::default()), host_done)
+        });
+
+        let joiner_transport = UdpTransport::connect(&address).unwrap();
+        let lossy = LossyTransport::new(joiner_transport, loss, latency, latency / 2, 2);
+        let (session, seed, body, config) = join(lossy, Duration::from_secs(10)).unwrap();
+        assert_eq!((seed, body, session.sync()), (11, Body::Mars, sync));
+        let (joiner_hash, joiner) = fly(session, race_world(seed, body, config), done);
+        let (host_hash, host) = host_side.join().unwrap();
+
+        assert_eq!(host_hash, joiner_hash);
+        assert_eq!(host.desync(), None);
+        assert_eq!(joiner.desync(), None);
+    }
+
+    #[test]
+    fn lockstep_races_stay_in_sync_over_a_bad_network() {
+        race(Sync::Lockstep { delay: 3 }, 0.2, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn rollback_races_stay_in_sync_over_a_bad_network() {
+        race(Sync::Rollback { delay: 1, max_rollback: 8 }, 0.2, Duration::from_millis(10));
+    }
+
+    // The joiner's own tuning would give it other terrain and physics, it
+    // flies with the config of the host instead
+    #[test]
+    fn joiners_race_with_the_config_of_the_host() {
+        let host_transport = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host_transport.local_addr().unwrap().to_string();
+        let mut host_config = Difficulty::Hard.apply(Config::default());
+        host_config.terrain.y_max_delta *= 2.0;
+        let done = Arc::new(AtomicUsize::new(0));
+        let host_done = done.clone();
+        let sync = Sync::Lockstep { delay: 2 };
+        let host_side = thread::spawn(move || {
+            let session = host(host_transport, 7, Body::Moon, host_config, sync, Duration::from_secs(10)).unwrap();
+            fly(session, race_world(7, Body::Moon, host_config), host_done)
+        });
+
+        let local_config = Difficulty::Easy.apply(Config::default());
+        let joiner_transport = UdpTransport::connect(&address).unwrap();
+        let (session, seed, body, config) = join(joiner_transport, Duration::from_secs(10)).unwrap();
+        assert_eq!(config, host_config);
+        assert_ne!(race_world(seed, body, local_config).state_hash(), race_world(seed, body, config).state_hash());
+        let (joiner_hash, joiner) = fly(session, race_world(seed, body, config), done);
+        let (host_hash, host) = host_side.join().unwrap();
+
+        assert_eq!(host_hash, joiner_hash);
+        assert_eq!(host.desync(), None);
+        assert_eq!(joiner.desync(), None);
+    }
+
+    // A peer that ticks a different World is told so by the hashes
+    #[test]
+    fn desyncs_are_detected() {
+        let host_transport = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host_transport.local_addr().unwrap().to_string();
+        let joiner_transport = UdpTransport::connect(&address).unwrap();
+        let sync = Sync::Lockstep { delay: 2 };
+        let mut host = Session::new(host_transport, 0, sync);
+        let mut joiner = Session::new(joiner_transport, 1, sync);
+        let mut host_world = race_world(3, Body::Moon, Config::default());
+        let mut joiner_world = race_world(3, Body::Moon, Config::default());
+        joiner_world.add_lander(SPAWN_POSITION, Color::RGB(0, 255, 0));
+
+        for _ in 0..200 {
+            host.step(&mut host_world, pilot(0, host.tick())).unwrap();
+            joiner.step(&mut joiner_world, pilot(1, joiner.tick())).unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(host.confirmed() > 10);
+        assert!(host.desync().is_some());
+        assert!(joiner.desync().is_some());
+    }
+
+    #[test]
+    fn rollback_matches_a_world_that_knew_all_input() {
+        let host_transport = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host_transport.local_addr().unwrap().to_string();
+        let joiner_transport = UdpTransport::connect(&address).unwrap();
+        let latency = Duration::from_millis(20);
+        let sync = Sync::Rollback { delay: 0, max_rollback: 10 };
+        let mut host = Session::new(LossyTransport::new(host_transport, 0.0, latency, Duration::ZERO, 1), 0, sync);
+        let mut joiner = Session::new(LossyTransport::new(joiner_transport, 0.0, latency, Duration::ZERO, 2), 1, sync);
+        let mut host_world = race_world(5, Body::Moon, Config::default());
+        let mut joiner_world = race_world(5, Body::Moon, Config::default());
+        while host.confirmed() < 60 || joiner.confirmed() < 60 {
+            host.step(&mut host_world, pilot(0, host.tick())).unwrap();
+            joiner.step(&mut joiner_world, pilot(1, joiner.tick())).unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        let mut offline = race_world(5, Body::Moon, Config::default());
+        for tick in 0..60 {
+            offline.apply_controls_to(0, pilot(0, tick));
+            offline.apply_controls_to(1, pilot(1, tick));
+            offline.tick(SIM_TICK_MS, SIM_TICK_RESOLUTION_MS);
+            assert_eq!(host.hash_at(tick), Some(offline.state_hash()), "tick {}", tick);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+// Larger than any packet the session sends
+const MAX_DATAGRAM: usize = 2048;
+
+// Carries datagrams to the peer and back. Packets may get lost, arrive
+// late or out of order, the session copes with all of that.
+pub trait Transport {
+    fn send(&mut self, datagram: &[u8]) -> Result<(), String>;
+    // The next datagram that arrived, None if there is none right now
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String>;
+}
+
+impl Transport for Box<dyn Transport> {
+    fn send(&mut self, datagram: &[u8]) -> Result<(), String> {
+        self.as_mut().send(datagram)
+    }
+
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String> {
+        self.as_mut().recv()
+    }
+}
+
+// A non-blocking UDP socket. The joiner connects to the host, the host
+// answers whoever sent the first datagram and ignores everybody else.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    // Waits for a peer on the address, e.g. "0.0.0.0:7777"
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let socket = UdpSocket::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+        socket.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(UdpTransport { socket, peer: None })
+    }
+
+    // Talks to the host at the address, e.g. "192.168.0.2:7777"
+    pub fn connect(address: &str) -> Result<Self, String> {
+        let peer = address
+            .to_socket_addrs()
+            .map_err(|e| format!("{}: {}", address, e))?
+            .next()
+            .ok_or(format!("{}: no address", address))?;
+        let local = if peer.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let mut transport = Self::bind(local)?;
+        transport.peer = Some(peer);
+        Ok(transport)
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.socket.local_addr().map_err(|e| e.to_string())
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, datagram: &[u8]) -> Result<(), String> {
+        let Some(peer) = self.peer else {
+            return Ok(());
+        };
+        match self.socket.send_to(datagram, peer) {
+            Ok(_) => Ok(()),
+            // A full buffer drops the datagram, just like the network would
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(format!("{}: {}", peer, e)),
+        }
+    }
+
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String> {
+        let mut buffer = [0u8; MAX_DATAGRAM];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, from)) => {
+                    if *self.peer.get_or_insert(from) == from {
+                        return Ok(Some(buffer[..size].to_vec()));
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                // Windows reports datagrams the peer did not take yet
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => return Ok(None),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+}
+
+// Makes any transport as bad as a real network can be: it drops a share
+// of the datagrams it sends and delivers the others late, by a random
+// jitter out of order. Seeded, so the same seed loses the same packets.
+pub struct LossyTransport<T: Transport> {
+    inner: T,
+    loss: f32, // 0.0 - 1.0
+    latency: Duration,
+    jitter: Duration,
+    rng: StdRng,
+    in_flight: Vec<(Instant, Vec<u8>)>,
+}
+
+impl<T: Transport> LossyTransport<T> {
+    pub fn new(inner: T, loss: f32, latency: Duration, jitter: Duration, seed: u64) -> Self {
+        LossyTransport {
+            inner,
+            loss: loss.clamp(0.0, 1.0),
+            latency,
+            jitter,
+            rng: StdRng::seed_from_u64(seed),
+            in_flight: Vec::new(),
+        }
+    }
+
+    // Hands the datagrams that are due to the inner transport
+    fn deliver(&mut self) -> Result<(), String> {
+        let now = Instant::now();
+        let mut idx = 0;
+        while idx < self.in_flight.len() {
+            if self.in_flight[idx].0 <= now {
+                let (_, datagram) = self.in_flight.remove(idx);
+                self.inner.send(&datagram)?;
+            } else {
+                idx += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for LossyTransport<T> {
+    fn send(&mut self, datagram: &[u8]) -> Result<(), String> {
+        if self.rng.gen::<f32>() >= self.loss {
+            let jitter = self.jitter.mul_f32(self.rng.gen::<f32>());
+            self.in_flight.push((Instant::now() + self.latency + jitter, datagram.to_vec()));
+        }
+        self.deliver()
+    }
+
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String> {
+        self.deliver()?;
+        self.inner.recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::net::transport::*;
+
+    fn wait_for(transport: &mut impl Transport) -> Vec<u8> {
+        for _ in 0..1000 {
+            if let Some(datagram) = transport.recv().unwrap() {
+                return datagram;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("nothing arrived");
+    }
+
+    #[test]
+    fn udp_reaches_the_peer_over_localhost() {
+        let mut host = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host.local_addr().unwrap().to_string();
+        let mut joiner = UdpTransport::connect(&address).unwrap();
+        joiner.send(b"ping").unwrap();
+        assert_eq!(wait_for(&mut host), b"ping");
+        host.send(b"pong").unwrap();
+        assert_eq!(wait_for(&mut joiner), b"pong");
+        assert_eq!(host.recv(), Ok(None));
+    }
+
+    #[test]
+    fn lossy_links_drop_and_delay() {
+        let mut host = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let address = host.local_addr().unwrap().to_string();
+        let joiner = UdpTransport::connect(&address).unwrap();
+        let latency = Duration::from_millis(30);
+        let mut lossy = LossyTransport::new(joiner, 0.5, latency, Duration::ZERO, 1);
+        for idx in 0..100u8 {
+            lossy.send(&[idx]).unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(host.recv(), Ok(None));
+
+        thread::sleep(latency);
+        lossy.recv().unwrap();
+        let mut arrived = 0;
+        while host.recv().unwrap().is_some() {
+            arrived += 1;
+        }
+        assert!(arrived > 30 && arrived < 70, "{} of 100 arrived", arrived);
+    }
+}
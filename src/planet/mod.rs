@@ -0,0 +1,210 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use crate::config::Config;
+use crate::terrain::TerrainStyle;
+use crate::{window_height, window_width};
+
+// Height of the bands the atmospheric haze is drawn with
+const HAZE_BAND: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Body {
+    Moon,
+    Mars,
+    Mercury,
+    Asteroid,
+    Titan,
+}
+
+pub const BODIES: [Body; 5] = [Body::Moon, Body::Mars, Body::Mercury, Body::Asteroid, Body::Titan];
+
+pub struct Palette {
+    pub sky: Color,
+    pub terrain: Color,
+}
+
+// How a body changes the config. The config describes the Moon, gravity
+// and terrain of the other bodies are given relative to it.
+struct Environment {
+    gravity: f32,    // relative to the Moon
+    roughness: f32,  // scales the height variation of the terrain
+    jaggedness: f32, // > 1.0 keeps more variation for small segments
+    atmosphere: f32, // density, 0.0 for none
+    wind: f32,       // in pixel per second
+    terrain: TerrainStyle,
+    palette: Palette,
+}
+
+impl Body {
+    pub fn from_name(name: &str) -> Option<Self> {
+        BODIES.iter().copied().find(|b| b.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Body::Moon => "Moon",
+            Body::Mars => "Mars",
+            Body::Mercury => "Mercury",
+            Body::Asteroid => "Asteroid",
+            Body::Titan => "Titan",
+        }
+    }
+
+    // Levels cycle through the bodies, starting with the given one
+    pub fn for_level(first: Body, level: u32) -> Body {
+        let start = BODIES.iter().position(|b| *b == first).unwrap();
+        BODIES[(start + level.saturating_sub(1) as usize) % BODIES.len()]
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.environment().palette
+    }
+
+    pub fn terrain_style(&self) -> TerrainStyle {
+        self.environment().terrain
+    }
+
+    pub fn apply(&self, config: Config) -> Config {
+        let env = self.environment();
+        let mut config = config;
+        config.physics.gravity *= env.gravity;
+        config.terrain.y_max_delta *= env.roughness;
+        config.terrain.y_delta_divider /= env.jaggedness;
+        config.atmosphere.density = config.atmosphere.density.max(env.atmosphere);
+        if config.atmosphere.wind_speed == 0.0 {
+            config.atmosphere.wind_speed = env.wind;
+        }
+        config
+    }
+
+    fn environment(&self) -> Environment {
+        match self {
+            Body::Moon => Environment {
+                gravity: 1.0,
+                roughness: 1.0,
+                jaggedness: 1.0,
+                atmosphere: 0.0,
+                wind: 0.0,
+                terrain: TerrainStyle::Midpoint,
+                palette: Palette {
+                    sky: Color::RGB(0, 0, 0),
+                    terrain: Color::RGB(255, 255, 255),
+                },
+            },
+            Body::Mars => Environment {
+                gravity: 2.3,
+                roughness: 0.8,
+                jaggedness: 1.0,
+                atmosphere: 0.1,
+                wind: 15.0,
+                terrain: TerrainStyle::Canyons,
+                palette: Palette {
+                    sky: Color::RGB(40, 12, 6),
+                    terrain: Color::RGB(230, 120, 70),
+                },
+            },
+            Body::Mercury => Environment {
+                gravity: 2.3,
+                roughness: 1.2,
+                jaggedness: 1.1,
+                atmosphere: 0.0,
+                wind: 0.0,
+                terrain: TerrainStyle::Craters,
+                palette: Palette {
+                    sky: Color::RGB(0, 0, 0),
+                    terrain: Color::RGB(180, 170, 160),
+                },
+            },
+            Body::Asteroid => Environment {
+                gravity: 0.2,
+                roughness: 1.5,
+                jaggedness: 1.2,
+                atmosphere: 0.0,
+                wind: 0.0,
+                terrain: TerrainStyle::Ridges,
+                palette: Palette {
+                    sky: Color::RGB(0, 0, 0),
+                    terrain: Color::RGB(150, 140, 120),
+                },
+            },
+            Body::Titan => Environment {
+                gravity: 0.85,
+                roughness: 0.6,
+                jaggedness: 0.9,
+                atmosphere: 0.5,
+                wind: 5.0,
+                terrain: TerrainStyle::Ridges,
+                palette: Palette {
+                    sky: Color::RGB(50, 35, 10),
+                    terrain: Color::RGB(220, 170, 80),
+                },
+            },
+        }
+    }
+
+    // Fills the sky and, with an atmosphere, a haze that gets thicker
+    // towards the ground
+    pub fn render_sky(&self, canvas: &mut Canvas<Window>, config: &Config) -> Result<(), String> {
+        let palette = self.palette();
+        canvas.set_draw_color(palette.sky);
+        canvas.fill_rect(None)?;
+
+        let atmosphere = config.atmosphere;
+        if atmosphere.density <= 0.0 {
+            return Ok(());
+        }
+        let (r, g, b) = palette.terrain.rgb();
+        canvas.set_blend_mode(BlendMode::Blend);
+        let mut altitude = 0;
+        while altitude < window_height {
+            let density = atmosphere.density * (-(altitude as f32) / atmosphere.scale_height).exp();
+            let alpha = (density * 60.0).min(120.0) as u8;
+            if alpha == 0 {
+                break;
+            }
+            canvas.set_draw_color(Color::RGBA(r, g, b, alpha));
+            let y = window_height as i32 - (altitude + HAZE_BAND) as i32;
+            canvas.fill_rect(Rect::new(0, y, window_width, HAZE_BAND))?;
+            altitude += HAZE_BAND;
+        }
+        canvas.set_blend_mode(BlendMode::None);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::planet::*;
+
+    #[test]
+    fn levels_cycle_through_all_bodies() {
+        let bodies: Vec<Body> = (1..=6).map(|l| Body::for_level(Body::Moon, l)).collect();
+        assert_eq!(bodies[..5], BODIES);
+        assert_eq!(bodies[5], Body::Moon);
+        assert_eq!(Body::for_level(Body::Titan, 2), Body::Moon);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for body in BODIES {
+            assert_eq!(Body::from_name(body.name()), Some(body));
+        }
+        assert_eq!(Body::from_name("mars"), Some(Body::Mars));
+        assert_eq!(Body::from_name("Pluto"), None);
+    }
+
+    #[test]
+    fn moon_is_the_plain_config() {
+        assert_eq!(Body::Moon.apply(Config::default()), Config::default());
+        let mars = Body::Mars.apply(Config::default());
+        assert!(mars.physics.gravity > Config::default().physics.gravity);
+        assert!(mars.atmosphere.density > 0.0 && mars.atmosphere.wind_speed != 0.0);
+        let asteroid = Body::Asteroid.apply(Config::default());
+        assert!(asteroid.physics.gravity < Config::default().physics.gravity);
+        assert_eq!(asteroid.atmosphere.density, 0.0);
+    }
+}
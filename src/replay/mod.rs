@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::planet::Body;
+use crate::simulation::Controls;
+
+const HEADER: &str = "lunar-replay 1";
+
+// Everything needed to fly a level again: the map seed, the config and
+// the controls of every single world tick. Since the World is
+// deterministic, playing the controls back reproduces the run exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: Config,
+    pub body: Body,
+    inputs: Vec<Controls>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: Config, body: Body) -> Self {
+        Replay {
+            seed,
+            config,
+            body,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, controls: Controls) {
+        self.inputs.push(controls);
+    }
+
+    pub fn controls_at(&self, tick: usize) -> Option<Controls> {
+        self.inputs.get(tick).copied()
+    }
+
+    pub fn controls(&self) -> &[Controls] {
+        &self.inputs
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_text(&text).map_err(|e| format!("{}:{}", path.display(), e))
+    }
+
+    // Controls are stored run length encoded, a line per change:
+    // "ticks <count> <throttle> <rotation>"
+    fn to_text(&self) -> String {
+        let mut text = format!("{}\nseed {}\nbody {}\n", HEADER, self.seed, self.body.name());
+        for (key, value) in self.config.values() {
+            text += &format!("config {} {}\n", key, value);
+        }
+        let mut idx = 0;
+        while idx < self.inputs.len() {
+            let controls = self.inputs[idx];
+            let count = self.inputs[idx..].iter().take_while(|c| **c == controls).count();
+            text += &format!("ticks {} {} {}\n", count, controls.throttle, controls.rotation);
+            idx += count;
+        }
+        text
+    }
+
+    fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.trim() == HEADER => (),
+            _ => return Err(format!("1: expected '{}'", HEADER)),
+        }
+
+        let mut replay = Replay::new(0, Config::default(), Body::Moon);
+        for (idx, line) in lines {
+            let line_no = idx + 1;
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [] => (),
+                ["seed", seed] => replay.seed = parse(seed, line_no, "seed")?,
+                ["body", name] => {
+                    replay.body = Body::from_name(name).ok_or(format!("{}: invalid body '{}'", line_no, name))?
+                }
+                ["config", key, value] => replay
+                    .config
+                    .set(key, value)
+                    .map_err(|e| format!("{}: {}", line_no, e))?,
+                ["ticks", count, throttle, rotation] => {
+                    let count: usize = parse(count, line_no, "ticks")?;
+                    let controls = Controls {
+                        throttle: parse(throttle, line_no, "throttle")?,
+                        rotation: parse(rotation, line_no, "rotation")?,
+                    };
+                    replay.inputs.extend(std::iter::repeat_n(controls, count));
+                }
+                _ => return Err(format!("{}: unexpected line '{}'", line_no, line)),
+            }
+        }
+        Ok(replay)
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, line_no: usize, field: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: invalid {} '{}'", line_no, field, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::planet::Body;
+    use crate::replay::Replay;
+    use crate::simulation::Controls;
+
+    fn controls(throttle: f32, rotation: f32) -> Controls {
+        Controls { throttle, rotation }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut replay = Replay::new(1234, Config::default(), Body::Mars);
+        replay.config.set("lander.thrust", "4.5").unwrap();
+        for _ in 0..10 {
+            replay.record(controls(0.0, 0.0));
+        }
+        for _ in 0..5 {
+            replay.record(controls(1.0, -1.0));
+        }
+        replay.record(controls(0.3, 0.0));
+
+        let text = replay.to_text();
+        assert_eq!(text.lines().filter(|l| l.starts_with("ticks")).count(), 3);
+        let loaded = Replay::from_text(&text).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.controls_at(15), Some(controls(0.3, 0.0)));
+        assert_eq!(loaded.controls_at(12), Some(controls(1.0, -1.0)));
+        assert_eq!(loaded.controls_at(16), None);
+    }
+
+    #[test]
+    fn errors_name_line_and_field() {
+        let err = Replay::from_text("lunar-replay 1\nseed 1\nseed lots\n").unwrap_err();
+        assert_eq!(err, "3: invalid seed 'lots'");
+        let err = Replay::from_text("lunar-replay 1\nconfig lander.fuel 3\n").unwrap_err();
+        assert_eq!(err, "2: unknown key 'lander.fuel'");
+        let err = Replay::from_text("lunar-replay 1\nspeed 3\n").unwrap_err();
+        assert_eq!(err, "2: unexpected line 'speed 3'");
+        let err = Replay::from_text("lunar-replay 1\nbody Pluto\n").unwrap_err();
+        assert_eq!(err, "2: invalid body 'Pluto'");
+        assert!(Replay::from_text("something else").is_err());
+    }
+}
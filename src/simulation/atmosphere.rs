@@ -0,0 +1,139 @@
+use crate::config::AtmosphereConfig;
+use crate::vecmath::Vec2d;
+use crate::window_height;
+
+// Altitude over which the wind picks up its full strength, in multiples of
+// the scale height
+const WIND_SHEAR_HEIGHT: f32 = 1.0;
+
+// Density, drag and wind of the air. Altitude is measured upwards from the
+// bottom of the window. Without density there is neither drag nor wind.
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+    config: AtmosphereConfig,
+    phase: f32, // shifts the gusts, so levels differ
+    time: f32,  // in seconds
+}
+
+impl Atmosphere {
+    pub fn new(config: AtmosphereConfig, phase: f32) -> Self {
+        Atmosphere {
+            config,
+            phase,
+            time: 0.0,
+        }
+    }
+
+    // Keeps the gusts going where they are
+    pub fn set_config(&mut self, config: AtmosphereConfig) {
+        self.config = config;
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.config.density > 0.0
+    }
+
+    pub fn advance(&mut self, time_in_s: f32) {
+        self.time += time_in_s;
+    }
+
+    fn altitude(position: Vec2d) -> f32 {
+        (window_height as f32 - position.y).max(0.0)
+    }
+
+    pub fn density_at(&self, position: Vec2d) -> f32 {
+        self.config.density * (-Self::altitude(position) / self.config.scale_height).exp()
+    }
+
+    // Horizontal wind, weaker close to the ground and gusting over time
+    // and height
+    pub fn wind_at(&self, position: Vec2d) -> Vec2d {
+        if !self.is_present() {
+            return Vec2d::default();
+        }
+        let altitude = Self::altitude(position);
+        let shear = (altitude / (self.config.scale_height * WIND_SHEAR_HEIGHT)).min(1.0);
+        let t = self.time;
+        let gust = 0.6 * (0.9 * t + self.phase).sin() + 0.4 * (2.3 * t + altitude / 40.0 + 2.0 * self.phase).sin();
+        let speed = self.config.wind_speed * (0.5 + 0.5 * shear) * (1.0 + self.config.gustiness * gust);
+        Vec2d::new(speed, 0.0)
+    }
+
+    // Acceleration by velocity squared drag against the moving air. It
+    // never reverses the relative velocity within one step, so big time
+    // steps stay stable.
+    pub fn drag(&self, position: Vec2d, velocity: Vec2d, time_in_s: f32) -> Vec2d {
+        if !self.is_present() {
+            return Vec2d::default();
+        }
+        let relative = velocity - self.wind_at(position);
+        let speed = relative.len();
+        if speed == 0.0 {
+            return Vec2d::default();
+        }
+        let decel = (self.config.drag * self.density_at(position) * speed * speed).min(speed / time_in_s);
+        relative * (-decel / speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::simulation::atmosphere::Atmosphere;
+    use crate::vecmath::Vec2d;
+
+    fn thick() -> Atmosphere {
+        let mut config = Config::default().atmosphere;
+        config.density = 1.0;
+        config.wind_speed = 2.0;
+        Atmosphere::new(config, 0.0)
+    }
+
+    #[test]
+    fn vacuum_has_no_drag_or_wind() {
+        let a = Atmosphere::new(Config::default().atmosphere, 0.0);
+        assert!(!a.is_present());
+        assert_eq!(a.drag(Vec2d::new(0.0, 0.0), Vec2d::new(10.0, 10.0), 0.05), Vec2d::default());
+        assert_eq!(a.wind_at(Vec2d::new(0.0, 0.0)), Vec2d::default());
+    }
+
+    #[test]
+    fn density_falls_with_altitude() {
+        let a = thick();
+        let ground = a.density_at(Vec2d::new(0.0, crate::window_height as f32));
+        let high = a.density_at(Vec2d::new(0.0, 0.0));
+        assert_eq!(ground, 1.0);
+        assert!(high < ground * 0.1);
+    }
+
+    #[test]
+    fn drag_opposes_the_relative_velocity() {
+        let a = thick();
+        let position = Vec2d::new(0.0, 500.0);
+        let wind = a.wind_at(position);
+        let falling = a.drag(position, wind + Vec2d::new(0.0, 5.0), 0.05);
+        assert!(falling.y < 0.0);
+        assert!(falling.x.abs() < 1e-4);
+        // Drifting with the wind means no drag at all
+        assert_eq!(a.drag(position, wind, 0.05), Vec2d::default());
+        // A huge step only stops the motion, it does not reverse it
+        let stopped = a.drag(position, wind + Vec2d::new(0.0, 100.0), 10.0);
+        assert!((stopped.y * 10.0 + 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wind_gusts_over_time_and_grows_with_height() {
+        let mut a = thick();
+        let low = Vec2d::new(0.0, crate::window_height as f32);
+        let high = Vec2d::new(0.0, 100.0);
+        let before = a.wind_at(high);
+        a.advance(1.5);
+        assert_ne!(a.wind_at(high), before);
+        let mut config = Config::default().atmosphere;
+        config.density = 1.0;
+        config.wind_speed = 2.0;
+        config.gustiness = 0.0;
+        let calm = Atmosphere::new(config, 0.0);
+        assert!(calm.wind_at(high).x > calm.wind_at(low).x);
+    }
+}
@@ -2,36 +2,100 @@ use std::{f32::consts::PI, num};
 
 use sdl2::pixels::Color;
 use sdl2::rect::Point;
+use sdl2::render::BlendMode;
 
 use crate::vecmath::TransformationMatrix;
-use crate::graphics::{self, renderGameOver, renderWonText};
+
+mod atmosphere;
+mod particles;
+
+use atmosphere::Atmosphere;
+use particles::ParticleSystem;
+use crate::config::Config;
+use crate::control::Observation;
+use crate::graphics;
+use crate::level::{Hazard, Level, Pad};
+use crate::planet::Body;
 use crate::{
     collision, draw, hud,
     map::PointList,
+    camera::Camera,
+    terrain::{self, FuelBudget, PadRules, TerrainLod},
     vecmath::{self, Vec2d},
 };
 
+// Contact response tuning. Speeds are in pixel per simulated second, the
+// lander has a mass of 1.
+const BOUNCE_SPEED: f32 = 7.5;
+const RESTITUTION: f32 = 0.4;
+const FRICTION: f32 = 0.25;
+const LANDER_INERTIA: f32 = 150.0;
+const CONTACT_SLOP: f32 = 0.5;
+const REST_SPEED: f32 = 0.5;
+const REST_SPIN: f32 = 0.25;
+const REST_TIME: f32 = 0.5; // in seconds
+// The exhaust plume reaches three lander heights below the nozzle
+const PLUME_LENGTH: f32 = 3.0 * graphics::LanderHeight as f32 * graphics::LanderScale.y;
+// Where new entities, and so the lander, start out
+pub const SPAWN_POSITION: Vec2d = Vec2d::new(200.0, 300.0);
+// Ghosts are drawn see-through
+const GHOST_ALPHA: u8 = 90;
+
+// What the pilot does with the lander during one tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Controls {
+    pub throttle: f32, // 0.0 - 1.0
+    pub rotation: f32, // -1.0 (left) - 1.0 (right)
+}
+
 struct Physics {
     gravity: f32, // force applied per second!
     gravity_direction: Vec2d,
+    atmosphere: Atmosphere,
 }
 
+#[derive(Clone)]
 pub struct Entity {
     position: Vec2d,
     direction: Vec2d,    // non normalized, has speed integrated!
     acceleration: Vec2d, // non normalized, has force integrated!
+    orientation: f32,    // in radians
+    spin: f32,           // in radians per second
     update: bool,
 }
 
+#[derive(Clone)]
 pub struct Lander {
     entity_id: usize,
     fuel: f32,     // in seconds!
     facing: Vec2d, // This is the direction the engine is facing, i.e. any thrust is opposite to this!
     drive_enabled: bool,
+    throttle: f32, // 0.0 - 1.0
     rotation: f32,
+    grounded: bool,
+    rest_time: f32, // in seconds, time spent standing still on both feet
+    state: State,
+    color: Color,
+    pad: Option<usize>, // claimed by landing on it first
+}
+
+// All it takes to draw a lander
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub position: Vec2d,
+    pub orientation: f32,
+    pub throttle: f32, // 0.0 with the engine off
+}
+
+// A lander came to rest safely, with touch and go it flies on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Touchdown {
+    pub lander: usize,
+    pub x: f32,
+    pub pad: Option<usize>, // index into the pads
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum State {
     Running,
     Won,
@@ -41,10 +105,36 @@ pub enum State {
 pub struct World {
     p: Physics,
     entities: Vec<Entity>,
-    lander: Option<Lander>,
+    landers: Vec<Lander>, // the first one is the player's
     map: PointList,
+    lod: TerrainLod, // of the map, for drawing and collision
+    camera: Camera,
     hud: hud::Hud,
     game_state: State,
+    particles: ParticleSystem,
+    show_hud: bool,
+    show_bbox: bool,
+    body: Body,
+    config: Config, // with the body applied
+    gravity: Option<f32>, // of a hand-made level, replaces the body's
+    pads: Vec<Pad>,
+    hazards: Vec<Hazard>,
+    ghost: Option<Pose>, // of an earlier run, only drawn
+    touch_and_go: bool, // touchdowns do not decide the landers
+    touchdowns: Vec<Touchdown>, // since last taken
+    highlights: Vec<(f32, f32)>, // parts of the surface to mark, from and to x
+}
+
+// All a tick changes of the World, to go back to an earlier tick and
+// simulate it again, e.g. once late network input arrived
+#[derive(Clone)]
+pub struct Snapshot {
+    entities: Vec<Entity>,
+    landers: Vec<Lander>,
+    game_state: State,
+    atmosphere: Atmosphere,
+    particles: ParticleSystem,
+    camera: Camera,
 }
 
 impl Entity {
@@ -53,6 +143,8 @@ impl Entity {
             position: Vec2d::default(),
             direction: Vec2d::default(),
             acceleration: Vec2d::default(),
+            orientation: 0.0,
+            spin: 0.0,
             update: true,
         }
     }
@@ -73,8 +165,9 @@ impl Entity {
 impl Physics {
     pub fn default() -> Self {
         Physics {
-            gravity: 9.81 / 24.0,
+            gravity: Config::default().physics.gravity,
             gravity_direction: Vec2d::new(0.0, 1.0),
+            atmosphere: Atmosphere::new(Config::default().atmosphere, 0.0),
         }
     }
 
@@ -86,10 +179,9 @@ impl Physics {
 
         // Apply gravity and acceleration to each entity,
         // Apply resulting speed to position of entity
+        let sim_time_in_seconds = time_in_ms / num_ticks as f32 / 1000.0;
         for _ in 0..num_ticks {
             for e in entities.iter_mut() {
-                let sim_time_in_seconds = time_in_ms / 1000.0;
-
                 // update direction by applying gravity:
                 let gravity_fragment =
                     self.gravity_direction.clone() * (self.gravity * sim_time_in_seconds);
@@ -99,245 +191,818 @@ impl Physics {
                     // update direction by appliying acceleration:
                     let accel_fragment = e.acceleration.clone() * (sim_time_in_seconds);
                     e.direction = e.direction + accel_fragment;
+                    // In an atmosphere drag limits the speed to a terminal velocity
+                    let drag = self.atmosphere.drag(e.position, e.direction, sim_time_in_seconds);
+                    e.direction = e.direction + drag * sim_time_in_seconds;
                     e.position = e.position + e.direction.clone() * (sim_time_in_seconds);
+                    e.orientation += e.spin * sim_time_in_seconds;
                 }
-
-                // TBD: Check if something like a terminal velocity would be a good idea
-                // -> This would probably make the game a bit easier and also make the physics
-                // simulation more robust
             }
         }
     }
 }
 
 impl World {
-    pub fn new(window_width: u32, window_height: u32) -> Self {
+    pub fn new(window_width: u32, window_height: u32, seed: u64, config: Config, body: Body) -> Self {
+        let applied = body.apply(config);
+        let generator = body.terrain_style().generator(&applied.terrain);
+        // Rerolls maps without enough pads the lander can get to
+        let budget = FuelBudget {
+            spawn: SPAWN_POSITION,
+            gravity: applied.physics.gravity,
+            thrust: applied.lander.thrust,
+            fuel: applied.lander.starting_fuel,
+        };
+        let map = terrain::generate_checked(seed, &PadRules::default(), &budget, |seed| {
+            let mut map = PointList::generate(
+                generator.as_ref(),
+                window_width as f32,
+                (window_height as f32) / 3.0,
+                seed,
+            );
+            map.set_window_height(window_height as f32);
+            map
+        });
+        let pads = Pad::landing_areas(&map);
+        let mut w = Self::with_map(map, config, body);
+        w.pads = pads;
+        // Every seed gets its own gusts
+        w.p.atmosphere = Atmosphere::new(w.config.atmosphere, (seed % 628) as f32 / 100.0);
+        w
+    }
+
+    pub fn with_map(map: PointList, config: Config, body: Body) -> Self {
+        let config = body.apply(config);
+        let mut p = Physics::default();
+        p.gravity = config.physics.gravity;
+        p.atmosphere = Atmosphere::new(config.atmosphere, 0.0);
+        let lod = TerrainLod::new(&map);
+        let width = map.get_values().last().map_or(crate::window_width as f32, |p| p.x);
         let mut w = World {
-            p: Physics::default(),
+            p,
             entities: Vec::new(),
-            lander: None,
-            map: PointList::new(window_width as f32, (window_height as f32) / 3.0),
+            landers: Vec::new(),
+            map,
+            lod,
+            camera: Camera::new(width, crate::window_height as f32),
             hud: hud::Hud::new(),
             game_state: State::Running,
+            particles: ParticleSystem::new(),
+            show_hud: true,
+            show_bbox: true,
+            body,
+            config,
+            gravity: None,
+            pads: Vec::new(),
+            hazards: Vec::new(),
+            ghost: None,
+            touch_and_go: false,
+            touchdowns: Vec::new(),
+            highlights: Vec::new(),
         };
-        w.map.set_window_height(window_height as f32);
-        let landerId = w.create_entity();
-        w.lander = Some(Lander {
-            entity_id: landerId,
-            fuel: 20.0,
-            facing: Vec2d::new(0.0, 1.0),
-            drive_enabled: false,
-            rotation: 0.0,
-        });
+        w.hud.set_thresholds(config.hud);
+        w.hud.set_body(body.name());
+        w.add_lander(SPAWN_POSITION, Color::RGB(255, 255, 255));
         w
     }
 
+    // Builds the World of a hand-made level, the config provides all that
+    // the level leaves open
+    pub fn from_level(level: &Level, config: Config) -> Self {
+        let mut w = Self::with_map(PointList::from_points(level.points.clone()), config, level.body);
+        w.gravity = level.gravity;
+        w.set_config(config);
+        w.pads = level.pads.clone();
+        w.hazards = level.hazards.clone();
+        let lander = &mut w.landers[0];
+        if let Some(fuel) = level.fuel {
+            lander.fuel = fuel;
+        }
+        let entity = &mut w.entities[lander.entity_id];
+        entity.position = level.spawn;
+        entity.direction = level.velocity;
+        w
+    }
+
+    pub fn map(&self) -> &PointList {
+        &self.map
+    }
+
     pub fn create_entity(&mut self) -> usize {
         let mut e = Entity::default();
-        e.set_position(Vec2d::new(200.0, 300.0));
+        e.set_position(SPAWN_POSITION);
         self.entities.push(e);
         return self.entities.len() - 1;
     }
 
+    // Applies new tuning to the running level. The terrain and the starting
+    // fuel only take effect with the next level.
+    pub fn set_config(&mut self, config: Config) {
+        let mut config = self.body.apply(config);
+        if let Some(gravity) = self.gravity {
+            config.physics.gravity = gravity;
+        }
+        self.config = config;
+        self.p.gravity = config.physics.gravity;
+        self.p.atmosphere.set_config(config.atmosphere);
+        self.hud.set_thresholds(config.hud);
+    }
+
+    pub fn set_show_hud(&mut self, show: bool) {
+        self.show_hud = show;
+    }
+
+    pub fn set_show_bbox(&mut self, show: bool) {
+        self.show_bbox = show;
+    }
+
+    pub fn state(&self) -> &State {
+        &self.game_state
+    }
+
+    // Points for a successful landing of the first lander
+    pub fn score(&self) -> u32 {
+        self.score_of(0)
+    }
+
+    // Points for a successful landing, every drop of fuel saved counts and
+    // landing on a pad multiplies them
+    pub fn score_of(&self, idx: usize) -> u32 {
+        let lander = &self.landers[idx];
+        if lander.state != State::Won {
+            return 0;
+        }
+        let x = self.entities[lander.entity_id].position.x;
+        let multiplier = self.pads.iter().find(|p| p.contains(x)).map_or(1, |p| p.multiplier);
+        (lander.fuel.max(0.0) * 100.0) as u32 * multiplier
+    }
+
+    // How it went for a single lander, the World is decided once all are
+    pub fn lander_state(&self, idx: usize) -> State {
+        self.landers[idx].state
+    }
+
+    pub fn lander_count(&self) -> usize {
+        self.landers.len()
+    }
+
+    // Another craft with its own controls, colour, fuel and score, starting
+    // at rest from position. Landers fly through each other. Returns the
+    // index to control it with.
+    pub fn add_lander(&mut self, position: Vec2d, color: Color) -> usize {
+        let id = self.create_entity();
+        let entity = self.get_entity(id);
+        entity.position = position;
+        entity.orientation = PI / 2.0;
+        self.landers.push(Lander {
+            entity_id: id,
+            fuel: self.config.lander.starting_fuel,
+            facing: Vec2d::new(0.0, 1.0),
+            drive_enabled: false,
+            throttle: 0.0,
+            rotation: 0.0,
+            grounded: false,
+            rest_time: 0.0,
+            state: State::Running,
+            color,
+            pad: None,
+        });
+        self.landers.len() - 1
+    }
+
+    pub fn pose_of(&self, idx: usize) -> Pose {
+        let lander = &self.landers[idx];
+        let entity = &self.entities[lander.entity_id];
+        Pose {
+            position: entity.position,
+            orientation: entity.orientation,
+            throttle: if lander.drive_enabled { lander.throttle } else { 0.0 },
+        }
+    }
+
+    // A lander of an earlier run to race against. It is only drawn, it
+    // neither collides nor claims pads nor moves the camera.
+    pub fn set_ghost(&mut self, ghost: Option<Pose>) {
+        self.ghost = ghost;
+    }
+
+    pub fn pads(&self) -> &[Pad] {
+        &self.pads
+    }
+
+    // Game modes decide the landers themselves. Touchdowns are only
+    // reported then, crashes are still decided by the World.
+    pub fn set_touch_and_go(&mut self, touch_and_go: bool) {
+        self.touch_and_go = touch_and_go;
+    }
+
+    pub fn take_touchdowns(&mut self) -> Vec<Touchdown> {
+        std::mem::take(&mut self.touchdowns)
+    }
+
+    // Ends the flight of a lander that is still running
+    pub fn decide_lander(&mut self, idx: usize, state: State) {
+        if self.landers[idx].state == State::Running && state != State::Running {
+            self.end_lander(idx, state);
+        }
+    }
+
+    pub fn set_fuel_of(&mut self, idx: usize, fuel: f32) {
+        self.landers[idx].fuel = fuel;
+    }
+
+    // Marks parts of the surface, e.g. the pad to land on next
+    pub fn set_highlights(&mut self, highlights: Vec<(f32, f32)>) {
+        self.highlights = highlights;
+    }
+
     pub fn get_entity(&mut self, id: usize) -> &mut Entity {
         return &mut self.entities[id];
     }
 
     pub fn tick(&mut self, time_in_ms: f32, tick_resolution_in_ms: f32) {
+        // The attitude thrusters only turn a lander while it is airborne,
+        // on the ground the contact forces decide about its rotation.
+        for lander in self.landers.iter() {
+            if !lander.grounded && lander.state == State::Running {
+                self.entities[lander.entity_id].spin = lander.rotation * self.config.lander.rotation_speed;
+            }
+        }
+
         // Do physics (i.e. Gravity & Acceleration) tick
         self.p
             .tick(time_in_ms, tick_resolution_in_ms, &mut self.entities);
+        self.p.atmosphere.advance(time_in_ms / 1000.0);
+        if !self.particles.is_empty() {
+            self.particles
+                .tick(&self.p, time_in_ms, tick_resolution_in_ms, &self.map);
+        }
 
-        // Consume fuel
-        let mut lander = self.lander.as_mut().unwrap();
-        let mut disableThrust = false; 
-        if lander.drive_enabled {
-            lander.fuel -= time_in_ms / 1000.0;
-            if lander.fuel <= 0.0
-            {
-                lander.drive_enabled = false;
-                disableThrust = true;
+        for idx in 0..self.landers.len() {
+            // Consume fuel
+            let lander = &mut self.landers[idx];
+            if lander.drive_enabled {
+                lander.fuel -= lander.throttle * time_in_ms / 1000.0;
+                if lander.fuel <= 0.0
+                {
+                    lander.drive_enabled = false;
+                }
             }
-        }
 
-        let mut next_angle = lander.facing.angle() + lander.rotation * (time_in_ms / 1000.0);
-        lander.facing = Vec2d::from_angle(next_angle);
+            lander.facing = Vec2d::from_angle(self.entities[lander.entity_id].orientation);
 
-        self.thrust_toggle(disableThrust);
+            // Re-apply the throttle, so the thrust follows the new facing and
+            // stops once the fuel ran out
+            let throttle = if lander.drive_enabled { lander.throttle } else { 0.0 };
+            self.set_throttle_of(idx, throttle);
+            self.emit_engine_particles(idx, time_in_ms / 1000.0);
 
+            // Do collision detection and contact response, the lander is
+            // decided once its hull hits the ground or it came to rest
+            self.do_collision_detection(idx, time_in_ms / 1000.0);
+        }
 
-        // Do collision detection, fail if we collided with the environment
-        // or a landingpad (in pad case: if velocity was too high)
-        self.do_collision_detection();
+        self.follow_with_camera(time_in_ms / 1000.0);
     }
 
-    fn get_lander_transform(&self, lander_pos: Vec2d, lander_rot: Vec2d) -> TransformationMatrix
+    // Zooms in once every lander still flying is close to the ground
+    fn follow_with_camera(&mut self, time_in_s: f32) {
+        let flying: Vec<usize> = (0..self.landers.len())
+            .filter(|idx| self.landers[*idx].state == State::Running)
+            .collect();
+        let followed = if flying.is_empty() { (0..self.landers.len()).collect() } else { flying };
+        if followed.is_empty() {
+            return;
+        }
+        let mut position = Vec2d::default();
+        let mut altitude: f32 = 0.0;
+        for idx in followed.iter() {
+            let o = self.observe_lander(*idx);
+            position = position + o.position;
+            altitude = altitude.max(o.altitude);
+        }
+        let position = position / followed.len() as f32;
+        self.camera.follow(position, altitude, time_in_s);
+    }
+
+    fn get_lander_transform(lander_pos: Vec2d, lander_orientation: f32) -> TransformationMatrix
     {
         let scale = vecmath::TransformationMatrix::scale(graphics::LanderScale.x, graphics::LanderScale.y);
         let translate = vecmath::TransformationMatrix::translation_v(lander_pos);
-        let rotation = vecmath::TransformationMatrix::rotate(lander_rot.angle() + PI / 2.0);
+        let rotation = vecmath::TransformationMatrix::rotate(lander_orientation + PI / 2.0);
         let transform = translate * rotation * scale;
         transform
     }
 
     pub(crate) fn render(&mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>) {
-        match self.game_state {
-            State::Won => renderWonText(canvas),
-            State::Lost => renderGameOver(canvas),
-            State::Running => (),
-        }
-        draw::draw_lines(
-            canvas,
-            &self.map.get_values(),
-            Color::RGB(255, 255, 255),
-            false,
-        )
-        .unwrap();
-        self.renderHud(canvas);
-
-        //draw the lander:
-        let id;
-        let thrust_enabled;
-        let lander_rot;
-        let fuel;
-        {
-            // This scope makes sure, that we only keep the lander
-            // borrowed as long as necessary
-            let lander = self.lander.as_ref().unwrap();
-            id = lander.entity_id;
-            lander_rot = lander.facing;
-            thrust_enabled = lander.drive_enabled;
-            fuel = lander.fuel;
+        self.body.render_sky(canvas, &self.config).unwrap();
+        // Only what is in view, as coarse as the zoom allows
+        let camera = self.camera.transform();
+        let (left, right) = self.camera.visible_x();
+        let level = self.lod.level_for(self.camera.zoom());
+        let terrain = camera.transform_many(&self.lod.between(level, left, right).to_vec());
+        draw::draw_lines(canvas, &terrain, self.body.palette().terrain, false).unwrap();
+        self.render_pads_and_hazards(canvas, &camera);
+        if self.show_hud {
+            self.renderHud(canvas);
         }
-        let entity = self.get_entity(id);
-        let lander_pos = entity.position;
+        self.particles.render(canvas, &camera);
 
-        let transform = self.get_lander_transform(lander_pos, lander_rot);
-        let items = [
+        if let Some(ghost) = self.ghost {
+            canvas.set_blend_mode(BlendMode::Blend);
+            Self::render_lander(canvas, &camera, &ghost, Color::RGBA(255, 255, 255, GHOST_ALPHA), false);
+            canvas.set_blend_mode(BlendMode::None);
+        }
+        // A crashed lander only exists as debris
+        for (idx, lander) in self.landers.iter().enumerate().filter(|(_, l)| l.state != State::Lost) {
+            Self::render_lander(canvas, &camera, &self.pose_of(idx), lander.color, self.show_bbox);
+        }
+    }
+
+    fn render_lander(
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        camera: &TransformationMatrix,
+        pose: &Pose,
+        color: Color,
+        show_bbox: bool,
+    ) {
+        let transform = *camera * Self::get_lander_transform(pose.position, pose.orientation);
+        let mut items = vec![
             &graphics::LanderTop,
             &graphics::LanderMiddle,
             &graphics::LanderBottom,
             &graphics::LanderDrive,
-            &graphics::BBox
         ];
+        if show_bbox {
+            items.push(&graphics::BBox);
+        }
         for lander_part in items.iter() {
             let geometry = transform.transform_many(&lander_part.to_vec());
-            draw::draw_lines(canvas, &geometry, Color::RGB(255, 255, 255), true).unwrap();
+            draw::draw_lines(canvas, &geometry, color, true).unwrap();
         }
 
-        if thrust_enabled {
+        // The flame core grows with the throttle, the plume itself is
+        // made of exhaust particles
+        if pose.throttle > 0.0 {
             let geometry;
-            if ((fuel * 10.0) as i32) % 2 == 0 {
+            if pose.throttle > 0.5 {
                 geometry = transform.transform_many(&graphics::FlameA.to_vec());
             } else {
                 geometry = transform.transform_many(&graphics::FlameB.to_vec());
             }
-            draw::draw_lines(canvas, &geometry, Color::RGB(255, 255, 255), true).unwrap();
+            draw::draw_lines(canvas, &geometry, color, true).unwrap();
+        }
+    }
+
+    // Pads with a bonus are marked with their multiplier, hazards are red
+    fn render_pads_and_hazards(
+        &self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        camera: &TransformationMatrix,
+    ) {
+        for pad in self.pads.iter().filter(|p| p.multiplier > 1) {
+            if let Some((height, _)) = self.map.surface_at(pad.left) {
+                let center = camera.transform(&Vec2d::new(pad.center(), height));
+                let center = Point::new(center.x as i32, center.y as i32 + 4);
+                draw::draw_text_centered(canvas, &format!("x{}", pad.multiplier), 12, center, Color::RGB(0, 255, 0))
+                    .unwrap();
+            }
+        }
+        for (from, to) in self.highlights.iter() {
+            if let (Some((left, _)), Some((right, _))) = (self.map.surface_at(*from), self.map.surface_at(*to)) {
+                for lift in [1.0, 2.0] {
+                    let line = vec![Vec2d::new(*from, left - lift), Vec2d::new(*to, right - lift)];
+                    draw::draw_lines(canvas, &camera.transform_many(&line), Color::RGB(255, 255, 0), false).unwrap();
+                }
+            }
+        }
+        for hazard in self.hazards.iter() {
+            let zoom = self.camera.zoom();
+            let (width, height) = ((hazard.size.x * zoom) as u32, (hazard.size.y * zoom) as u32);
+            let origin = camera.transform(&hazard.position);
+            draw::draw_rect::<u32>(canvas, &origin, width, height, Color::RGB(255, 0, 0), false).unwrap();
         }
     }
 
     pub(crate) fn thrust_toggle(&mut self, enable: bool) {
-        if self.game_state != State::Running {
+        self.set_throttle(if enable { 1.0 } else { 0.0 });
+    }
+
+    pub(crate) fn set_throttle(&mut self, throttle: f32) {
+        self.set_throttle_of(0, throttle);
+    }
+
+    pub(crate) fn set_throttle_of(&mut self, idx: usize, throttle: f32) {
+        if self.landers[idx].state != State::Running {
             return;
         }
         let id;
         let thrust_dir;
         let hasFuel;
+        let throttle = throttle.clamp(0.0, 1.0);
         {
             // This scope makes sure, that we only keep the lander
             // borrowed as long as necessary
-            let lander = self.lander.as_mut().unwrap();
+            let lander = &mut self.landers[idx];
             thrust_dir = lander.facing;       
             id = lander.entity_id;
             hasFuel = lander.fuel > 0.0;
-            lander.drive_enabled = hasFuel && enable;
+            lander.drive_enabled = hasFuel && throttle > 0.0;
+            lander.throttle = if lander.drive_enabled { throttle } else { 0.0 };
         }
+        let thrust = self.config.lander.thrust;
         let entity = self.get_entity(id);
-        if enable && hasFuel{
-            entity.set_acceleration(thrust_dir * -5.0);
+        if throttle > 0.0 && hasFuel{
+            entity.set_acceleration(thrust_dir * (-thrust * throttle));
         } else {
             entity.set_acceleration(Vec2d::default());
         }
     }
 
-    pub(crate) fn rotation_left_toggle(&mut self, enable: bool) {
-        if self.game_state != State::Running {
+    // Feeds the exhaust plume and, if the plume reaches the terrain, kicks
+    // up dust along the slope. The closer the ground, the more dust.
+    fn emit_engine_particles(&mut self, idx: usize, time_in_s: f32) {
+        let lander = &self.landers[idx];
+        if lander.state != State::Running {
             return;
         }
-        let lander = self.lander.as_mut().unwrap();
-        if enable == true {
-            lander.rotation = -1.0;
-        } else {
-            lander.rotation = 0.0;
+        if !lander.drive_enabled {
+            return;
+        }
+        let throttle = lander.throttle;
+        let exhaust_dir = lander.facing;
+        let entity = &self.entities[lander.entity_id];
+        let velocity = entity.direction;
+        let transform = Self::get_lander_transform(entity.position, entity.orientation);
+        let nozzle = transform.transform(&graphics::LanderNozzle);
+
+        self.particles
+            .emit_exhaust(nozzle, exhaust_dir, velocity, throttle, time_in_s);
+
+        let plume_end = nozzle + exhaust_dir * PLUME_LENGTH;
+        let terrain = self.lod.finest_near(nozzle.x.min(plume_end.x), nozzle.x.max(plume_end.x));
+        if let Some((hit, (a, b))) = collision::raycast(nozzle, plume_end, terrain) {
+            let strength = throttle * (1.0 - (hit - nozzle).len() / PLUME_LENGTH);
+            self.particles
+                .emit_dust(hit, (b - a).normalized(), exhaust_dir, strength, time_in_s);
         }
     }
 
-    pub(crate) fn rotation_right_toggle(&mut self, enable: bool) {
-        if self.game_state != State::Running {
-            return;
+    // What a Controller of the first lander gets to see of the level
+    pub fn observe(&self) -> Observation<'_> {
+        self.observe_lander(0)
+    }
+
+    pub fn observe_lander(&self, idx: usize) -> Observation<'_> {
+        let lander = &self.landers[idx];
+        let entity = &self.entities[lander.entity_id];
+        let transform = Self::get_lander_transform(entity.position, entity.orientation);
+        let altitude = [graphics::LeftLeg[1], graphics::RightLeg[1]]
+            .iter()
+            .map(|foot| transform.transform(foot))
+            .filter_map(|foot| self.map.surface_at(foot.x).map(|(height, _)| height - foot.y))
+            .fold(f32::MAX, f32::min);
+        Observation {
+            position: entity.position,
+            velocity: entity.direction,
+            orientation: entity.orientation,
+            facing: lander.facing,
+            fuel: lander.fuel,
+            altitude,
+            grounded: lander.grounded,
+            gravity: self.p.gravity,
+            thrust: self.config.lander.thrust,
+            rotation_speed: self.config.lander.rotation_speed,
+            pads: &self.pads,
+            map: &self.map,
         }
-        let lander = self.lander.as_mut().unwrap();
-        if enable == true {
-            lander.rotation = 1.0;
-        } else {
-            lander.rotation = 0.0;
+    }
+
+    pub fn controls(&self) -> Controls {
+        self.controls_of(0)
+    }
+
+    pub fn controls_of(&self, idx: usize) -> Controls {
+        let lander = &self.landers[idx];
+        Controls {
+            throttle: lander.throttle,
+            rotation: lander.rotation,
+        }
+    }
+
+    pub fn apply_controls(&mut self, controls: Controls) {
+        self.apply_controls_to(0, controls);
+    }
+
+    pub fn apply_controls_to(&mut self, idx: usize, controls: Controls) {
+        self.set_throttle_of(idx, controls.throttle);
+        self.set_rotation_of(idx, controls.rotation);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            entities: self.entities.clone(),
+            landers: self.landers.clone(),
+            game_state: self.game_state,
+            atmosphere: self.p.atmosphere,
+            particles: self.particles.clone(),
+            camera: self.camera,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.entities = snapshot.entities.clone();
+        self.landers = snapshot.landers.clone();
+        self.game_state = snapshot.game_state;
+        self.p.atmosphere = snapshot.atmosphere;
+        self.particles = snapshot.particles.clone();
+        self.camera = snapshot.camera;
+    }
+
+    // FNV-1a over the exact bits of everything the landers' flight depends
+    // on. Two Worlds that went through the same ticks with the same input
+    // hash the same, the slightest difference shows. Particles only look.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut mix = |bits: u32| {
+            for byte in bits.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        mix(self.game_state as u32);
+        for e in self.entities.iter() {
+            for value in [e.position.x, e.position.y, e.direction.x, e.direction.y, e.orientation, e.spin] {
+                mix(value.to_bits());
+            }
+            mix(e.update as u32);
+        }
+        for l in self.landers.iter() {
+            for value in [l.fuel, l.throttle, l.rotation, l.rest_time] {
+                mix(value.to_bits());
+            }
+            mix(l.state as u32);
+            mix(l.pad.map_or(u32::MAX, |pad| pad as u32));
         }
+        hash
+    }
+
+    pub(crate) fn set_rotation(&mut self, rotation: f32) {
+        self.set_rotation_of(0, rotation);
+    }
+
+    pub(crate) fn set_rotation_of(&mut self, idx: usize, rotation: f32) {
+        let lander = &mut self.landers[idx];
+        if lander.state != State::Running {
+            return;
+        }
+        lander.rotation = rotation.clamp(-1.0, 1.0);
+    }
+
+    pub(crate) fn rotation_left_toggle(&mut self, enable: bool) {
+        self.set_rotation(if enable { -1.0 } else { 0.0 });
     }
 
-    fn do_collision_detection(&mut self)
+    pub(crate) fn rotation_right_toggle(&mut self, enable: bool) {
+        self.set_rotation(if enable { 1.0 } else { 0.0 });
+    }
+
+    fn do_collision_detection(&mut self, idx: usize, time_in_s: f32)
     {
-        if let Some(lander) = self.lander.as_ref() {
-            let id = lander.entity_id;
-            let position;
-            let direction;
-            {
-                let entity = self.get_entity(id);
-                position = entity.position;
-                direction = entity.direction;
+        let lander = &self.landers[idx];
+        if lander.state != State::Running {
+            return;
+        }
+        let id = lander.entity_id;
+        let position;
+        let orientation;
+        {
+            let entity = self.get_entity(id);
+            position = entity.position;
+            orientation = entity.orientation;
+        }
+
+        let transform = Self::get_lander_transform(position, orientation);
+        let hull = transform.transform_many(&graphics::LanderHull.to_vec());
+
+        let in_hazard = hull.iter().any(|p| self.hazards.iter().any(|h| h.contains(*p)));
+        let left = hull.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        let right = hull.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        if in_hazard || collision::detect_collision(hull, self.lod.finest_near(left, right)).is_some() {
+            self.end_lander(idx, State::Lost);
+            return;
+        }
+
+        let mut contacts = 0;
+        let mut impact: f32 = 0.0;
+        for foot in [graphics::LeftLeg[1], graphics::RightLeg[1]] {
+            let entity = &mut self.entities[id];
+            if let Some(foot_impact) = resolve_foot_contact(entity, foot, &self.map) {
+                contacts += 1;
+                impact = impact.max(foot_impact);
             }
+        }
+
+        if impact > self.config.lander.crash_speed {
+            self.end_lander(idx, State::Lost);
+            return;
+        }
 
-            let transform = self.get_lander_transform(position, direction);
-            let bbox = transform.transform_many(&graphics::BBox.to_vec());
-
-            if let Some(collision) = collision::detect_collision(
-                bbox, self.map.get_values())
-            {
-                    let entity = self.get_entity(id);
-                    entity.set_update(false);
-                    let angle = (collision[0].0 - collision[0].1).angle();
-                    if angle > PI / 16.0 && angle < PI - (PI /16.0){
-                        self.game_state = State::Lost
-                    }else {
-                        //TODO: check landing speed
-                        self.game_state = State::Won
-                    }
+        let entity = &self.entities[id];
+        let at_rest = contacts == 2
+            && entity.direction.len() < REST_SPEED
+            && entity.spin.abs() < REST_SPIN;
+        let x = entity.position.x;
+        let lander = &mut self.landers[idx];
+        lander.grounded = contacts > 0;
+        let rested = lander.rest_time;
+        lander.rest_time = if at_rest { lander.rest_time + time_in_s } else { 0.0 };
+
+        if lander.rest_time >= REST_TIME {
+            let slope = match self.map.surface_at(x) {
+                Some((_, normal)) => (-normal.y).acos(),
+                None => PI,
+            };
+            if slope > self.config.lander.safe_slope {
+                self.end_lander(idx, State::Lost);
+                return;
+            }
+            let pad = self.pads.iter().position(|p| p.contains(x));
+            if self.touch_and_go {
+                // Once per touchdown, the lander has to take off for the next
+                if rested < REST_TIME {
+                    self.touchdowns.push(Touchdown { lander: idx, x, pad });
+                }
+                return;
+            }
+            // Pads are first come, first served. On a pad somebody else
+            // claimed a lander has to take off again while it still can.
+            let taken = pad.is_some() && self.landers.iter().any(|l| l.pad == pad);
+            if !taken {
+                self.landers[idx].pad = pad;
+                self.end_lander(idx, State::Won);
+            } else if self.landers[idx].fuel <= 0.0 {
+                self.end_lander(idx, State::Lost);
             }
         }
     }
 
+    // Decides a single lander, the World once every lander is decided
+    fn end_lander(&mut self, idx: usize, state: State) {
+        let lander = &mut self.landers[idx];
+        lander.state = state;
+        let entity = &mut self.entities[lander.entity_id];
+        entity.set_update(false);
+        if state == State::Lost {
+            let position = entity.position;
+            let velocity = entity.direction;
+            let transform = Self::get_lander_transform(position, entity.orientation);
+            let parts: Vec<Vec<Vec2d>> = [
+                &graphics::LanderTop,
+                &graphics::LanderMiddle,
+                &graphics::LanderBottom,
+                &graphics::LanderDrive,
+            ]
+            .iter()
+            .map(|part| transform.transform_many(&part.to_vec()))
+            .collect();
+            self.particles.explode(&parts, position, velocity);
+        }
+        if self.landers.iter().all(|l| l.state != State::Running) {
+            let won = self.landers.iter().any(|l| l.state == State::Won);
+            self.game_state = if won { State::Won } else { State::Lost };
+        }
+    }
+
     fn renderHud(&mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>) {
-        if let Some(lander) = self.lander.as_ref() {
+        if let Some(lander) = self.landers.first() {
             let fuel = lander.fuel;
-            let id = lander.entity_id;
-            let entity = self.get_entity(id);
+            let entity = &self.entities[lander.entity_id];
             let position = entity.position;
             let direction = entity.direction;
             self.hud.update(position, direction, fuel, 0);
+            let wind = self.p.atmosphere.wind_at(position);
+            self.hud.set_wind(self.p.atmosphere.is_present().then_some(wind.x));
         }
         self.hud.render(canvas);
+
+        // Every further lander gets a line in its colour on the right
+        for (idx, lander) in self.landers.iter().enumerate().skip(1) {
+            let status = match lander.state {
+                State::Running => format!("Fuel: {:.1}", lander.fuel),
+                State::Won => format!("Landed: {}", self.score_of(idx)),
+                State::Lost => String::from("Crashed"),
+            };
+            let line = format!("P{} {}", idx + 1, status);
+            let origin = Point::new(crate::window_width as i32 - 150, 10 * (idx as i32 - 1));
+            draw::draw_text(canvas, &line, 10, origin, lander.color).unwrap();
+        }
+    }
+}
+
+// Pushes a foot that sunk into the terrain back out and applies the contact
+// impulse (restitution and friction) at the foot, which also makes the
+// lander turn around the foot if its center of mass is not supported.
+// Returns the speed the foot hit the ground with, None if it did not touch.
+fn resolve_foot_contact(entity: &mut Entity, foot: Vec2d, map: &PointList) -> Option<f32> {
+    let transform = World::get_lander_transform(entity.position, entity.orientation);
+    let foot = transform.transform(&foot);
+    let (height, normal) = map.surface_at(foot.x)?;
+
+    // y grows downwards, so a foot below the surface has a bigger y
+    let depth = (foot.y - height) * -normal.y;
+    if depth < -CONTACT_SLOP {
+        return None;
+    }
+    if depth > 0.0 {
+        entity.position = entity.position + normal * depth;
     }
+
+    let r = foot - entity.position;
+    let tangent = Vec2d::new(-normal.y, normal.x);
+    let foot_velocity = entity.direction + Vec2d::new(-entity.spin * r.y, entity.spin * r.x);
+    let normal_speed = dot(foot_velocity, normal);
+    if normal_speed >= 0.0 {
+        return Some(0.0);
+    }
+
+    let impact = -normal_speed;
+    let restitution = if impact > BOUNCE_SPEED { RESTITUTION } else { 0.0 };
+    let r_cross_n = cross(r, normal);
+    let j = -(1.0 + restitution) * normal_speed / (1.0 + r_cross_n * r_cross_n / LANDER_INERTIA);
+    entity.direction = entity.direction + normal * j;
+    entity.spin += r_cross_n * j / LANDER_INERTIA;
+
+    // Coulomb friction, the lander slides once the slope is too steep
+    let foot_velocity = entity.direction + Vec2d::new(-entity.spin * r.y, entity.spin * r.x);
+    let r_cross_t = cross(r, tangent);
+    let jt = (-dot(foot_velocity, tangent) / (1.0 + r_cross_t * r_cross_t / LANDER_INERTIA))
+        .clamp(-FRICTION * j, FRICTION * j);
+    entity.direction = entity.direction + tangent * jt;
+    entity.spin += r_cross_t * jt / LANDER_INERTIA;
+
+    Some(impact)
+}
+
+fn dot(a: Vec2d, b: Vec2d) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn cross(a: Vec2d, b: Vec2d) -> f32 {
+    a.x * b.y - a.y * b.x
 }
 
+#[cfg(test)]
 mod tests {
     use crate::{simulation, vecmath::Vec2d};
 
-    use super::{Entity, Physics};
+    use std::f32::consts::PI;
+
+    use sdl2::pixels::Color;
+
+    use crate::graphics;
+    use crate::map::PointList;
+
+    use super::{Controls, Entity, Physics, State, World};
+    use crate::config::{Config, Difficulty};
+    use crate::level::{Hazard, Level, Pad};
+    use crate::planet::Body;
+
+    // Places the lander at x = 400 so that the foot closest to the ground
+    // hovers just above the surface.
+    fn world_with_lander(ground: Vec<Vec2d>, orientation: f32, velocity: Vec2d) -> World {
+        let mut w = World::with_map(PointList::from_points(ground), Config::default(), Body::Moon);
+        let transform = World::get_lander_transform(Vec2d::new(400.0, 0.0), orientation);
+        let y = [graphics::LeftLeg[1], graphics::RightLeg[1]]
+            .iter()
+            .map(|f| transform.transform(f))
+            .map(|f| w.map.surface_at(f.x).unwrap().0 - f.y)
+            .fold(f32::MAX, f32::min);
+        let e = w.get_entity(0);
+        e.position = Vec2d::new(400.0, y - 0.1);
+        e.orientation = orientation;
+        e.direction = velocity;
+        w
+    }
+
+    fn flat_ground() -> Vec<Vec2d> {
+        vec![Vec2d::new(0.0, 500.0), Vec2d::new(800.0, 500.0)]
+    }
+
+    fn run(w: &mut World, ticks: usize) {
+        for _ in 0..ticks {
+            w.tick(50.0, 10.0);
+            if w.game_state != State::Running {
+                break;
+            }
+        }
+    }
 
     #[test]
     fn can_apply_gravity() {
         let w = Physics {
             gravity: 1.0,
             gravity_direction: Vec2d::new(0.0, -1.0),
+            ..Physics::default()
         };
 
         let mut e = Entity::default();
@@ -353,6 +1018,7 @@ mod tests {
         let w = Physics {
             gravity: 1.0,
             gravity_direction: Vec2d::default(),
+            ..Physics::default()
         };
 
         let mut e = Entity::default();
@@ -363,4 +1029,276 @@ mod tests {
         assert_eq!(v[0].position.x, 1.0);
         assert_eq!(v[0].direction.x, 1.0);
     }
+
+    #[test]
+    fn gentle_landing_on_flat_ground_wins() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 5.0));
+        run(&mut w, 100);
+        assert!(w.game_state == State::Won);
+    }
+
+    #[test]
+    fn hard_landing_crashes() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 50.0));
+        run(&mut w, 100);
+        assert!(w.game_state == State::Lost);
+    }
+
+    #[test]
+    fn slightly_hard_landing_bounces() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 15.0));
+        w.tick(50.0, 10.0);
+        assert!(w.game_state == State::Running);
+        assert!(w.entities[0].direction.y < 0.0);
+    }
+
+    #[test]
+    fn touching_a_slope_slides_downhill() {
+        // rises to the right, so downhill is to the left
+        let ground = vec![Vec2d::new(0.0, 700.0), Vec2d::new(800.0, 300.0)];
+        let normal = PointList::from_points(ground.clone()).surface_at(400.0).unwrap().1;
+        let orientation = (-normal.y).atan2(-normal.x);
+        let mut w = world_with_lander(ground, orientation, Vec2d::new(0.0, 2.5));
+        run(&mut w, 60);
+        assert!(w.entities[0].position.x < 395.0);
+    }
+
+    #[test]
+    fn slightly_tilted_lander_rights_itself() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0 + 0.3, Vec2d::default());
+        run(&mut w, 400);
+        assert!(w.game_state == State::Won);
+        assert!((w.entities[0].orientation - PI / 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn off_balance_lander_tips_over() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0 + 1.0, Vec2d::default());
+        run(&mut w, 400);
+        assert!(w.game_state == State::Lost);
+    }
+
+    #[test]
+    fn thrust_stays_on_until_released() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::default());
+        w.get_entity(0).position = Vec2d::new(400.0, 400.0);
+        w.thrust_toggle(true);
+        run(&mut w, 3);
+        assert!(w.landers[0].drive_enabled);
+        assert!(w.entities[0].direction.y < 0.0);
+        assert!(!w.particles.is_empty());
+
+        w.thrust_toggle(false);
+        w.tick(50.0, 10.0);
+        assert!(!w.landers[0].drive_enabled);
+    }
+
+    #[test]
+    fn throttle_scales_fuel_consumption() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::default());
+        w.get_entity(0).position = Vec2d::new(400.0, 100.0);
+        w.set_throttle(0.5);
+        w.tick(1000.0, 10.0);
+        assert_eq!(w.landers[0].fuel, 19.5);
+    }
+
+    #[test]
+    fn difficulty_changes_fuel_and_gravity() {
+        let hard = Difficulty::Hard.apply(Config::default());
+        let w = World::with_map(PointList::from_points(flat_ground()), hard, Body::Moon);
+        assert_eq!(w.landers[0].fuel, hard.lander.starting_fuel);
+        assert_eq!(w.p.gravity, hard.physics.gravity);
+    }
+
+    #[test]
+    fn config_changes_apply_live() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::default());
+        let mut config = Config::default();
+        config.physics.gravity = 0.0;
+        config.lander.thrust = 10.0;
+        w.set_config(config);
+        w.set_throttle(1.0);
+        w.tick(50.0, 10.0);
+        let id = w.landers[0].entity_id;
+        assert!((w.entities[id].acceleration.y + 10.0).abs() < 1e-4);
+        assert_eq!(w.p.gravity, 0.0);
+    }
+
+    #[test]
+    fn atmosphere_limits_the_fall_speed() {
+        let mut config = Config::default();
+        config.atmosphere.density = 1.0;
+        let mut vacuum = World::with_map(PointList::from_points(flat_ground()), Config::default(), Body::Moon);
+        let mut air = World::with_map(PointList::from_points(flat_ground()), config, Body::Moon);
+        for w in [&mut vacuum, &mut air] {
+            w.get_entity(0).position = Vec2d::new(400.0, 400.0);
+            w.get_entity(0).direction = Vec2d::new(0.0, 25.0);
+            w.tick(50.0, 10.0);
+        }
+        assert!(air.entities[0].direction.y < vacuum.entities[0].direction.y);
+    }
+
+    #[test]
+    fn wind_pushes_the_lander_sideways() {
+        let mut config = Config::default();
+        config.atmosphere.density = 1.0;
+        config.atmosphere.wind_speed = 3.0;
+        config.atmosphere.gustiness = 0.0;
+        let mut w = World::with_map(PointList::from_points(flat_ground()), config, Body::Moon);
+        w.get_entity(0).position = Vec2d::new(400.0, 400.0);
+        for _ in 0..5 {
+            w.tick(50.0, 10.0);
+        }
+        assert!(w.entities[0].direction.x > 0.0);
+    }
+
+    #[test]
+    fn levels_set_up_the_world() {
+        let mut level = Level::new(Body::Mars, flat_ground());
+        level.gravity = Some(0.25);
+        level.fuel = Some(3.0);
+        level.spawn = Vec2d::new(100.0, 50.0);
+        level.velocity = Vec2d::new(2.0, 0.0);
+        let w = World::from_level(&level, Config::default());
+        assert_eq!(w.p.gravity, 0.25);
+        assert_eq!(w.landers[0].fuel, 3.0);
+        assert_eq!(w.entities[0].position, level.spawn);
+        assert_eq!(w.entities[0].direction, level.velocity);
+        assert!(w.p.atmosphere.is_present());
+    }
+
+    #[test]
+    fn pads_multiply_the_score() {
+        let mut plain = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 5.0));
+        let mut pad = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 5.0));
+        pad.pads = vec![Pad {
+            left: 350.0,
+            right: 450.0,
+            multiplier: 3,
+        }];
+        run(&mut plain, 100);
+        run(&mut pad, 100);
+        assert!(plain.score() > 0);
+        assert_eq!(pad.score(), plain.score() * 3);
+    }
+
+    #[test]
+    fn hazards_destroy_the_lander() {
+        let mut level = Level::new(Body::Moon, flat_ground());
+        level.hazards = vec![Hazard {
+            position: Vec2d::new(150.0, 250.0),
+            size: Vec2d::new(100.0, 100.0),
+        }];
+        let mut w = World::from_level(&level, Config::default());
+        w.tick(50.0, 10.0);
+        assert!(w.game_state == State::Lost);
+    }
+
+    #[test]
+    fn controls_round_trip() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::default());
+        let controls = Controls {
+            throttle: 0.25,
+            rotation: -1.0,
+        };
+        w.apply_controls(controls);
+        assert_eq!(w.controls(), controls);
+    }
+
+    #[test]
+    fn crash_breaks_lander_into_debris() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 50.0));
+        assert!(w.particles.is_empty());
+        run(&mut w, 100);
+        assert!(w.game_state == State::Lost);
+        assert!(!w.particles.is_empty());
+    }
+
+    // A second lander standing at x, a little above the first one
+    fn add_lander_at(w: &mut World, x: f32, lift: f32) -> usize {
+        let y = w.entities[0].position.y - lift;
+        w.add_lander(Vec2d::new(x, y), Color::RGB(0, 200, 255))
+    }
+
+    #[test]
+    fn landers_have_their_own_controls_and_fuel() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::default());
+        w.get_entity(0).position = Vec2d::new(200.0, 300.0);
+        let second = add_lander_at(&mut w, 600.0, 0.0);
+        w.get_entity(1).position = Vec2d::new(600.0, 300.0);
+        w.apply_controls_to(
+            second,
+            Controls {
+                throttle: 1.0,
+                rotation: 0.0,
+            },
+        );
+        run(&mut w, 10);
+        assert!(w.entities[0].direction.y > 0.0);
+        assert!(w.entities[1].direction.y < 0.0);
+        assert!(w.landers[1].fuel < w.landers[0].fuel);
+        assert_eq!(w.controls_of(second).throttle, 1.0);
+        assert_eq!(w.controls().throttle, 0.0);
+    }
+
+    #[test]
+    fn world_is_decided_once_every_lander_is() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 50.0));
+        add_lander_at(&mut w, 100.0, 200.0);
+        for _ in 0..10 {
+            w.tick(50.0, 10.0);
+        }
+        assert_eq!(w.lander_state(0), State::Lost);
+        assert_eq!(w.lander_state(1), State::Running);
+        assert!(w.game_state == State::Running);
+    }
+
+    #[test]
+    fn pads_are_first_come_first_served() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 5.0));
+        w.pads = vec![Pad {
+            left: 350.0,
+            right: 450.0,
+            multiplier: 2,
+        }];
+        let second = add_lander_at(&mut w, 430.0, 2.0);
+        for _ in 0..100 {
+            w.tick(50.0, 10.0);
+        }
+        assert_eq!(w.lander_state(0), State::Won);
+        assert!(w.score_of(0) > 0);
+        // Standing on the taken pad decides nothing while there is fuel
+        assert_eq!(w.lander_state(second), State::Running);
+        assert!(w.game_state == State::Running);
+
+        w.landers[second].fuel = 0.0;
+        w.tick(50.0, 10.0);
+        assert_eq!(w.lander_state(second), State::Lost);
+        assert!(w.game_state == State::Won);
+        assert_eq!(w.score(), w.score_of(0));
+    }
+
+    #[test]
+    fn touch_and_go_reports_touchdowns_once() {
+        let mut w = world_with_lander(flat_ground(), PI / 2.0, Vec2d::new(0.0, 5.0));
+        w.pads = vec![Pad {
+            left: 350.0,
+            right: 450.0,
+            multiplier: 1,
+        }];
+        w.set_touch_and_go(true);
+        let mut touchdowns = Vec::new();
+        for _ in 0..100 {
+            w.tick(50.0, 10.0);
+            touchdowns.extend(w.take_touchdowns());
+        }
+        assert_eq!(touchdowns.len(), 1);
+        assert_eq!(touchdowns[0].pad, Some(0));
+        assert_eq!(w.lander_state(0), State::Running);
+
+        w.decide_lander(0, State::Won);
+        assert!(w.game_state == State::Won);
+        w.decide_lander(0, State::Lost);
+        assert_eq!(w.lander_state(0), State::Won);
+    }
 }
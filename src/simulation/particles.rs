@@ -0,0 +1,299 @@
+use std::f32::consts::PI;
+
+use rand::prelude::*;
+use sdl2::pixels::Color;
+
+use crate::draw;
+use crate::map::PointList;
+use crate::vecmath::{TransformationMatrix, Vec2d};
+
+use super::{Entity, Physics};
+
+const PIECE_RESTITUTION: f32 = 0.3;
+const PIECE_FRICTION: f32 = 0.7;
+// Particles per second at full throttle / full dust strength
+const EXHAUST_RATE: f32 = 60.0;
+const DUST_RATE: f32 = 80.0;
+
+// A single line that flies around until it fades out. Debris pieces are
+// lines of the lander, sparks are short streaks.
+#[derive(Clone)]
+pub struct Piece {
+    half_extent: Vec2d, // from the center to one end of the line, unrotated
+    color: Color,
+    age: f32,      // in seconds
+    lifetime: f32, // in seconds
+}
+
+// Owns its own entities, so that spawning and expiring pieces never
+// touches the entity ids handed out by the World.
+#[derive(Clone)]
+pub struct ParticleSystem {
+    entities: Vec<Entity>,
+    pieces: Vec<Piece>,
+    // Fractional particles carried over to the next tick, so low rates
+    // still emit something
+    exhaust_budget: f32,
+    dust_budget: f32,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        ParticleSystem {
+            entities: Vec::new(),
+            pieces: Vec::new(),
+            exhaust_budget: 0.0,
+            dust_budget: 0.0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn spawn(&mut self, a: Vec2d, b: Vec2d, velocity: Vec2d, spin: f32, lifetime: f32, color: Color) {
+        let mut e = Entity::default();
+        e.position = (a + b) / 2.0;
+        e.direction = velocity;
+        e.spin = spin;
+        self.entities.push(e);
+        self.pieces.push(Piece {
+            half_extent: (b - a) / 2.0,
+            color,
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    // Breaks the given closed polylines into their single lines and sends
+    // them flying away from center, together with a shower of sparks.
+    pub fn explode(&mut self, parts: &[Vec<Vec2d>], center: Vec2d, velocity: Vec2d) {
+        let mut rng = rand::thread_rng();
+        for part in parts.iter() {
+            for idx in 0..part.len() {
+                let a = part[idx];
+                let b = part[(idx + 1) % part.len()];
+                let outwards = ((a + b) / 2.0 - center).normalized();
+                let piece_velocity = velocity * 0.5
+                    + outwards * rng.gen_range(20.0..60.0)
+                    + Vec2d::from_angle(rng.gen_range(0.0..2.0 * PI)) * rng.gen_range(0.0..15.0);
+                self.spawn(
+                    a,
+                    b,
+                    piece_velocity,
+                    rng.gen_range(-15.0..15.0),
+                    rng.gen_range(4.0..8.0),
+                    Color::RGB(255, 255, 255),
+                );
+            }
+        }
+
+        for _ in 0..24 {
+            let dir = Vec2d::from_angle(rng.gen_range(0.0..2.0 * PI));
+            self.spawn(
+                center,
+                center + dir * 2.0,
+                velocity * 0.5 + dir * rng.gen_range(50.0..125.0),
+                0.0,
+                rng.gen_range(0.5..1.5),
+                Color::RGB(255, 200, 64),
+            );
+        }
+    }
+
+    // Sprays hot exhaust out of the nozzle into exhaust_dir, i.e. opposite
+    // to the thrust. More throttle means more and faster particles.
+    pub fn emit_exhaust(&mut self, nozzle: Vec2d, exhaust_dir: Vec2d, velocity: Vec2d, throttle: f32, time_in_s: f32) {
+        let mut rng = rand::thread_rng();
+        self.exhaust_budget += EXHAUST_RATE * throttle * time_in_s;
+        while self.exhaust_budget >= 1.0 {
+            self.exhaust_budget -= 1.0;
+            let dir = TransformationMatrix::rotate(rng.gen_range(-0.25..0.25)).transform(&exhaust_dir);
+            let speed = rng.gen_range(75.0..125.0) * (0.5 + 0.5 * throttle);
+            let start = nozzle + dir * rng.gen_range(0.0..3.0);
+            self.spawn(
+                start,
+                start + dir * 3.0,
+                velocity + dir * speed,
+                0.0,
+                rng.gen_range(0.3..0.6),
+                Color::RGB(255, 160, 32),
+            );
+        }
+    }
+
+    // Kicks up dust where the plume hits the ground. The dust flies along
+    // the slope, mostly to the side the exhaust is blowing to.
+    pub fn emit_dust(&mut self, hit: Vec2d, slope: Vec2d, exhaust_dir: Vec2d, strength: f32, time_in_s: f32) {
+        let mut rng = rand::thread_rng();
+        let normal = Vec2d::new(slope.y, -slope.x);
+        let bias = slope.x * exhaust_dir.x + slope.y * exhaust_dir.y;
+        self.dust_budget += DUST_RATE * strength * time_in_s;
+        while self.dust_budget >= 1.0 {
+            self.dust_budget -= 1.0;
+            let side = if rng.gen::<f32>() < 0.5 + 0.4 * bias { 1.0 } else { -1.0 };
+            let velocity = slope * (side * rng.gen_range(25.0..75.0) * (0.5 + strength))
+                + normal * rng.gen_range(2.5..15.0);
+            let start = hit + normal;
+            self.spawn(
+                start,
+                start + slope * (side * 1.5),
+                velocity,
+                0.0,
+                rng.gen_range(0.5..1.2),
+                Color::RGB(160, 140, 120),
+            );
+        }
+    }
+
+    pub fn tick(&mut self, p: &Physics, time_in_ms: f32, tick_resolution_in_ms: f32, map: &PointList) {
+        p.tick(time_in_ms, tick_resolution_in_ms, &mut self.entities);
+
+        for (e, piece) in self.entities.iter_mut().zip(self.pieces.iter_mut()) {
+            piece.age += time_in_ms / 1000.0;
+            let rotated = TransformationMatrix::rotate(e.orientation).transform(&piece.half_extent);
+            for end in [e.position + rotated, e.position - rotated] {
+                let (height, normal) = match map.surface_at(end.x) {
+                    Some(surface) => surface,
+                    None => continue,
+                };
+                let depth = (end.y - height) * -normal.y;
+                if depth <= 0.0 {
+                    continue;
+                }
+                e.position = e.position + normal * depth;
+                let normal_speed = e.direction.x * normal.x + e.direction.y * normal.y;
+                if normal_speed < 0.0 {
+                    let tangential = e.direction - normal * normal_speed;
+                    e.direction = tangential * PIECE_FRICTION - normal * (normal_speed * PIECE_RESTITUTION);
+                    e.spin *= PIECE_FRICTION;
+                }
+            }
+        }
+
+        // Drop everything that faded out, keeping both lists in sync
+        let mut idx = 0;
+        while idx < self.pieces.len() {
+            if self.pieces[idx].age >= self.pieces[idx].lifetime {
+                self.pieces.swap_remove(idx);
+                self.entities.swap_remove(idx);
+            } else {
+                idx += 1;
+            }
+        }
+    }
+
+    // Through the camera, from world into screen pixels
+    pub fn render(&self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, camera: &TransformationMatrix) {
+        for (e, piece) in self.entities.iter().zip(self.pieces.iter()) {
+            // Fade towards the black background, like a phosphor screen
+            let brightness = (1.0 - piece.age / piece.lifetime).clamp(0.0, 1.0);
+            let color = Color::RGB(
+                (piece.color.r as f32 * brightness) as u8,
+                (piece.color.g as f32 * brightness) as u8,
+                (piece.color.b as f32 * brightness) as u8,
+            );
+            let rotated = TransformationMatrix::rotate(e.orientation).transform(&piece.half_extent);
+            let (from, to) = (camera.transform(&(e.position - rotated)), camera.transform(&(e.position + rotated)));
+            draw::draw_line(canvas, &from, &to, color).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color;
+
+    use crate::map::PointList;
+    use crate::vecmath::Vec2d;
+
+    use super::super::Physics;
+    use super::{ParticleSystem, DUST_RATE, EXHAUST_RATE};
+
+    fn flat_ground() -> PointList {
+        PointList::from_points(vec![Vec2d::new(0.0, 500.0), Vec2d::new(800.0, 500.0)])
+    }
+
+    #[test]
+    fn explosion_splits_parts_into_lines() {
+        let mut particles = ParticleSystem::new();
+        let square = vec![
+            Vec2d::new(0.0, 0.0),
+            Vec2d::new(1.0, 0.0),
+            Vec2d::new(1.0, 1.0),
+            Vec2d::new(0.0, 1.0),
+        ];
+        let triangle = vec![Vec2d::new(0.0, 0.0), Vec2d::new(1.0, 0.0), Vec2d::new(0.0, 1.0)];
+        particles.explode(&[square, triangle], Vec2d::new(0.5, 0.5), Vec2d::default());
+        // 7 lines plus the sparks
+        assert_eq!(particles.pieces.len(), 7 + 24);
+    }
+
+    #[test]
+    fn pieces_do_not_fall_through_the_ground() {
+        let mut particles = ParticleSystem::new();
+        let map = flat_ground();
+        particles.spawn(
+            Vec2d::new(390.0, 400.0),
+            Vec2d::new(410.0, 400.0),
+            Vec2d::new(0.0, 20.0),
+            1.0,
+            100.0,
+            Color::RGB(255, 255, 255),
+        );
+        for _ in 0..200 {
+            particles.tick(&Physics::default(), 50.0, 10.0, &map);
+        }
+        assert!(particles.entities[0].position.y <= 500.0);
+        assert!(particles.entities[0].position.y > 480.0);
+    }
+
+    #[test]
+    fn exhaust_follows_throttle_and_direction() {
+        let mut particles = ParticleSystem::new();
+        particles.emit_exhaust(Vec2d::default(), Vec2d::new(0.0, 1.0), Vec2d::default(), 0.0, 1.0);
+        assert!(particles.is_empty());
+
+        particles.emit_exhaust(Vec2d::default(), Vec2d::new(0.0, 1.0), Vec2d::default(), 1.0, 1.0);
+        assert_eq!(particles.pieces.len(), EXHAUST_RATE as usize);
+        for e in particles.entities.iter() {
+            assert!(e.direction.y > e.direction.x.abs());
+        }
+
+        let mut half = ParticleSystem::new();
+        half.emit_exhaust(Vec2d::default(), Vec2d::new(0.0, 1.0), Vec2d::default(), 0.5, 1.0);
+        assert_eq!(half.pieces.len(), EXHAUST_RATE as usize / 2);
+    }
+
+    #[test]
+    fn dust_sprays_along_the_slope() {
+        let mut particles = ParticleSystem::new();
+        let slope = Vec2d::new(1.0, -1.0).normalized();
+        particles.emit_dust(Vec2d::new(100.0, 100.0), slope, Vec2d::new(0.0, 1.0), 1.0, 1.0);
+        assert_eq!(particles.pieces.len(), DUST_RATE as usize);
+        let normal = Vec2d::new(slope.y, -slope.x);
+        for e in particles.entities.iter() {
+            let along = (e.direction.x * slope.x + e.direction.y * slope.y).abs();
+            let up = e.direction.x * normal.x + e.direction.y * normal.y;
+            assert!(along > up && up > 0.0);
+        }
+    }
+
+    #[test]
+    fn pieces_fade_out() {
+        let mut particles = ParticleSystem::new();
+        let map = flat_ground();
+        particles.spawn(
+            Vec2d::new(390.0, 100.0),
+            Vec2d::new(410.0, 100.0),
+            Vec2d::default(),
+            0.0,
+            1.0,
+            Color::RGB(255, 255, 255),
+        );
+        particles.tick(&Physics::default(), 500.0, 10.0, &map);
+        assert_eq!(particles.pieces.len(), 1);
+        particles.tick(&Physics::default(), 500.0, 10.0, &map);
+        assert_eq!(particles.pieces.len(), 0);
+    }
+}
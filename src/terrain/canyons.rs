@@ -0,0 +1,50 @@
+use rand::prelude::*;
+
+use super::{sample_with_pads, seeded, TerrainGenerator};
+use crate::vecmath::Vec2d;
+
+// A high plateau cut by steep walled canyons with flat floors
+pub struct Canyons {
+    pub count: usize,
+    pub width: f32,     // at the top, in pixel
+    pub depth: f32,     // as part of max_y
+    pub wall: f32,      // horizontal extent of a wall, as part of the width
+    pub plateau: f32,   // height of the plateau, as part of max_y
+    pub roughness: f32, // small height variation, in pixel
+}
+
+impl Canyons {
+    pub fn default() -> Self {
+        Canyons {
+            count: 2,
+            width: 120.0,
+            depth: 0.7,
+            wall: 0.2,
+            plateau: 0.9,
+            roughness: 6.0,
+        }
+    }
+}
+
+impl TerrainGenerator for Canyons {
+    fn generate(&self, max_x: f32, max_y: f32, seed: u64) -> Vec<Vec2d> {
+        let mut rng = seeded(seed);
+        // One canyon per equally wide slot, so they never merge
+        let slot = max_x / self.count.max(1) as f32;
+        let half = (self.width / 2.0).min(slot / 2.0);
+        let centers: Vec<f32> = (0..self.count)
+            .map(|idx| slot * idx as f32 + rng.gen_range(half..=(slot - half).max(half)))
+            .collect();
+        let bumps: Vec<f32> = (0..((max_x / 40.0) as usize + 2)).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+        let wall = (self.width * self.wall).max(1.0);
+
+        sample_with_pads(&mut rng, max_x, max_y, |x| {
+            let cut = centers
+                .iter()
+                .map(|c| ((half - (x - c).abs()) / wall).clamp(0.0, 1.0))
+                .fold(0.0, f32::max);
+            let bump = bumps[((x / 40.0) as usize).min(bumps.len() - 1)];
+            max_y * (self.plateau - self.depth * cut) + self.roughness * bump
+        })
+    }
+}
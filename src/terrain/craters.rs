@@ -0,0 +1,73 @@
+use std::f32::consts::TAU;
+
+use rand::prelude::*;
+
+use super::{sample_with_pads, seeded, TerrainGenerator};
+use crate::vecmath::Vec2d;
+
+// A gently rolling plain with impact craters pressed into it. Each crater is
+// a parabolic bowl with a raised rim, bigger craters are deeper.
+pub struct CrateredPlains {
+    pub count: usize,
+    pub min_radius: f32, // in pixel
+    pub max_radius: f32, // in pixel
+    pub depth: f32,      // of the biggest crater, in pixel
+    pub level: f32,      // height of the plain, as part of max_y
+    pub undulation: f32, // height of the rolling, in pixel
+}
+
+impl CrateredPlains {
+    pub fn default() -> Self {
+        CrateredPlains {
+            count: 6,
+            min_radius: 20.0,
+            max_radius: 70.0,
+            depth: 60.0,
+            level: 0.5,
+            undulation: 15.0,
+        }
+    }
+}
+
+struct Crater {
+    center: f32,
+    radius: f32,
+    depth: f32,
+}
+
+impl Crater {
+    fn offset(&self, x: f32) -> f32 {
+        let u = (x - self.center).abs() / self.radius;
+        let rim = 0.25 * self.depth;
+        if u < 1.0 {
+            -self.depth * (1.0 - u * u) + rim * u * u
+        } else if u < 1.5 {
+            rim * (1.0 - (u - 1.0) / 0.5).powi(2)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl TerrainGenerator for CrateredPlains {
+    fn generate(&self, max_x: f32, max_y: f32, seed: u64) -> Vec<Vec2d> {
+        let mut rng = seeded(seed);
+        let phases: [f32; 2] = [rng.gen_range(0.0..TAU), rng.gen_range(0.0..TAU)];
+        let craters: Vec<Crater> = (0..self.count)
+            .map(|_| {
+                let radius = rng.gen_range(self.min_radius..=self.max_radius.max(self.min_radius));
+                Crater {
+                    center: rng.gen_range(0.0..=max_x),
+                    radius,
+                    depth: self.depth * radius / self.max_radius,
+                }
+            })
+            .collect();
+
+        sample_with_pads(&mut rng, max_x, max_y, |x| {
+            let rolling = (x / 130.0 + phases[0]).sin() * 0.6 + (x / 47.0 + phases[1]).sin() * 0.4;
+            let craters: f32 = craters.iter().map(|c| c.offset(x)).sum();
+            max_y * self.level + self.undulation * rolling + craters
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::map::{PointList, X_LANDING_AREA_SIZE};
+use crate::vecmath::Vec2d;
+
+// Coarser levels after the finest, each may be off twice as far
+const LEVELS: usize = 5;
+// How far the first coarse level may be off the finest, in pixel
+const BASE_TOLERANCE: f32 = 0.5;
+// Largest error a drawn level may show, in screen pixel
+const MAX_SCREEN_ERROR: f32 = 0.5;
+// Times every sloped segment of the map is split for the finest level
+const REFINEMENTS: usize = 2;
+// How far a new midpoint may move up or down, as part of its segment's
+// length
+const DISPLACEMENT: f32 = 0.1;
+
+// The terrain at several levels of detail. Level 0 is the map with every
+// sloped segment split at a displaced midpoint a few times, so there is
+// more to see than the map has once the camera is close. Every further
+// level is simplified from it to within its tolerance. Flat ground is
+// never split and pad edges are kept on all levels, so pads stay exactly
+// flat.
+pub struct TerrainLod {
+    levels: Vec<Vec<Vec2d>>,
+}
+
+impl TerrainLod {
+    // The same map always gets the same detail
+    pub fn new(map: &PointList) -> Self {
+        let mut rng = StdRng::seed_from_u64(points_hash(map.get_values()));
+        let mut finest = map.get_values().clone();
+        for _ in 0..REFINEMENTS {
+            finest = refine(&finest, &mut rng);
+        }
+        let mut levels: Vec<Vec<Vec2d>> = (1..=LEVELS).map(|level| simplify(&finest, Self::tolerance(level))).collect();
+        levels.insert(0, finest);
+        TerrainLod { levels }
+    }
+
+    // Furthest a level is off the finest, in world pixel
+    pub fn tolerance(level: usize) -> f32 {
+        if level == 0 {
+            0.0
+        } else {
+            BASE_TOLERANCE * 2f32.powi(level as i32 - 1)
+        }
+    }
+
+    // The coarsest level that still looks exact at this zoom, the closer
+    // the camera gets the finer it is
+    pub fn level_for(&self, zoom: f32) -> usize {
+        (1..self.levels.len())
+            .take_while(|level| Self::tolerance(*level) * zoom <= MAX_SCREEN_ERROR)
+            .last()
+            .unwrap_or(0)
+    }
+
+    pub fn level(&self, level: usize) -> &[Vec2d] {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+
+    // The points of a level between both x, with one more on either side
+    // so the polyline reaches over the whole range
+    pub fn between(&self, level: usize, from: f32, to: f32) -> &[Vec2d] {
+        let points = self.level(level);
+        let start = points.partition_point(|p| p.x < from).saturating_sub(1);
+        let end = (points.partition_point(|p| p.x <= to) + 1).min(points.len());
+        &points[start..end.max(start)]
+    }
+
+    // Collision always tests against the full detail, which is what the
+    // camera shows once it is close to the ground
+    pub fn finest_near(&self, from: f32, to: f32) -> &[Vec2d] {
+        self.between(0, from, to)
+    }
+}
+
+// Splits every sloped segment at its midpoint, moved up or down by up to
+// DISPLACEMENT of the segment's length
+fn refine(points: &[Vec2d], rng: &mut StdRng) -> Vec<Vec2d> {
+    let mut refined = Vec::with_capacity(points.len() * 2);
+    for w in points.windows(2) {
+        refined.push(w[0]);
+        if w[0].y != w[1].y {
+            let offset = rng.gen_range(-DISPLACEMENT..=DISPLACEMENT) * (w[1] - w[0]).len();
+            refined.push(Vec2d::new((w[0].x + w[1].x) / 2.0, (w[0].y + w[1].y) / 2.0 + offset));
+        }
+    }
+    refined.extend(points.last());
+    refined
+}
+
+// FNV-1a over the bits of every coordinate
+fn points_hash(points: &[Vec2d]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for p in points {
+        for byte in p.x.to_bits().to_le_bytes().into_iter().chain(p.y.to_bits().to_le_bytes()) {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+// Douglas-Peucker between the pad edges, which are always kept
+fn simplify(points: &[Vec2d], tolerance: f32) -> Vec<Vec2d> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    for (idx, w) in points.windows(2).enumerate() {
+        if w[0].y == w[1].y && (w[1].x - w[0].x - X_LANDING_AREA_SIZE).abs() < 1e-3 {
+            keep[idx] = true;
+            keep[idx + 1] = true;
+        }
+    }
+
+    let fixed: Vec<usize> = (0..points.len()).filter(|idx| keep[*idx]).collect();
+    let mut stack: Vec<(usize, usize)> = fixed.windows(2).map(|w| (w[0], w[1])).collect();
+    while let Some((first, last)) = stack.pop() {
+        let farthest = (first + 1..last)
+            .map(|idx| (idx, distance_to_segment(points[idx], points[first], points[last])))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        if let Some((idx, distance)) = farthest {
+            if distance > tolerance {
+                keep[idx] = true;
+                stack.push((first, idx));
+                stack.push((idx, last));
+            }
+        }
+    }
+    points.iter().zip(keep).filter(|(_, keep)| *keep).map(|(p, _)| *p).collect()
+}
+
+fn distance_to_segment(p: Vec2d, a: Vec2d, b: Vec2d) -> f32 {
+    let ab = b - a;
+    let length = ab.x * ab.x + ab.y * ab.y;
+    if length == 0.0 {
+        return (p - a).len();
+    }
+    let t = (((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / length).clamp(0.0, 1.0);
+    (p - (a + ab * t)).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::level::Pad;
+    use crate::map::PointList;
+    use crate::terrain::lod::*;
+
+    fn map() -> PointList {
+        let mut map = PointList::from_seed(800.0, 200.0, 5, &Config::default().terrain);
+        map.set_window_height(600.0);
+        map
+    }
+
+    // Off a segment of the map by at most the displacement of its own
+    // midpoint plus half of it for the one after
+    #[test]
+    fn the_finest_level_refines_the_map() {
+        let map = map();
+        let lod = TerrainLod::new(&map);
+        let finest = lod.level(0);
+        assert!(finest.len() > 3 * map.get_values().len());
+        assert!(finest.windows(2).all(|w| w[0].x <= w[1].x));
+        for w in map.get_values().windows(2) {
+            let bound = 1.5 * DISPLACEMENT * (w[1] - w[0]).len() + 1e-3;
+            for p in finest.iter().filter(|p| p.x >= w[0].x && p.x <= w[1].x) {
+                assert!(distance_to_segment(*p, w[0], w[1]) <= bound, "{:?} off {:?}", p, w);
+            }
+        }
+        assert_eq!(Pad::landing_areas(&PointList::from_points(finest.to_vec())), Pad::landing_areas(&map));
+        assert_eq!(TerrainLod::new(&map).level(0), finest);
+    }
+
+    #[test]
+    fn coarse_levels_stay_close_and_keep_pads() {
+        let map = map();
+        let lod = TerrainLod::new(&map);
+        let pads = Pad::landing_areas(&map);
+        for level in 1..=LEVELS {
+            let points = lod.level(level);
+            assert!(points.len() <= lod.level(level - 1).len());
+            for p in lod.level(0) {
+                let off = points
+                    .windows(2)
+                    .map(|w| distance_to_segment(*p, w[0], w[1]))
+                    .fold(f32::MAX, f32::min);
+                assert!(off <= TerrainLod::tolerance(level) + 1e-3, "level {} off by {}", level, off);
+            }
+            let coarse = PointList::from_points(points.to_vec());
+            assert_eq!(Pad::landing_areas(&coarse), pads);
+        }
+        assert!(lod.level(LEVELS).len() < map.get_values().len());
+    }
+
+    #[test]
+    fn zooming_in_picks_finer_levels() {
+        let lod = TerrainLod::new(&map());
+        assert!(lod.level_for(0.25) > lod.level_for(1.0));
+        assert!(lod.level_for(1.0) > lod.level_for(4.0));
+        assert_eq!(lod.level_for(4.0), 0);
+    }
+
+    #[test]
+    fn ranges_reach_over_their_ends() {
+        let lod = TerrainLod::new(&map());
+        let points = lod.finest_near(100.0, 120.0);
+        assert!(points.first().unwrap().x <= 100.0);
+        assert!(points.last().unwrap().x >= 120.0);
+        assert!(points.len() < lod.level(0).len());
+        assert_eq!(lod.between(0, -50.0, 1000.0), lod.level(0));
+    }
+}
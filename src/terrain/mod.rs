@@ -0,0 +1,170 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::config::TerrainConfig;
+use crate::map::{self, X_LANDING_AREA_SIZE};
+use crate::vecmath::Vec2d;
+
+mod canyons;
+mod craters;
+mod lod;
+mod noise;
+mod pads;
+
+pub use canyons::Canyons;
+pub use craters::CrateredPlains;
+pub use lod::TerrainLod;
+pub use noise::NoiseRidges;
+pub use pads::{generate_checked, FuelBudget, PadRules};
+
+// Number of landing pads the generators build into their terrain
+pub(crate) const PADS: usize = 2;
+// Distance between terrain samples, in pixel
+const SAMPLE_STEP: f32 = 8.0;
+
+// Builds the terrain of a level. Points go from x = 0 to max_x, sorted by
+// x, with heights between 0 and max_y measured upwards. Landing pads are
+// flat segments X_LANDING_AREA_SIZE wide. The same seed always gives the
+// same terrain.
+pub trait TerrainGenerator {
+    fn generate(&self, max_x: f32, max_y: f32, seed: u64) -> Vec<Vec2d>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TerrainStyle {
+    Midpoint,
+    Ridges,
+    Craters,
+    Canyons,
+}
+
+impl TerrainStyle {
+    // The generator with its default parameters, the config roughness
+    // scales the height variation of all of them
+    pub fn generator(&self, terrain: &TerrainConfig) -> Box<dyn TerrainGenerator> {
+        let roughness = terrain.y_max_delta / 200.0;
+        match self {
+            TerrainStyle::Midpoint => Box::new(MidpointDisplacement::new(*terrain)),
+            TerrainStyle::Ridges => Box::new(NoiseRidges {
+                amplitude: 0.8 * roughness,
+                ..NoiseRidges::default()
+            }),
+            TerrainStyle::Craters => Box::new(CrateredPlains {
+                depth: 60.0 * roughness,
+                ..CrateredPlains::default()
+            }),
+            TerrainStyle::Canyons => Box::new(Canyons {
+                depth: 0.7 * roughness.min(1.2),
+                ..Canyons::default()
+            }),
+        }
+    }
+}
+
+// The original generator, see map::midpoint_displacement
+pub struct MidpointDisplacement {
+    terrain: TerrainConfig,
+}
+
+impl MidpointDisplacement {
+    pub fn new(terrain: TerrainConfig) -> Self {
+        MidpointDisplacement { terrain }
+    }
+}
+
+impl TerrainGenerator for MidpointDisplacement {
+    fn generate(&self, max_x: f32, max_y: f32, seed: u64) -> Vec<Vec2d> {
+        let mut points = map::midpoint_displacement(max_x, max_y, seed, &self.terrain);
+        points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+        points
+    }
+}
+
+// Samples a height function every SAMPLE_STEP pixels and flattens PADS
+// landing pads into it, one in each equally wide slot of the map. Heights
+// are clamped into 0..=max_y.
+fn sample_with_pads(rng: &mut StdRng, max_x: f32, max_y: f32, height: impl Fn(f32) -> f32) -> Vec<Vec2d> {
+    let slot = max_x / PADS as f32;
+    let margin = SAMPLE_STEP;
+    let mut pads: Vec<f32> = Vec::new();
+    if slot > X_LANDING_AREA_SIZE + 2.0 * margin {
+        for idx in 0..PADS {
+            let start = slot * idx as f32 + margin;
+            let end = slot * (idx + 1) as f32 - margin - X_LANDING_AREA_SIZE;
+            pads.push(rng.gen_range(start..=end));
+        }
+    }
+
+    let clamped = |x: f32| height(x).clamp(0.0, max_y);
+    let mut points = Vec::new();
+    let mut x = 0.0;
+    while x < max_x {
+        if !pads.iter().any(|p| x > *p && x < *p + X_LANDING_AREA_SIZE) {
+            points.push(Vec2d::new(x, clamped(x)));
+        }
+        x += SAMPLE_STEP;
+    }
+    points.push(Vec2d::new(max_x, clamped(max_x)));
+    for pad in pads {
+        let y = clamped(pad);
+        points.retain(|p| p.x != pad && p.x != pad + X_LANDING_AREA_SIZE);
+        points.push(Vec2d::new(pad, y));
+        points.push(Vec2d::new(pad + X_LANDING_AREA_SIZE, y));
+    }
+    points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+    points
+}
+
+fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::map::X_LANDING_AREA_SIZE;
+    use crate::terrain::*;
+
+    const STYLES: [TerrainStyle; 4] = [
+        TerrainStyle::Midpoint,
+        TerrainStyle::Ridges,
+        TerrainStyle::Craters,
+        TerrainStyle::Canyons,
+    ];
+
+    fn pads(points: &[Vec2d]) -> usize {
+        points
+            .windows(2)
+            .filter(|w| w[0].y == w[1].y && (w[1].x - w[0].x - X_LANDING_AREA_SIZE).abs() < 1e-3)
+            .count()
+    }
+
+    #[test]
+    fn every_style_builds_a_valid_terrain() {
+        let terrain = Config::default().terrain;
+        for style in STYLES {
+            for seed in 0..20 {
+                let generator = style.generator(&terrain);
+                let points = generator.generate(800.0, 200.0, seed);
+                assert_eq!(points.first().unwrap().x, 0.0, "{:?}", style);
+                assert_eq!(points.last().unwrap().x, 800.0, "{:?}", style);
+                assert!(points.windows(2).all(|w| w[0].x <= w[1].x), "{:?}", style);
+                assert!(pads(&points) >= PADS, "{:?} seed {}", style, seed);
+                assert_eq!(points, generator.generate(800.0, 200.0, seed));
+                if style != TerrainStyle::Midpoint {
+                    assert_eq!(pads(&points), PADS, "{:?} seed {}", style, seed);
+                    assert!(points.iter().all(|p| p.y >= 0.0 && p.y <= 200.0), "{:?}", style);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_give_different_terrain() {
+        let terrain = Config::default().terrain;
+        for style in STYLES {
+            let generator = style.generator(&terrain);
+            assert_ne!(generator.generate(800.0, 200.0, 1), generator.generate(800.0, 200.0, 2));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use rand::prelude::*;
+
+use super::{sample_with_pads, seeded, TerrainGenerator};
+use crate::vecmath::Vec2d;
+
+// Ridged gradient noise: several octaves of 1D Perlin noise folded at zero,
+// which gives sharp crests and rounded valleys.
+pub struct NoiseRidges {
+    pub wavelength: f32, // of the first octave, in pixel
+    pub octaves: u32,
+    pub persistence: f32, // amplitude factor from one octave to the next
+    pub base: f32,        // lowest height, as part of max_y
+    pub amplitude: f32,   // height of the ridges, as part of max_y
+}
+
+impl NoiseRidges {
+    pub fn default() -> Self {
+        NoiseRidges {
+            wavelength: 250.0,
+            octaves: 4,
+            persistence: 0.5,
+            base: 0.1,
+            amplitude: 0.8,
+        }
+    }
+}
+
+impl TerrainGenerator for NoiseRidges {
+    fn generate(&self, max_x: f32, max_y: f32, seed: u64) -> Vec<Vec2d> {
+        let mut rng = seeded(seed);
+        let octaves: Vec<Octave> = (0..self.octaves.max(1))
+            .map(|idx| {
+                let wavelength = self.wavelength / 2f32.powi(idx as i32);
+                Octave::new(&mut rng, wavelength, max_x, self.persistence.powi(idx as i32))
+            })
+            .collect();
+        let total: f32 = octaves.iter().map(|o| o.weight).sum();
+
+        sample_with_pads(&mut rng, max_x, max_y, |x| {
+            let ridges: f32 = octaves
+                .iter()
+                .map(|o| o.weight * (1.0 - (2.0 * o.value(x)).abs().min(1.0)).powi(2))
+                .sum();
+            max_y * (self.base + self.amplitude * ridges / total)
+        })
+    }
+}
+
+struct Octave {
+    wavelength: f32,
+    weight: f32,
+    gradients: Vec<f32>,
+}
+
+impl Octave {
+    fn new(rng: &mut impl Rng, wavelength: f32, max_x: f32, weight: f32) -> Self {
+        let cells = (max_x / wavelength).ceil() as usize + 2;
+        Octave {
+            wavelength,
+            weight,
+            gradients: (0..cells).map(|_| rng.gen_range(-1.0..=1.0)).collect(),
+        }
+    }
+
+    // Perlin noise, roughly in -0.5..=0.5
+    fn value(&self, x: f32) -> f32 {
+        let x = (x / self.wavelength).max(0.0);
+        let cell = (x.floor() as usize).min(self.gradients.len() - 2);
+        let t = x - cell as f32;
+        let a = self.gradients[cell] * t;
+        let b = self.gradients[cell + 1] * (t - 1.0);
+        let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        a + (b - a) * fade
+    }
+}
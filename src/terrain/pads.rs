@@ -0,0 +1,193 @@
+use crate::level::{fuel_to_reach, Pad};
+use crate::map::{PointList, X_LANDING_AREA_SIZE};
+use crate::terrain::PADS;
+use crate::vecmath::Vec2d;
+
+// Maps generated for a seed before the last one gets its pads carved in
+const MAX_TRIES: u64 = 8;
+// Spreads the seeds of the rerolls, any odd number does
+const REROLL_STEP: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// What every generated map has to offer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PadRules {
+    pub count: usize,        // distinct pads at least
+    pub min_spacing: f32,    // terrain between two pads, in pixel
+    pub corridor_width: f32, // beside each pad, in pixel
+    pub corridor_slope: f32, // the terrain there rises no steeper from the pad
+}
+
+impl Default for PadRules {
+    fn default() -> Self {
+        PadRules {
+            count: PADS,
+            min_spacing: 120.0,
+            corridor_width: 30.0,
+            corridor_slope: 2.0,
+        }
+    }
+}
+
+// Where the lander starts, at rest, and what it can do
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FuelBudget {
+    pub spawn: Vec2d,
+    pub gravity: f32,
+    pub thrust: f32,
+    pub fuel: f32, // in seconds at full throttle
+}
+
+// Pads that count are clear to approach and keep their distance to the
+// one before, from left to right. At least one of them has to be within
+// the fuel budget. Maps are in window coordinates, y grows downwards.
+pub fn check(map: &PointList, rules: &PadRules, budget: &FuelBudget) -> Result<(), String> {
+    let mut spaced: Vec<Pad> = Vec::new();
+    for pad in Pad::landing_areas(map) {
+        let apart = spaced.last().is_none_or(|last| pad.left - last.right >= rules.min_spacing);
+        if apart && corridor_clear(map, &pad, rules) {
+            spaced.push(pad);
+        }
+    }
+    if spaced.len() < rules.count {
+        return Err(format!(
+            "{} of {} pads are spaced and clear to approach",
+            spaced.len(),
+            rules.count
+        ));
+    }
+    let reachable = |pad: &Pad| {
+        fuel_to_reach(map, budget.spawn, Vec2d::default(), pad, budget.gravity, budget.thrust) <= budget.fuel
+    };
+    if rules.count > 0 && !spaced.iter().any(reachable) {
+        return Err(format!("no pad can be reached with {} s of fuel", budget.fuel));
+    }
+    Ok(())
+}
+
+// Generates maps until one passes the check. The seeds of the rerolls
+// follow from the seed, so it still always gives the same map. If none
+// passes, the pads get carved into the last one.
+pub fn generate_checked(
+    seed: u64,
+    rules: &PadRules,
+    budget: &FuelBudget,
+    generate: impl Fn(u64) -> PointList,
+) -> PointList {
+    let mut attempt: u64 = 0;
+    loop {
+        let map = generate(seed.wrapping_add(attempt.wrapping_mul(REROLL_STEP)));
+        if check(&map, rules, budget).is_ok() {
+            return map;
+        }
+        attempt += 1;
+        if attempt == MAX_TRIES {
+            return repair(map, rules, budget);
+        }
+    }
+}
+
+// One pad in each equally wide slot of the map, the one in the slot of
+// the spawn right below it, as no pad is cheaper to reach
+fn repair(mut map: PointList, rules: &PadRules, budget: &FuelBudget) -> PointList {
+    let width = map.get_values().last().map_or(0.0, |p| p.x);
+    let slot = width / rules.count.max(1) as f32;
+    for idx in 0..rules.count {
+        let center = if (budget.spawn.x / slot) as usize == idx {
+            budget.spawn.x
+        } else {
+            slot * (idx as f32 + 0.5)
+        };
+        let left = (center - X_LANDING_AREA_SIZE / 2.0).clamp(0.0, (width - X_LANDING_AREA_SIZE).max(0.0));
+        map.carve_pad(left, rules.corridor_width, rules.corridor_slope);
+    }
+    map
+}
+
+// No terrain beside the pad rises above the corridor slope, y grows
+// downwards
+fn corridor_clear(map: &PointList, pad: &Pad, rules: &PadRules) -> bool {
+    let Some((y, _)) = map.surface_at(pad.left) else {
+        return false;
+    };
+    map.get_values().iter().all(|p| {
+        let distance = if p.x < pad.left { pad.left - p.x } else { p.x - pad.right };
+        distance > rules.corridor_width || y - p.y <= rules.corridor_slope * distance.max(0.0) + 1e-3
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::level::Pad;
+    use crate::map::PointList;
+    use crate::planet::{Body, BODIES};
+    use crate::simulation::{World, SPAWN_POSITION};
+    use crate::terrain::pads::*;
+    use crate::vecmath::Vec2d;
+
+    fn budget(fuel: f32) -> FuelBudget {
+        let config = Config::default();
+        FuelBudget {
+            spawn: SPAWN_POSITION,
+            gravity: config.physics.gravity,
+            thrust: config.lander.thrust,
+            fuel,
+        }
+    }
+
+    // A pad at 500 in a gorge 100 pixel deep
+    fn gorge() -> PointList {
+        PointList::from_points(vec![
+            Vec2d::new(0.0, 450.0),
+            Vec2d::new(480.0, 450.0),
+            Vec2d::new(500.0, 550.0),
+            Vec2d::new(540.0, 550.0),
+            Vec2d::new(560.0, 450.0),
+            Vec2d::new(800.0, 500.0),
+        ])
+    }
+
+    #[test]
+    fn pads_in_gorges_do_not_count() {
+        let rules = PadRules {
+            count: 1,
+            ..PadRules::default()
+        };
+        assert!(check(&gorge(), &rules, &budget(20.0)).is_err());
+
+        let mut map = gorge();
+        map.carve_pad(500.0, rules.corridor_width, rules.corridor_slope);
+        assert_eq!(Pad::landing_areas(&map).len(), 1);
+        assert_eq!(check(&map, &rules, &budget(20.0)), Ok(()));
+        assert!(check(&map, &rules, &budget(0.1)).is_err());
+    }
+
+    #[test]
+    fn failing_maps_get_repaired() {
+        let rules = PadRules::default();
+        let map = generate_checked(1, &rules, &budget(20.0), |_| gorge());
+        assert_eq!(check(&map, &rules, &budget(20.0)), Ok(()));
+        assert!(Pad::landing_areas(&map).iter().any(|pad| pad.contains(SPAWN_POSITION.x)));
+    }
+
+    #[test]
+    fn generated_maps_keep_the_rules() {
+        let rules = PadRules::default();
+        for body in BODIES {
+            for seed in 0..30 {
+                let config = body.apply(Config::default());
+                let world = World::new(800, 600, seed, Config::default(), body);
+                let budget = FuelBudget {
+                    spawn: SPAWN_POSITION,
+                    gravity: config.physics.gravity,
+                    thrust: config.lander.thrust,
+                    fuel: config.lander.starting_fuel,
+                };
+                let checked = check(world.map(), &rules, &budget);
+                assert_eq!(checked, Ok(()), "{:?} seed {}", body, seed);
+            }
+        }
+        let seeded = |seed| World::new(800, 600, seed, Config::default(), Body::Moon).map().get_values().clone();
+        assert_eq!(seeded(3), seeded(3));
+    }
+}
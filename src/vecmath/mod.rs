@@ -2,7 +2,7 @@ use std::{arch::x86_64::_mm_sqrt_ps, f32::consts::PI};
 
 use crate::draw;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec2d {
     pub x: f32,
     pub y: f32,
@@ -13,6 +13,12 @@ pub struct TransformationMatrix {
     m: [[f32; 3]; 3],
 }
 
+impl Default for Vec2d {
+    fn default() -> Self {
+        Vec2d { x: 0.0, y: 0.0 }
+    }
+}
+
 impl Vec2d {
     pub const fn new(x: f32, y: f32) -> Self {
         Vec2d { x, y }
@@ -25,10 +31,6 @@ impl Vec2d {
         };
     }
 
-    pub fn default() -> Self {
-        Vec2d { x: 0.0, y: 0.0 }
-    }
-
     pub fn len(&self) -> f32 {
         return (self.x * self.x + self.y * self.y).sqrt();
     }
@@ -0,0 +1,135 @@
+use sdl2::event::Event;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::vecmath::Vec2d;
+use crate::{window_height, window_width};
+
+// The game is always laid out for window_width x window_height, the
+// virtual resolution. The viewport scales it uniformly into the real window
+// and centers it, the remaining bars stay black.
+pub struct Viewport {
+    width: u32,
+    height: u32,
+}
+
+impl Viewport {
+    pub fn new(width: u32, height: u32) -> Self {
+        Viewport {
+            width: width.max(1),
+            height: height.max(1),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        *self = Self::new(width, height);
+    }
+
+    pub fn scale(&self) -> f32 {
+        let x = self.width as f32 / window_width as f32;
+        let y = self.height as f32 / window_height as f32;
+        x.min(y)
+    }
+
+    // Top left corner of the game area in window pixels
+    pub fn offset(&self) -> Vec2d {
+        let scale = self.scale();
+        Vec2d::new(
+            (self.width as f32 - window_width as f32 * scale) / 2.0,
+            (self.height as f32 - window_height as f32 * scale) / 2.0,
+        )
+    }
+
+    // Moves mouse events from window pixels into the virtual resolution,
+    // other events are passed through
+    pub fn to_virtual(&self, event: Event) -> Event {
+        let mut event = event;
+        let scale = self.scale();
+        let offset = self.offset();
+        let convert = |x: &mut i32, y: &mut i32| {
+            *x = ((*x as f32 - offset.x) / scale).floor() as i32;
+            *y = ((*y as f32 - offset.y) / scale).floor() as i32;
+        };
+        match &mut event {
+            Event::MouseMotion { x, y, .. } => convert(x, y),
+            Event::MouseButtonDown { x, y, .. } => convert(x, y),
+            Event::MouseButtonUp { x, y, .. } => convert(x, y),
+            _ => (),
+        }
+        event
+    }
+
+    // Everything drawn afterwards is scaled and clipped to the game area.
+    // SDL applies the scale to the viewport as well, so it is given in
+    // scaled units.
+    pub fn apply(&self, canvas: &mut Canvas<Window>) -> Result<(), String> {
+        let scale = self.scale();
+        let offset = self.offset();
+        canvas.set_scale(scale, scale)?;
+        canvas.set_viewport(Rect::new(
+            (offset.x / scale).round() as i32,
+            (offset.y / scale).round() as i32,
+            window_width,
+            window_height,
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::event::Event;
+    use sdl2::mouse::MouseState;
+
+    use crate::vecmath::Vec2d;
+    use crate::viewport::Viewport;
+    use crate::{window_height, window_width};
+
+    #[test]
+    fn native_size_is_identity() {
+        let v = Viewport::new(window_width, window_height);
+        assert_eq!(v.scale(), 1.0);
+        assert_eq!(v.offset(), Vec2d::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn wide_windows_get_side_bars() {
+        let v = Viewport::new(window_width * 3, window_height * 2);
+        assert_eq!(v.scale(), 2.0);
+        assert_eq!(v.offset(), Vec2d::new(window_width as f32 / 2.0, 0.0));
+    }
+
+    #[test]
+    fn tall_windows_get_top_bars() {
+        let mut v = Viewport::new(window_width, window_height);
+        v.resize(window_width / 2, window_height);
+        assert_eq!(v.scale(), 0.5);
+        assert_eq!(v.offset(), Vec2d::new(0.0, window_height as f32 / 4.0));
+    }
+
+    #[test]
+    fn mouse_events_are_moved_into_the_game_area() {
+        let v = Viewport::new(window_width * 3, window_height * 2);
+        let event = Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: MouseState::from_sdl_state(0),
+            x: window_width as i32 / 2 + 100,
+            y: 50,
+            xrel: 0,
+            yrel: 0,
+        };
+        match v.to_virtual(event) {
+            Event::MouseMotion { x, y, .. } => assert_eq!((x, y), (50, 25)),
+            _ => panic!("not a mouse event"),
+        }
+    }
+
+    #[test]
+    fn empty_window_does_not_divide_by_zero() {
+        let v = Viewport::new(0, 0);
+        assert!(v.scale() > 0.0);
+    }
+}